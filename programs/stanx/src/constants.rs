@@ -5,15 +5,64 @@ pub const OUTCOME_NO_SEED: &[u8] = b"outcome_b";
 pub const ORDERBOOK_SEED: &[u8] = b"orderbook";
 pub const USER_STATS_SEED: &[u8] = b"user_stats";
 pub const ESCROW_SEED: &[u8] = b"escrow";
+pub const INCENTIVE_VAULT_SEED: &[u8] = b"incentive_vault";
+pub const SETTLEMENT_RECORD_SEED: &[u8] = b"settlement_record";
+pub const MARKET_REGISTRY_SEED: &[u8] = b"market_registry";
+// Byte offset of `Market.category` in the account data (discriminator + authority + market_id),
+// for `getProgramAccounts` memcmp filters on the little-endian u16.
+pub const MARKET_CATEGORY_OFFSET: usize = 8 + 32 + 4;
+// Byte offset of `Market.in_progress`, right after the category
+pub const MARKET_IN_PROGRESS_OFFSET: usize = MARKET_CATEGORY_OFFSET + 2;
 pub const MAX_ORDERS_PER_SIDE: usize = 32;
 pub const ORDERBOOK_GROWTH_BATCH: usize = 10;
 
-// Both outcome tokens and collateral have 6 decimals.
-// quantity (base units) × price (micro USDC per display token) must be divided by this
-// to get the collateral amount in micro USDC.
-pub const TOKEN_DECIMALS_SCALE: u64 = 1_000_000;
+// Upper bound on `max_iteration` for place_order/market_order so worst-case compute stays predictable.
+// Fills deeper than this need to be split across multiple transactions.
+pub const MAX_ITERATION_CAP: u64 = 32;
+
+// Outcome mints are always created with this many decimals.
+pub const OUTCOME_TOKEN_DECIMALS: u8 = 6;
+
+// `price` is collateral base units per whole outcome token (10^OUTCOME_TOKEN_DECIMALS base units).
+// quantity (base units) × price must be divided by this to get the collateral amount in base units,
+// see `Order::collateral_for` for the rounding rules.
+pub const TOKEN_DECIMALS_SCALE: u64 = 10u64.pow(OUTCOME_TOKEN_DECIMALS as u32);
+
+// Trading fee charged on a fill's seller proceeds, 0 = no trading fee yet.
+// Sellers whose UserStats is `fee_exempt` always pay 0.
+pub const TRADING_FEE_BPS: u16 = 0;
 
 // Minimum order size: 0.001 display tokens (1_000 base units).
 // Prevents quantity × price / TOKEN_DECIMALS_SCALE from truncating to zero.
 pub const MIN_ORDER_QUANTITY: u64 = 1_000;
 
+// `SettlementRecord::source` values
+pub const SETTLEMENT_SOURCE_AUTHORITY: u8 = 0;
+
+// Markets are listed in registry pages by id range: page = market_id / MARKETS_PER_REGISTRY_PAGE
+pub const MARKETS_PER_REGISTRY_PAGE: u32 = 256;
+
+// Minimum seconds between two update_metadata calls on a market, 0 disables the limit
+pub const METADATA_UPDATE_INTERVAL: i64 = 0;
+
+pub const CONFIG_SEED: &[u8] = b"config";
+// Size of the market creator allowlist in Config
+pub const MAX_MARKET_CREATORS: usize = 32;
+
+// Counterparties match_preview can return: 4-byte length + 32 bytes each within the
+// 1024-byte return data limit
+pub const MAX_PREVIEW_COUNTERPARTIES: usize = 31;
+
+pub const STAKE_SEED: &[u8] = b"stake";
+pub const STAKE_VAULT_SEED: &[u8] = b"stake_vault";
+// Collateral base units a maker must stake per market to reach priority tier 1, 2, 3.
+// Tiers only break ties between orders at the same price, see `OrderBook::sort_side`.
+pub const STAKE_TIER_THRESHOLDS: [u64; 3] = [100_000_000, 1_000_000_000, 10_000_000_000];
+
+pub const MARKET_SLUG_SEED: &[u8] = b"market_slug";
+// A slug is one PDA seed, so it can't be longer than a seed (32 bytes)
+pub const MAX_SLUG_LEN: usize = 32;
+
+// Terminal orders kept per orderbook in `OrderBook::closed_orders`, the oldest is overwritten
+// once it is full
+pub const CLOSED_ORDERS_CAPACITY: usize = 16;
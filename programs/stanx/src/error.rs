@@ -2,7 +2,8 @@ use anchor_lang::prelude::*;
 
 #[error_code]
 pub enum PredictionMarketError {
-    Invalid,
+    #[msg("Outcome token account does not match its YES/NO slot or is not owned by the user")]
+    OutcomeAccountMismatch,
     #[msg("Invalid settlement deadline")]
     InvalidSettlementDeadline,
     #[msg("Market already settled")]
@@ -63,4 +64,110 @@ pub enum PredictionMarketError {
     OrderTooSmall,
     #[msg("Order is fully filled and cannot be cancelled")]
     OrderFullyFilled,
+    #[msg("An order with this id is already resting on the book, use a different client nonce")]
+    OrderIdCollision,
+    #[msg("auto_mint only applies to market buys")]
+    AutoMintBuyOnly,
+    #[msg("Market collateral cap exceeded")]
+    CollateralCapExceeded,
+    #[msg("Market order would cross your own resting order placed in the last slot")]
+    WashTradeCooldown,
+    #[msg("Incentive vault must be provided while the market pays maker rewards")]
+    IncentiveVaultRequired,
+    #[msg("Outcome mint must be provided for restricted-transfer markets")]
+    OutcomeMintRequired,
+    #[msg("min_price must not be greater than max_price")]
+    InvalidPriceRange,
+    #[msg("A market with this id already exists")]
+    MarketAlreadyExists,
+    #[msg("Escrow or vault balance is lower than the amount owed, accounting has drifted")]
+    InsufficientEscrow,
+    #[msg("Outcome label must be at most 32 bytes")]
+    InvalidOutcomeLabel,
+    #[msg("Relisted order would cross the best bid, use place_order instead")]
+    OrderWouldCross,
+    #[msg("Scalar market bounds must satisfy low < high")]
+    InvalidScalarBounds,
+    #[msg("Scalar markets must be settled with a settlement value")]
+    SettlementValueRequired,
+    #[msg("Settlement record already written for this market")]
+    SettlementAlreadyRecorded,
+    #[msg("Reserved capacity must grow the orderbook and stay within MAX_ORDERS_PER_SIDE")]
+    InvalidOrderBookCapacity,
+    #[msg("Price bounds must satisfy 0 < min_price < max_price")]
+    InvalidPriceBounds,
+    #[msg("Order price is outside the market's price bounds")]
+    PriceOutOfBounds,
+    #[msg("Signer is neither the user nor their claim delegate")]
+    UnauthorizedClaimer,
+    #[msg("Cancel and place steps of replace_order must use the same user")]
+    ReplaceSignerMismatch,
+    #[msg("Template market belongs to another authority or uses a different collateral mint")]
+    InvalidTemplate,
+    #[msg("auto_mint and rest_remainder_at cannot be combined")]
+    ConflictingRemainderOptions,
+    #[msg("YES and NO outcome supplies diverged before settlement")]
+    SupplyInvariantViolated,
+    #[msg("Metadata was updated less than METADATA_UPDATE_INTERVAL seconds ago")]
+    MetadataUpdateTooFrequent,
+    #[msg("Market creator allowlist is full")]
+    CreatorAllowlistFull,
+    #[msg("Too many counterparties for one preview, lower max_iteration")]
+    PreviewTooLarge,
+    #[msg("Cancel resting orders before unstaking")]
+    StakeLockedByOrders,
+    #[msg("Stake must be withdrawn before the market can close")]
+    StakeOutstanding,
+    #[msg("Trading is closed ahead of the settlement deadline")]
+    TradingWindowClosed,
+    #[msg("Trading cutoff buffer cannot be negative")]
+    InvalidTradingCutoff,
+    #[msg("Token account or mint is not owned by the passed token program")]
+    TokenProgramMismatch,
+    #[msg("Slug must be 1-32 lowercase letters, digits or dashes")]
+    InvalidSlug,
+    #[msg("Slug is already used by another market")]
+    SlugTaken,
+    #[msg("Order is resting or no longer retained in closed orders")]
+    ClosedOrderNotRetained,
+    #[msg("Order quantity must be a multiple of the market lot size")]
+    InvalidLotSize,
+    #[msg("Cancelling the authority's orders needs its stats, the vault or escrow, its token account and the token program")]
+    RefundAccountsRequired,
+    #[msg("Minting this amount would overflow the outcome mint supply")]
+    SupplyOverflow,
+    #[msg("Order quantity is above the market maximum")]
+    OrderTooLarge,
+    #[msg("Only maker quotes can rest on this book, take liquidity with a market order instead")]
+    MakerOnlyBook,
+    #[msg("Another instruction on this market is still in progress")]
+    ReentrancyDetected,
+    #[msg("An order can only be transferred to a different owner")]
+    SameOrderOwner,
+    #[msg("Trading on this outcome is paused")]
+    SideTradingPaused,
+    #[msg("Collateral vault cannot cover the payout of this outcome")]
+    InsufficientCollateralForSettlement,
+    #[msg("Fee schedule needs a non-zero decay step and min_fee_bps <= base_fee_bps <= 10000")]
+    InvalidFeeSchedule,
+    #[msg("Every resting order's owner UserStats is needed to reset the orderbook")]
+    OrderBookResetIncomplete,
+    #[msg("Interest-bearing collateral is only accepted on a rebasing market")]
+    RebasingCollateralNotEnabled,
+    #[msg("Cancel and claim steps of cancel_and_claim must use the same user")]
+    CancelClaimUserMismatch,
+    #[msg("Orderbook market_id or next_order_id doesn't match the stored account")]
+    OrderBookStateMismatch,
+    #[msg("Seed collateral is below the configured minimum")]
+    SeedCollateralTooLow,
+    #[msg("Seeding a market needs the authority's collateral token account")]
+    SeedCollateralAccountRequired,
+    #[msg("Outcome mint authority was revoked at settlement, no more tokens can be minted")]
+    MintAuthorityRevoked,
+    #[msg("Market vault and escrow accounts must be distinct")]
+    HoldingAccountsCollide,
+    #[msg("Treasury balance must be withdrawn before the market can close")]
+    TreasuryNotWithdrawn,
+    #[msg("Market account matches neither the legacy nor the current layout")]
+    UnknownMarketLayout,
 }
@@ -11,6 +11,14 @@ pub struct MarketInitialized {
     pub outcome_yes_mint: Pubkey,
     pub outcome_no_mint: Pubkey,
     pub meta_data_url: String,
+    pub category: u16,
+    pub restricted_transfer: bool,
+    pub outcome_a_label: String,
+    pub outcome_b_label: String,
+    pub market_kind: MarketKind,
+    pub slug: String,
+    pub rebasing: bool,
+    pub seed_collateral: u64,
     pub timestamp: i64,
 }
 
@@ -26,7 +34,10 @@ pub struct TokensSplit {
 pub struct TokensMerged {
     pub market_id: u32,
     pub user: Pubkey,
-    pub amount: u64,
+    // YES+NO pairs burned
+    pub burned: u64,
+    // Collateral paid out for them, equal to `burned` while merges are fee-free
+    pub collateral_returned: u64,
     pub timestamp: i64,
 }
 
@@ -60,6 +71,9 @@ pub struct MarketOrderExecuted {
     pub initial_quantity: u64,
     pub filled_quantity: u64,
     pub orders_matched: u64,
+    // Effective fill price: collateral exchanged (fees included) × TOKEN_DECIMALS_SCALE / tokens
+    // exchanged, in the same units as order prices. 0 if nothing filled
+    pub avg_price: u64,
     pub timestamp: i64,
 }
 
@@ -89,6 +103,8 @@ pub struct MetadataUpdated {
     pub market_id: u32,
     pub authority: Pubkey,
     pub new_metadata_url: String,
+    pub outcome_a_label: String,
+    pub outcome_b_label: String,
     pub timestamp: i64,
 }
 
@@ -103,6 +119,7 @@ pub struct MarketClosed {
 pub struct WinningSideSet {
     pub market_id: u32,
     pub winning_outcome: WinningOutcome,
+    pub settlement_value: i64,
     pub authority: Pubkey,
     pub timestamp: i64,
 }
@@ -120,4 +137,371 @@ pub struct OrderMatched {
     pub price: u64,
     pub quantity: u64,
     pub timestamp: i64,
-}
\ No newline at end of file
+}
+
+#[event]
+pub struct UserStatsMigrated {
+    pub market_id: u32,
+    pub user: Pubkey,
+    pub payer: Pubkey,
+    pub old_space: u64,
+    pub new_space: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CollateralCapUpdated {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub max_collateral: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WashTradeGuardUpdated {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub enabled: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct IncentivesFunded {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub incentive_pool: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardRateUpdated {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub reward_rate: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct IncentivesWithdrawn {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MakerRewardAccrued {
+    pub market_id: u32,
+    pub order_id: u64,
+    pub maker: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UserSetup {
+    pub market_id: u32,
+    pub user: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OrderBookResized {
+    pub market_id: u32,
+    pub old_space: u64,
+    pub new_space: u64,
+    pub payer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RoundingModeUpdated {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub rounding_mode: RoundingMode,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OrderBookSettled {
+    pub market_id: u32,
+    pub orders_settled: u32,
+    pub orders_remaining: u32,
+    pub caller: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ClaimDelegateUpdated {
+    pub market_id: u32,
+    pub user: Pubkey,
+    pub delegate: Option<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeCollected {
+    pub market_id: u32,
+    pub payer: Pubkey,
+    pub amount: u64,
+    pub kind: FeeKind,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeesWithdrawn {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CreatorAllowlistUpdated {
+    pub creator: Pubkey,
+    pub allowed: bool,
+    pub admin: Pubkey,
+    // Allowlist size after the update, 0 means anyone can create markets
+    pub creators: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StakeUpdated {
+    pub market_id: u32,
+    pub user: Pubkey,
+    // Stake after the change
+    pub amount: u64,
+    pub tier: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CollateralDeposited {
+    pub market_id: u32,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub available_collateral: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CollateralWithdrawn {
+    pub market_id: u32,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub available_collateral: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokensDeposited {
+    pub market_id: u32,
+    pub user: Pubkey,
+    pub token_type: TokenType,
+    pub amount: u64,
+    // available_yes or available_no after the change
+    pub available: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokensWithdrawn {
+    pub market_id: u32,
+    pub user: Pubkey,
+    pub token_type: TokenType,
+    pub amount: u64,
+    // available_yes or available_no after the change
+    pub available: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TradingCutoffUpdated {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub trading_cutoff_buffer: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FundsSwept {
+    pub market_id: u32,
+    pub user: Pubkey,
+    // Collateral paid out to the user by force_settle_and_close
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StpPolicyUpdated {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub stp_policy: StpPolicy,
+    pub timestamp: i64,
+}
+
+// An incoming order reached a resting order of the same user, `quantity` is what was taken off
+// the resting order (CancelNewest takes nothing off it, the incoming remainder is cancelled)
+#[event]
+pub struct SelfTradePrevented {
+    pub market_id: u32,
+    pub user: Pubkey,
+    pub stp_policy: StpPolicy,
+    pub resting_order_id: u64,
+    pub quantity: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ExpiredOrdersPruned {
+    pub market_id: u32,
+    pub orders_pruned: u32,
+    pub orders_remaining: u32,
+    pub caller: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LotSizeUpdated {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub lot_size: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MaxOrderQuantityUpdated {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub max_order_quantity: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BookModeUpdated {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub book_mode: BookMode,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CollateralResynced {
+    pub market_id: u32,
+    pub caller: Pubkey,
+    pub previous: u64,
+    pub resynced: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OrderTransferred {
+    pub market_id: u32,
+    pub order_id: u64,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub remaining_quantity: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SocializedLossUpdated {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub enabled: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LossSocialized {
+    pub market_id: u32,
+    pub user: Pubkey,
+    pub nominal: u64,
+    pub paid: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SideTradingPauseUpdated {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub token_type: TokenType,
+    pub paused: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeExemptionUpdated {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub user: Pubkey,
+    pub exempt: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeScheduleUpdated {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub fee_schedule: Option<FeeSchedule>,
+    pub effective_fee_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OrderBookReset {
+    pub market_id: u32,
+    pub orders_cleared: u32,
+    pub old_space: u64,
+    pub new_space: u64,
+    pub rent_refunded: u64,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MinSeedCollateralUpdated {
+    pub admin: Pubkey,
+    pub min_seed_collateral: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SeedWithdrawn {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub seed: u64,
+    pub paid: u64,
+    pub timestamp: i64,
+}
+
+// Emitted by place_order when the incoming order filled against the book before resting
+#[event]
+pub struct LimitOrderExecuted {
+    pub market_id: u32,
+    pub order_id: u64,
+    pub user: Pubkey,
+    pub side: OrderSide,
+    pub token_type: TokenType,
+    // The order's limit price
+    pub price: u64,
+    pub initial_quantity: u64,
+    pub filled_quantity: u64,
+    pub orders_matched: u64,
+    // Blended execution price across the levels it crossed: collateral exchanged (fees
+    // included) × TOKEN_DECIMALS_SCALE / tokens exchanged, in the same units as order prices
+    pub avg_fill_price: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketMigrated {
+    pub market_id: u32,
+    pub payer: Pubkey,
+    pub old_space: u64,
+    pub new_space: u64,
+    pub timestamp: i64,
+}
@@ -0,0 +1,63 @@
+use anchor_lang::prelude::*;
+
+use crate::error::*;
+use crate::instructions::*;
+use crate::reentrancy_guard::*;
+
+// Union of the cancel and claim contexts. Both name the same market, UserStats and token
+// accounts, the handler keeps the two copies in sync between the steps.
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct CancelAndClaim<'info> {
+    pub cancel: CancelOrder<'info>,
+    pub claim: ClaimFunds<'info>,
+}
+
+impl<'info> CancelAndClaim<'info> {
+    /// Cancels `order_id` and sweeps the user's claimable balances in one transaction
+    ///
+    /// The cancel returns the order's unfilled lock, the claim then pays out whatever earlier
+    /// fills left in `claimable_*`. With nothing claimable the claim step is skipped, so this
+    /// never fails with `NothingToClaim`. Only the order's owner can call it.
+    pub fn handler(&mut self, market_id: u32, order_id: u64, program_id: &Pubkey) -> Result<()> {
+        require_keys_eq!(
+            self.cancel.user.key(),
+            self.claim.user.key(),
+            PredictionMarketError::CancelClaimUserMismatch
+        );
+        require_keys_eq!(
+            self.cancel.user.key(),
+            self.claim.claimer.key(),
+            PredictionMarketError::CancelClaimUserMismatch
+        );
+
+        {
+            let _guard = ReentrancyGuard::enter(&self.cancel.market)?;
+            self.cancel.handler(market_id, order_id)?;
+        }
+
+        // Write the cancel's state and reload the claim step's copies of the same accounts.
+        // On exit the cancel copies are written first, so the claim step's state is final.
+        self.cancel.market.exit(program_id)?;
+        self.cancel.user_stats_account.exit(program_id)?;
+
+        self.claim.market.reload()?;
+        self.claim.user_stats.reload()?;
+        self.claim.collateral_vault.reload()?;
+        self.claim.user_collateral.reload()?;
+        self.claim.user_outcome_yes.reload()?;
+        self.claim.user_outcome_no.reload()?;
+        self.claim.yes_escrow.reload()?;
+        self.claim.no_escrow.reload()?;
+
+        let stats = &self.claim.user_stats;
+        if stats.claimable_collateral == 0 && stats.claimable_yes == 0 && stats.claimable_no == 0 {
+            msg!("Nothing to claim after cancelling order {}", order_id);
+            return Ok(());
+        }
+
+        // The cancel step's exit wrote its cleared flag, so the claim step guards on its own
+        let _guard = ReentrancyGuard::enter(&self.claim.market)?;
+        self.claim.handler(market_id)
+    }
+}
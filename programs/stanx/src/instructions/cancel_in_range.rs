@@ -0,0 +1,341 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{self, Transfer},
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+use crate::transfer_lock::*;
+
+#[derive(Accounts)]
+#[instruction(market_id:u32)]
+pub struct CancelInRange<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        seeds = [ORDERBOOK_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+        constraint = orderbook.market_id == market_id
+    )]
+    pub orderbook: Account<'info, OrderBook>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
+        constraint = collateral_vault.key() == market.collateral_vault
+    )]
+    pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_collateral.mint == market.collateral_mint,
+        constraint = user_collateral.owner == user.key()
+    )]
+    pub user_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump = user_stats_account.bump
+    )]
+    pub user_stats_account: Box<Account<'info, UserStats>>,
+
+    // At the time of Buy, not require this
+    #[account(
+        mut,
+        constraint = user_outcome_yes.mint == market.outcome_yes_mint @ PredictionMarketError::OutcomeAccountMismatch,
+        constraint = user_outcome_yes.owner == user.key() @ PredictionMarketError::OutcomeAccountMismatch
+    )]
+    pub user_outcome_yes: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        constraint = user_outcome_no.mint == market.outcome_no_mint @ PredictionMarketError::OutcomeAccountMismatch,
+        constraint = user_outcome_no.owner == user.key() @ PredictionMarketError::OutcomeAccountMismatch
+    )]
+    pub user_outcome_no: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        constraint = yes_escrow.mint == market.outcome_yes_mint,
+        seeds = [ESCROW_SEED, market.market_id.to_le_bytes().as_ref(), market.outcome_yes_mint.as_ref()],
+        bump = market.yes_escrow_bump,
+        constraint = yes_escrow.key() == market.yes_escrow
+    )]
+    pub yes_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = no_escrow.mint == market.outcome_no_mint,
+        seeds = [ESCROW_SEED, market.market_id.to_le_bytes().as_ref(), market.outcome_no_mint.as_ref()],
+        bump = market.no_escrow_bump,
+        constraint = no_escrow.key() == market.no_escrow
+    )]
+    pub no_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Only needed on restricted-transfer markets, to thaw/freeze the outcome account being used
+    #[account(
+        constraint = outcome_mint.key() == market.outcome_yes_mint
+            || outcome_mint.key() == market.outcome_no_mint
+    )]
+    pub outcome_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    // Only needed while the market pays maker rewards (reward_rate > 0)
+    #[account(
+        mut,
+        constraint = incentive_vault.key() == market.incentive_vault
+    )]
+    pub incentive_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> CancelInRange<'info> {
+    /// Cancel all of the caller's resting orders on one side of the book whose price is
+    /// within `[min_price, max_price]`
+    ///
+    /// - The side is price-sorted, so the range is located with two binary searches
+    /// - Unfilled collateral/tokens of all cancelled orders are refunded in a single transfer
+    /// - One `OrderCancelled` is emitted per removed order
+    pub fn handler(
+        &mut self,
+        market_id: u32,
+        token_type: TokenType,
+        side: OrderSide,
+        min_price: u64,
+        max_price: u64,
+    ) -> Result<()> {
+        let market = &mut self.market;
+        let orderbook = &mut self.orderbook;
+
+        market.check_distinct_holdings()?;
+
+        // Resting orders stay cancellable once the market is settled so their locked
+        // funds can always be recovered
+        require!(
+            market.is_settled || Clock::get()?.unix_timestamp < market.settlement_deadline,
+            PredictionMarketError::MarketExpired
+        );
+
+        require!(
+            min_price <= max_price,
+            PredictionMarketError::InvalidPriceRange
+        );
+
+        let order_vec = match (token_type, side) {
+            (TokenType::Yes, OrderSide::Buy) => &mut orderbook.yes_buy_orders,
+            (TokenType::Yes, OrderSide::Sell) => &mut orderbook.yes_sell_orders,
+            (TokenType::No, OrderSide::Buy) => &mut orderbook.no_buy_orders,
+            (TokenType::No, OrderSide::Sell) => &mut orderbook.no_sell_orders,
+        };
+
+        // Buy orders are sorted highest price first, sell orders lowest price first
+        let (start, end) = if side == OrderSide::Buy {
+            (
+                order_vec.partition_point(|o| o.price > max_price),
+                order_vec.partition_point(|o| o.price >= min_price),
+            )
+        } else {
+            (
+                order_vec.partition_point(|o| o.price < min_price),
+                order_vec.partition_point(|o| o.price <= max_price),
+            )
+        };
+
+        let user_key = self.user.key();
+        let mut cancelled: Vec<Order> = Vec::new();
+        let mut idx = start;
+        let mut range_end = end;
+        while idx < range_end {
+            if order_vec[idx].user_key == user_key {
+                cancelled.push(order_vec.remove(idx));
+                range_end -= 1;
+            } else {
+                idx += 1;
+            }
+        }
+
+        require!(!cancelled.is_empty(), PredictionMarketError::OrdernotFound);
+
+        orderbook.refresh_book_hash();
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut total_refund: u64 = 0;
+        let mut total_reward: u64 = 0;
+
+        for order in cancelled.iter() {
+            orderbook.record_closed(*order, ClosedOrderReason::Cancelled, now);
+            let unfilled_quantity = order
+                .quantity
+                .checked_sub(order.filledquantity)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+
+            // Same refund as cancel_order, collateral for buys and tokens for sells
+            let refund = if side == OrderSide::Buy {
+                Order::collateral_for(unfilled_quantity, order.price)?
+            } else {
+                unfilled_quantity
+            };
+            total_refund = total_refund
+                .checked_add(refund)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+
+            let maker_reward =
+                market.accrue_maker_reward(unfilled_quantity, order.timestamp, now)?;
+            if maker_reward > 0 {
+                total_reward = total_reward
+                    .checked_add(maker_reward)
+                    .ok_or(PredictionMarketError::MathOverflow)?;
+                emit!(MakerRewardAccrued {
+                    market_id,
+                    order_id: order.id,
+                    maker: user_key,
+                    amount: maker_reward,
+                    timestamp: now,
+                });
+            }
+
+            emit!(OrderCancelled {
+                market_id,
+                order_id: order.id,
+                user: user_key,
+                side: order.side,
+                token_type: order.token_type,
+                remaining_quantity: unfilled_quantity,
+                timestamp: now,
+            });
+        }
+
+        let market_id_bytes = market.market_id.to_le_bytes();
+        let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[market.bump]];
+
+        if total_reward > 0 {
+            self.user_stats_account.credit_maker_reward(total_reward)?;
+
+            let incentive_vault = self
+                .incentive_vault
+                .as_ref()
+                .ok_or(PredictionMarketError::IncentiveVaultRequired)?;
+
+            token::transfer(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    Transfer {
+                        from: incentive_vault.to_account_info(),
+                        to: self.collateral_vault.to_account_info(),
+                        authority: market.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                total_reward,
+            )?;
+
+            market.total_collateral_locked = market
+                .total_collateral_locked
+                .checked_add(total_reward)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+        }
+
+        if side == OrderSide::Buy {
+            self.user_stats_account.locked_collateral = self
+                .user_stats_account
+                .locked_collateral
+                .checked_sub(total_refund)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+
+            if total_refund > 0 {
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        self.token_program.to_account_info(),
+                        Transfer {
+                            from: self.collateral_vault.to_account_info(),
+                            to: self.user_collateral.to_account_info(),
+                            authority: market.to_account_info(),
+                        },
+                        &[seeds],
+                    ),
+                    total_refund,
+                )?;
+            }
+
+            market.total_collateral_locked = market
+                .total_collateral_locked
+                .checked_sub(total_refund)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+        } else {
+            let (user_token_account, token_escrow) = match token_type {
+                TokenType::Yes => (
+                    self.user_outcome_yes
+                        .as_ref()
+                        .ok_or(PredictionMarketError::OutcomeAccountRequired)?,
+                    &self.yes_escrow,
+                ),
+                TokenType::No => (
+                    self.user_outcome_no
+                        .as_ref()
+                        .ok_or(PredictionMarketError::OutcomeAccountRequired)?,
+                    &self.no_escrow,
+                ),
+            };
+
+            let locked_field = match token_type {
+                TokenType::Yes => &mut self.user_stats_account.locked_yes,
+                TokenType::No => &mut self.user_stats_account.locked_no,
+            };
+            *locked_field = locked_field
+                .checked_sub(total_refund)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+
+            thaw_if_restricted(
+                market,
+                &self.token_program,
+                user_token_account,
+                self.outcome_mint.as_ref().map(|m| m.to_account_info()),
+            )?;
+            token::transfer(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    Transfer {
+                        from: token_escrow.to_account_info(),
+                        to: user_token_account.to_account_info(),
+                        authority: market.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                total_refund,
+            )?;
+            freeze_if_restricted(
+                market,
+                &self.token_program,
+                user_token_account,
+                self.outcome_mint.as_ref().map(|m| m.to_account_info()),
+            )?;
+        }
+
+        msg!(
+            "Cancelled {} orders between {} and {}",
+            cancelled.len(),
+            min_price,
+            max_price
+        );
+
+        Ok(())
+    }
+}
@@ -2,13 +2,14 @@ use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
     token::{self, Transfer},
-    token_interface::{TokenAccount, TokenInterface},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
 use crate::constants::*;
 use crate::error::*;
 use crate::events::*;
 use crate::state::*;
+use crate::transfer_lock::*;
 
 #[derive(Accounts)]
 #[instruction(market_id:u32)]
@@ -34,6 +35,8 @@ pub struct CancelOrder<'info> {
 
     #[account(
         mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
         constraint = collateral_vault.key() == market.collateral_vault
     )]
     pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
@@ -53,15 +56,25 @@ pub struct CancelOrder<'info> {
     pub user_stats_account: Box<Account<'info, UserStats>>,
 
     // At the time of Buy, not require this
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = user_outcome_yes.mint == market.outcome_yes_mint @ PredictionMarketError::OutcomeAccountMismatch,
+        constraint = user_outcome_yes.owner == user.key() @ PredictionMarketError::OutcomeAccountMismatch
+    )]
     pub user_outcome_yes: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = user_outcome_no.mint == market.outcome_no_mint @ PredictionMarketError::OutcomeAccountMismatch,
+        constraint = user_outcome_no.owner == user.key() @ PredictionMarketError::OutcomeAccountMismatch
+    )]
     pub user_outcome_no: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
     #[account(
         mut,
         constraint = yes_escrow.mint == market.outcome_yes_mint,
+        seeds = [ESCROW_SEED, market.market_id.to_le_bytes().as_ref(), market.outcome_yes_mint.as_ref()],
+        bump = market.yes_escrow_bump,
         constraint = yes_escrow.key() == market.yes_escrow
     )]
     pub yes_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
@@ -69,10 +82,26 @@ pub struct CancelOrder<'info> {
     #[account(
         mut,
         constraint = no_escrow.mint == market.outcome_no_mint,
+        seeds = [ESCROW_SEED, market.market_id.to_le_bytes().as_ref(), market.outcome_no_mint.as_ref()],
+        bump = market.no_escrow_bump,
         constraint = no_escrow.key() == market.no_escrow
     )]
     pub no_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    // Only needed on restricted-transfer markets, to thaw/freeze the outcome account being used
+    #[account(
+        constraint = outcome_mint.key() == market.outcome_yes_mint
+            || outcome_mint.key() == market.outcome_no_mint
+    )]
+    pub outcome_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    // Only needed while the market pays maker rewards (reward_rate > 0)
+    #[account(
+        mut,
+        constraint = incentive_vault.key() == market.incentive_vault
+    )]
+    pub incentive_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
@@ -83,14 +112,13 @@ impl<'info> CancelOrder<'info> {
         let market = &mut self.market;
         let orderbook = &mut self.orderbook;
 
-        require!(
-            Clock::get()?.unix_timestamp < market.settlement_deadline,
-            PredictionMarketError::MarketExpired
-        );
+        market.check_distinct_holdings()?;
 
+        // Resting orders stay cancellable once the market is settled so their locked
+        // funds can always be recovered
         require!(
-            !market.is_settled,
-            PredictionMarketError::MarketAlreadySettled
+            market.is_settled || Clock::get()?.unix_timestamp < market.settlement_deadline,
+            PredictionMarketError::MarketExpired
         );
 
         // Search for the order across all order books sequentially
@@ -139,6 +167,8 @@ impl<'info> CancelOrder<'info> {
             PredictionMarketError::NotAuthorized
         );
 
+        orderbook.refresh_book_hash();
+
         // Calculate the unfilled portion to refund
         let unfilled_quantity = order_found
             .quantity
@@ -150,13 +180,51 @@ impl<'info> CancelOrder<'info> {
             PredictionMarketError::OrderFullyFilled
         );
 
+        let now = Clock::get()?.unix_timestamp;
+        orderbook.record_closed(order_found, ClosedOrderReason::Cancelled, now);
+        // Liquidity-mining reward for the quantity that rested until now
+        let maker_reward =
+            market.accrue_maker_reward(unfilled_quantity, order_found.timestamp, now)?;
+        if maker_reward > 0 {
+            self.user_stats_account.credit_maker_reward(maker_reward)?;
+
+            let incentive_vault = self
+                .incentive_vault
+                .as_ref()
+                .ok_or(PredictionMarketError::IncentiveVaultRequired)?;
+            let market_id_bytes = market.market_id.to_le_bytes();
+            let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[market.bump]];
+
+            token::transfer(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    Transfer {
+                        from: incentive_vault.to_account_info(),
+                        to: self.collateral_vault.to_account_info(),
+                        authority: market.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                maker_reward,
+            )?;
+
+            market.total_collateral_locked = market
+                .total_collateral_locked
+                .checked_add(maker_reward)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+
+            emit!(MakerRewardAccrued {
+                market_id,
+                order_id,
+                maker: self.user.key(),
+                amount: maker_reward,
+                timestamp: now,
+            });
+        }
+
         if order_side == OrderSide::Buy {
             // For buy orders, unlock collateral for the unfilled portion only
-            let refund_amount = unfilled_quantity
-                .checked_mul(order_found.price)
-                .ok_or(PredictionMarketError::MathOverflow)?
-                .checked_div(TOKEN_DECIMALS_SCALE)
-                .ok_or(PredictionMarketError::MathOverflow)?;
+            let refund_amount = Order::collateral_for(unfilled_quantity, order_found.price)?;
 
             self.user_stats_account.locked_collateral = self
                 .user_stats_account
@@ -221,6 +289,12 @@ impl<'info> CancelOrder<'info> {
             }
 
             // Transfer unfilled tokens back from escrow to user
+            thaw_if_restricted(
+                market,
+                &self.token_program,
+                user_token_account,
+                self.outcome_mint.as_ref().map(|m| m.to_account_info()),
+            )?;
             let market_id_bytes = market.market_id.to_le_bytes();
             let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[market.bump]];
 
@@ -236,6 +310,12 @@ impl<'info> CancelOrder<'info> {
                 ),
                 unfilled_quantity,
             )?;
+            freeze_if_restricted(
+                market,
+                &self.token_program,
+                user_token_account,
+                self.outcome_mint.as_ref().map(|m| m.to_account_info()),
+            )?;
         }
 
         msg!("Order {} cancelled successfully", order_id);
@@ -0,0 +1,92 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::instructions::pay_claimable_collateral;
+use crate::state::*;
+
+// claim_funds without its init_if_needed token accounts: only the user's existing collateral
+// account is needed, so no outcome token account is created for a leg with nothing to claim
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct ClaimCollateral<'info> {
+    // The user or their `claim_delegate`
+    pub claimer: Signer<'info>,
+
+    /// CHECK: owner of the funds, only used as the UserStats seed and token account owner
+    pub user: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump = user_stats.bump,
+        constraint = user_stats.user == user.key(),
+        constraint = claimer.key() == user.key()
+            || user_stats.claim_delegate == Some(claimer.key())
+            @ PredictionMarketError::UnauthorizedClaimer
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        mut,
+        constraint = user_collateral.mint == market.collateral_mint,
+        constraint = user_collateral.owner == user.key()
+    )]
+    pub user_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
+        constraint = collateral_vault.key() == market.collateral_vault
+    )]
+    pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> ClaimCollateral<'info> {
+    /// Withdraws only `claimable_collateral`, claimable YES/NO stay for `claim_funds`
+    pub fn handler(&mut self, market_id: u32) -> Result<()> {
+        require!(
+            self.user_stats.claimable_collateral > 0,
+            PredictionMarketError::NothingToClaim
+        );
+
+        let collateral_paid = pay_claimable_collateral(
+            &mut self.market,
+            &mut self.user_stats,
+            &self.collateral_vault,
+            &self.user_collateral,
+            &self.token_program,
+            self.user.key(),
+        )?;
+
+        msg!(
+            "User {} claimed {} collateral",
+            self.user.key(),
+            collateral_paid
+        );
+
+        emit!(FundsClaimed {
+            market_id,
+            user: self.user.key(),
+            collateral_amount: collateral_paid,
+            yes_amount: 0,
+            no_amount: 0,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
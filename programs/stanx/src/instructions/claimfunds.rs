@@ -2,6 +2,7 @@ use crate::constants::*;
 use crate::error::*;
 use crate::events::*;
 use crate::state::*;
+use crate::transfer_lock::*;
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
@@ -12,8 +13,12 @@ use anchor_spl::{
 #[derive(Accounts)]
 #[instruction(market_id: u32)]
 pub struct ClaimFunds<'info> {
+    // The user or their `claim_delegate`, pays for any token account created here
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub claimer: Signer<'info>,
+
+    /// CHECK: owner of the funds, only used as the UserStats seed and token account authority
+    pub user: UncheckedAccount<'info>,
 
     #[account(
         mut,
@@ -27,7 +32,10 @@ pub struct ClaimFunds<'info> {
         mut,
         seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
         bump = user_stats.bump,
-        constraint = user_stats.user == user.key()
+        constraint = user_stats.user == user.key(),
+        constraint = claimer.key() == user.key()
+            || user_stats.claim_delegate == Some(claimer.key())
+            @ PredictionMarketError::UnauthorizedClaimer
     )]
     pub user_stats: Account<'info, UserStats>,
 
@@ -42,7 +50,7 @@ pub struct ClaimFunds<'info> {
 
     #[account(
         init_if_needed,
-        payer = user,
+        payer = claimer,
         associated_token::mint = collateral_mint,
         associated_token::authority = user,
         associated_token::token_program = token_program,
@@ -51,13 +59,15 @@ pub struct ClaimFunds<'info> {
 
     #[account(
         mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
         constraint = collateral_vault.key() == market.collateral_vault
     )]
     pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         init_if_needed,
-        payer = user,
+        payer = claimer,
         associated_token::mint = outcome_yes_mint,
         associated_token::authority = user,
         associated_token::token_program = token_program,
@@ -66,7 +76,7 @@ pub struct ClaimFunds<'info> {
 
     #[account(
         init_if_needed,
-        payer = user,
+        payer = claimer,
         associated_token::mint = outcome_no_mint,
         associated_token::authority = user,
         associated_token::token_program = token_program,
@@ -76,6 +86,8 @@ pub struct ClaimFunds<'info> {
     #[account(
         mut,
         constraint = yes_escrow.mint == market.outcome_yes_mint,
+        seeds = [ESCROW_SEED, market.market_id.to_le_bytes().as_ref(), market.outcome_yes_mint.as_ref()],
+        bump = market.yes_escrow_bump,
         constraint = yes_escrow.key() == market.yes_escrow
     )]
     pub yes_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
@@ -83,6 +95,8 @@ pub struct ClaimFunds<'info> {
     #[account(
         mut,
         constraint = no_escrow.mint == market.outcome_no_mint,
+        seeds = [ESCROW_SEED, market.market_id.to_le_bytes().as_ref(), market.outcome_no_mint.as_ref()],
+        bump = market.no_escrow_bump,
         constraint = no_escrow.key() == market.no_escrow
     )]
     pub no_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
@@ -109,30 +123,26 @@ impl<'info> ClaimFunds<'info> {
 
         // If Claimable assets are available, transfer them to the user
 
-        if claimable_collateral > 0 {
-            token::transfer(
-                CpiContext::new_with_signer(
-                    self.token_program.to_account_info(),
-                    Transfer {
-                        from: self.collateral_vault.to_account_info(),
-                        to: self.user_collateral.to_account_info(),
-                        authority: self.market.to_account_info(),
-                    },
-                    signer_seeds,
-                ),
-                claimable_collateral,
-            )?;
-            self.user_stats.claimable_collateral = 0;
-
-            // Track vault-level collateral leaving
-            self.market.total_collateral_locked = self
-                .market
-                .total_collateral_locked
-                .checked_sub(claimable_collateral)
-                .ok_or(PredictionMarketError::MathOverflow)?;
-        }
+        let collateral_paid = pay_claimable_collateral(
+            &mut self.market,
+            &mut self.user_stats,
+            &self.collateral_vault,
+            &self.user_collateral,
+            &self.token_program,
+            self.user.key(),
+        )?;
 
         if claimable_yes > 0 {
+            require!(
+                self.yes_escrow.amount >= claimable_yes,
+                PredictionMarketError::InsufficientEscrow
+            );
+            thaw_if_restricted(
+                &self.market,
+                &self.token_program,
+                &self.user_outcome_yes,
+                Some(self.outcome_yes_mint.to_account_info()),
+            )?;
             token::transfer(
                 CpiContext::new_with_signer(
                     self.token_program.to_account_info(),
@@ -145,10 +155,26 @@ impl<'info> ClaimFunds<'info> {
                 ),
                 claimable_yes,
             )?;
+            freeze_if_restricted(
+                &self.market,
+                &self.token_program,
+                &self.user_outcome_yes,
+                Some(self.outcome_yes_mint.to_account_info()),
+            )?;
             self.user_stats.claimable_yes = 0;
         }
 
         if claimable_no > 0 {
+            require!(
+                self.no_escrow.amount >= claimable_no,
+                PredictionMarketError::InsufficientEscrow
+            );
+            thaw_if_restricted(
+                &self.market,
+                &self.token_program,
+                &self.user_outcome_no,
+                Some(self.outcome_no_mint.to_account_info()),
+            )?;
             token::transfer(
                 CpiContext::new_with_signer(
                     self.token_program.to_account_info(),
@@ -161,13 +187,19 @@ impl<'info> ClaimFunds<'info> {
                 ),
                 claimable_no,
             )?;
+            freeze_if_restricted(
+                &self.market,
+                &self.token_program,
+                &self.user_outcome_no,
+                Some(self.outcome_no_mint.to_account_info()),
+            )?;
             self.user_stats.claimable_no = 0;
         }
 
         msg!(
             "User {} claimed: {} collateral, {} YES tokens, {} NO tokens",
             self.user.key(),
-            claimable_collateral,
+            collateral_paid,
             claimable_yes,
             claimable_no
         );
@@ -175,7 +207,7 @@ impl<'info> ClaimFunds<'info> {
         emit!(FundsClaimed {
             market_id,
             user: self.user.key(),
-            collateral_amount: claimable_collateral,
+            collateral_amount: collateral_paid,
             yes_amount: claimable_yes,
             no_amount: claimable_no,
             timestamp: Clock::get()?.unix_timestamp,
@@ -184,3 +216,62 @@ impl<'info> ClaimFunds<'info> {
         Ok(())
     }
 }
+
+/// Pays the user's `claimable_collateral` from the vault and clears it, returns what was paid
+///
+/// The full claim leaves `total_collateral_locked` even when a socialized loss or a rebasing
+/// vault pays less. Does nothing when there is no claimable collateral.
+pub fn pay_claimable_collateral<'info>(
+    market: &mut Account<'info, Market>,
+    user_stats: &mut Account<'info, UserStats>,
+    collateral_vault: &InterfaceAccount<'info, TokenAccount>,
+    user_collateral: &InterfaceAccount<'info, TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+    user: Pubkey,
+) -> Result<u64> {
+    let claimable_collateral = user_stats.claimable_collateral;
+    if claimable_collateral == 0 {
+        return Ok(0);
+    }
+
+    let paid = market.socialized_payout(claimable_collateral, collateral_vault.amount)?;
+    require!(
+        collateral_vault.amount >= paid,
+        PredictionMarketError::InsufficientEscrow
+    );
+
+    let market_id_bytes = market.market_id.to_le_bytes();
+    let bump = market.bump;
+    let signer_seeds: &[&[&[u8]]] = &[&[MARKET_SEED, market_id_bytes.as_ref(), &[bump]]];
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: collateral_vault.to_account_info(),
+                to: user_collateral.to_account_info(),
+                authority: market.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        paid,
+    )?;
+    user_stats.claimable_collateral = 0;
+
+    // Track vault-level collateral leaving
+    market.total_collateral_locked = market
+        .total_collateral_locked
+        .checked_sub(claimable_collateral)
+        .ok_or(PredictionMarketError::MathOverflow)?;
+
+    if paid < claimable_collateral {
+        emit!(LossSocialized {
+            market_id: market.market_id,
+            user,
+            nominal: claimable_collateral,
+            paid,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+    }
+
+    Ok(paid)
+}
@@ -2,18 +2,23 @@ use crate::constants::*;
 use crate::error::*;
 use crate::events::*;
 use crate::state::*;
+use crate::transfer_lock::*;
 use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::{
-    token::{self, Burn, Transfer},
+    token::{self, Burn, CloseAccount, Transfer},
     token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
 #[derive(Accounts)]
 #[instruction(market_id:u32)]
 pub struct ClaimRewards<'info> {
+    // The user or their `claim_delegate`, pays for any token account created here
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub claimer: Signer<'info>,
+
+    /// CHECK: owner of the funds, only used as the UserStats seed and token account authority
+    pub user: UncheckedAccount<'info>,
 
     #[account(
         mut,
@@ -27,7 +32,10 @@ pub struct ClaimRewards<'info> {
         mut,
         seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
         bump = user_stats.bump,
-        constraint = user_stats.user == user.key()
+        constraint = user_stats.user == user.key(),
+        constraint = claimer.key() == user.key()
+            || user_stats.claim_delegate == Some(claimer.key())
+            @ PredictionMarketError::UnauthorizedClaimer
     )]
     pub user_stats: Account<'info, UserStats>,
 
@@ -36,7 +44,7 @@ pub struct ClaimRewards<'info> {
 
     #[account(
         init_if_needed,
-        payer = user,
+        payer = claimer,
         associated_token::mint = collateral_mint,
         associated_token::authority = user,
         associated_token::token_program = token_program,
@@ -45,6 +53,8 @@ pub struct ClaimRewards<'info> {
 
     #[account(
         mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
         constraint = collateral_vault.key() == market.collateral_vault
     )]
     pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
@@ -81,7 +91,23 @@ pub struct ClaimRewards<'info> {
 }
 
 impl<'info> ClaimRewards<'info> {
-    pub fn handler(&mut self, _market_id: u32) -> Result<()> {
+    /// Burns winning tokens for collateral 1:1, or both sides at their settled value for
+    /// scalar markets
+    ///
+    /// `max_amount` caps how much is redeemed in this call so a large balance can be claimed
+    /// over several transactions, `None` redeems the whole balance
+    ///
+    /// A claim delegate burns as the SPL token delegate, so the user must also have approved
+    /// it on the outcome token accounts
+    ///
+    /// `close_empty_ata` closes whichever outcome token accounts are empty afterwards and
+    /// refunds their rent to the user, only honoured when the user claims for themselves
+    pub fn handler(
+        &mut self,
+        _market_id: u32,
+        max_amount: Option<u64>,
+        close_empty_ata: bool,
+    ) -> Result<()> {
         require!(
             self.market.is_settled,
             PredictionMarketError::MarketNotSettled
@@ -93,6 +119,14 @@ impl<'info> ClaimRewards<'info> {
             PredictionMarketError::NothingToClaim
         );
 
+        if matches!(self.market.market_kind, MarketKind::Scalar { .. }) {
+            self.claim_scalar(max_amount)?;
+            if close_empty_ata {
+                self.close_empty_atas()?;
+            }
+            return Ok(());
+        }
+
         let winner = self
             .market
             .winning_outcome
@@ -112,11 +146,12 @@ impl<'info> ClaimRewards<'info> {
             self.outcome_no_mint.to_account_info()
         };
 
-        let amount = if is_yes_winner {
+        let balance = if is_yes_winner {
             self.user_outcome_yes.amount
         } else {
             self.user_outcome_no.amount
         };
+        let amount = max_amount.map_or(balance, |max| max.min(balance));
 
         let winner_ata_info = if is_yes_winner {
             self.user_outcome_yes.to_account_info()
@@ -126,22 +161,46 @@ impl<'info> ClaimRewards<'info> {
 
         require!(amount > 0, PredictionMarketError::InvalidAmount);
 
+        // Market is settled, so the account is left thawed afterwards
+        thaw_if_restricted(
+            &self.market,
+            &self.token_program,
+            if is_yes_winner {
+                &self.user_outcome_yes
+            } else {
+                &self.user_outcome_no
+            },
+            Some(winner_mint.clone()),
+        )?;
+
         token::burn(
             CpiContext::new(
                 self.token_program.to_account_info(),
                 Burn {
                     mint: winner_mint,
                     from: winner_ata_info,
-                    authority: self.user.to_account_info(),
+                    authority: self.claimer.to_account_info(),
                 },
             ),
             amount,
         )?;
+        if is_yes_winner {
+            self.market.record_burn(amount, 0)?;
+        } else {
+            self.market.record_burn(0, amount)?;
+        }
 
         let market_id_bytes = self.market.market_id.to_le_bytes();
         let bump = self.market.bump;
         let signer_seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[bump]];
 
+        let paid = self
+            .market
+            .socialized_payout(amount, self.collateral_vault.amount)?;
+        require!(
+            self.collateral_vault.amount >= paid,
+            PredictionMarketError::InsufficientEscrow
+        );
         token::transfer(
             CpiContext::new_with_signer(
                 self.token_program.to_account_info(),
@@ -152,7 +211,7 @@ impl<'info> ClaimRewards<'info> {
                 },
                 &[signer_seeds],
             ),
-            amount,
+            paid,
         )?;
 
         self.market.total_collateral_locked = self
@@ -161,25 +220,201 @@ impl<'info> ClaimRewards<'info> {
             .checked_sub(amount)
             .ok_or(PredictionMarketError::MathOverflow)?;
 
-        // Rewards claimed set to true
-        self.user_stats.reward_claimed = true;
+        // Only marked claimed once the whole winning balance has been redeemed
+        if amount == balance {
+            self.user_stats.reward_claimed = true;
+        }
 
         let market_id_val = self.market.market_id;
         let user_key = self.user.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        if paid < amount {
+            emit!(LossSocialized {
+                market_id: market_id_val,
+                user: user_key,
+                nominal: amount,
+                paid,
+                timestamp: now,
+            });
+        }
 
         msg!(
             "User {} claimed {} collateral (burned {} winning tokens)",
             user_key,
-            amount,
+            paid,
             amount
         );
 
         emit!(RewardsClaimed {
             market_id: market_id_val,
             user: user_key,
-            collateral_amount: amount,
+            collateral_amount: paid,
             yes_tokens_burned: if is_yes_winner { amount } else { 0 },
             no_tokens_burned: if !is_yes_winner { amount } else { 0 },
+            timestamp: now,
+        });
+
+        if close_empty_ata {
+            self.close_empty_atas()?;
+        }
+
+        Ok(())
+    }
+
+    /// Closes the user's YES/NO token accounts that hold nothing, rent goes back to the user
+    ///
+    /// Only the owner can close a token account, so nothing is closed when a delegate claims.
+    /// Accounts with a different close authority are left alone.
+    fn close_empty_atas(&mut self) -> Result<()> {
+        if self.claimer.key() != self.user.key() {
+            return Ok(());
+        }
+        // Balances and freeze state changed during the claim
+        self.user_outcome_yes.reload()?;
+        self.user_outcome_no.reload()?;
+
+        let user_key = self.user.key();
+        for (mint, account) in [
+            (&self.outcome_yes_mint, &self.user_outcome_yes),
+            (&self.outcome_no_mint, &self.user_outcome_no),
+        ] {
+            let foreign_close_authority =
+                Option::<Pubkey>::from(account.close_authority).is_some_and(|a| a != user_key);
+            if account.amount != 0 || foreign_close_authority {
+                continue;
+            }
+
+            // Frozen accounts can't be closed, the market is settled so it stays thawed
+            thaw_if_restricted(
+                &self.market,
+                &self.token_program,
+                account,
+                Some(mint.to_account_info()),
+            )?;
+
+            // claimer is the user here, and the one passed as writable
+            token::close_account(CpiContext::new(
+                self.token_program.to_account_info(),
+                CloseAccount {
+                    account: account.to_account_info(),
+                    destination: self.claimer.to_account_info(),
+                    authority: self.claimer.to_account_info(),
+                },
+            ))?;
+            msg!("Closed empty token account {}", account.key());
+        }
+
+        Ok(())
+    }
+
+    /// Scalar redemption: burns up to `max_amount` of each side and pays out their combined
+    /// value at the settlement price
+    fn claim_scalar(&mut self, max_amount: Option<u64>) -> Result<()> {
+        let yes_balance = self.user_outcome_yes.amount;
+        let no_balance = self.user_outcome_no.amount;
+        let yes_amount = max_amount.map_or(yes_balance, |max| max.min(yes_balance));
+        let no_amount = max_amount.map_or(no_balance, |max| max.min(no_balance));
+
+        require!(
+            yes_amount > 0 || no_amount > 0,
+            PredictionMarketError::InvalidAmount
+        );
+
+        for (amount, mint, account) in [
+            (yes_amount, &self.outcome_yes_mint, &self.user_outcome_yes),
+            (no_amount, &self.outcome_no_mint, &self.user_outcome_no),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+
+            // Market is settled, so the account is left thawed afterwards
+            thaw_if_restricted(
+                &self.market,
+                &self.token_program,
+                account,
+                Some(mint.to_account_info()),
+            )?;
+
+            token::burn(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    Burn {
+                        mint: mint.to_account_info(),
+                        from: account.to_account_info(),
+                        authority: self.claimer.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+        }
+        self.market.record_burn(yes_amount, no_amount)?;
+
+        let redemption = self.market.scalar_payout(yes_amount, no_amount)?;
+        let nominal = redemption.payout;
+        let payout = self
+            .market
+            .socialized_payout(nominal, self.collateral_vault.amount)?;
+
+        if payout > 0 {
+            let market_id_bytes = self.market.market_id.to_le_bytes();
+            let bump = self.market.bump;
+            let signer_seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[bump]];
+
+            require!(
+                self.collateral_vault.amount >= payout,
+                PredictionMarketError::InsufficientEscrow
+            );
+            token::transfer(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    Transfer {
+                        from: self.collateral_vault.to_account_info(),
+                        to: self.user_collateral.to_account_info(),
+                        authority: self.market.to_account_info(),
+                    },
+                    &[signer_seeds],
+                ),
+                payout,
+            )?;
+        }
+
+        // The rounding dust stays in the vault for the treasury, so the market can still close
+        let dust = self.market.retire_payout(redemption)?;
+        if dust > 0 {
+            msg!("{} collateral of rounding dust moved to the treasury", dust);
+        }
+
+        if payout < nominal {
+            emit!(LossSocialized {
+                market_id: self.market.market_id,
+                user: self.user.key(),
+                nominal,
+                paid: payout,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        }
+
+        if yes_amount == yes_balance && no_amount == no_balance {
+            self.user_stats.reward_claimed = true;
+        }
+
+        let user_key = self.user.key();
+        msg!(
+            "User {} claimed {} collateral (burned {} YES, {} NO)",
+            user_key,
+            payout,
+            yes_amount,
+            no_amount
+        );
+
+        emit!(RewardsClaimed {
+            market_id: self.market.market_id,
+            user: user_key,
+            collateral_amount: payout,
+            yes_tokens_burned: yes_amount,
+            no_tokens_burned: no_amount,
             timestamp: Clock::get()?.unix_timestamp,
         });
 
@@ -1,9 +1,12 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
 use crate::constants::*;
 use crate::error::*;
 use crate::events::*;
 use crate::state::*;
+use crate::transfer_lock::*;
 
 #[derive(Accounts)]
 #[instruction(market_id: u32)]
@@ -29,25 +32,121 @@ pub struct CloseMarket<'info> {
         constraint = orderbook.market_id == market_id
     )]
     pub orderbook: Account<'info, OrderBook>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_REGISTRY_SEED, MarketRegistry::page_for(market_id).to_le_bytes().as_ref()],
+        bump = market_registry.bump
+    )]
+    pub market_registry: Account<'info, MarketRegistry>,
+
+    // Closed with the market so the slug can be used again
+    #[account(
+        mut,
+        close = authority,
+        seeds = [MARKET_SLUG_SEED, market.slug.as_bytes()],
+        bump = market_slug.bump
+    )]
+    pub market_slug: Account<'info, MarketSlug>,
+
+    // The rest is only needed with `cancel_own_orders`, to refund the authority's orders
+    #[account(
+        mut,
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), authority.key().as_ref()],
+        bump = authority_stats.bump
+    )]
+    pub authority_stats: Option<Box<Account<'info, UserStats>>>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
+        constraint = collateral_vault.key() == market.collateral_vault
+    )]
+    pub collateral_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        constraint = authority_collateral.mint == market.collateral_mint,
+        constraint = authority_collateral.owner == authority.key()
+    )]
+    pub authority_collateral: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        constraint = yes_escrow.key() == market.yes_escrow
+    )]
+    pub yes_escrow: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        constraint = no_escrow.key() == market.no_escrow
+    )]
+    pub no_escrow: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        constraint = authority_outcome_yes.mint == market.outcome_yes_mint @ PredictionMarketError::OutcomeAccountMismatch,
+        constraint = authority_outcome_yes.owner == authority.key() @ PredictionMarketError::OutcomeAccountMismatch
+    )]
+    pub authority_outcome_yes: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        constraint = authority_outcome_no.mint == market.outcome_no_mint @ PredictionMarketError::OutcomeAccountMismatch,
+        constraint = authority_outcome_no.owner == authority.key() @ PredictionMarketError::OutcomeAccountMismatch
+    )]
+    pub authority_outcome_no: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Only needed on restricted-transfer markets, to thaw the authority's outcome accounts
+    #[account(constraint = outcome_yes_mint.key() == market.outcome_yes_mint)]
+    pub outcome_yes_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    #[account(constraint = outcome_no_mint.key() == market.outcome_no_mint)]
+    pub outcome_no_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
 }
 
 impl<'info> CloseMarket<'info> {
     /// Close the market and reclaim rent
     /// Can only be called after market is settled
     /// All orders must be cancelled or filled before closing
-    pub fn handler(&self, _market_id: u32) -> Result<()> {
+    ///
+    /// With `cancel_own_orders` the authority's resting orders are cancelled first and their
+    /// locked funds sent back to the authority, orders of other users still block the close
+    pub fn handler(&mut self, market_id: u32, cancel_own_orders: bool) -> Result<()> {
+        // Ensure market is settled
+        require!(
+            self.market.is_settled,
+            PredictionMarketError::MarketNotSettled
+        );
+
+        if cancel_own_orders {
+            self.cancel_authority_orders(market_id)?;
+        }
+
         let market = &self.market;
         let orderbook = &self.orderbook;
 
-        // Ensure market is settled
-        require!(market.is_settled, PredictionMarketError::MarketNotSettled);
-
         // Ensure all collateral has been claimed or withdrawn
         require!(
             market.total_collateral_locked == 0,
             PredictionMarketError::CollateralNotFullyClaimed
         );
 
+        // Fees and rounding dust sit in the vault outside the locked total
+        require!(
+            market.treasury_balance == 0,
+            PredictionMarketError::TreasuryNotWithdrawn
+        );
+
+        // The stake vault is owned by the market PDA, closing now would strand it
+        require!(
+            market.total_staked == 0,
+            PredictionMarketError::StakeOutstanding
+        );
+
         // Ensure all orders have been cancelled or completed
         require!(
             orderbook.yes_buy_orders.is_empty()
@@ -57,6 +156,8 @@ impl<'info> CloseMarket<'info> {
             PredictionMarketError::OrdersStillPending
         );
 
+        self.market_registry.mark_closed(market.market_id);
+
         msg!("Market {} closed successfully", market.market_id);
 
         emit!(MarketClosed {
@@ -67,4 +168,152 @@ impl<'info> CloseMarket<'info> {
 
         Ok(())
     }
+
+    /// Cancels every resting order of the authority and refunds it like `cancel_order` would.
+    /// No maker reward is paid, it would land in the vault the close needs empty.
+    fn cancel_authority_orders(&mut self, market_id: u32) -> Result<()> {
+        let authority_key = self.authority.key();
+
+        let mut cancelled: Vec<Order> = Vec::new();
+        let book: &mut OrderBook = &mut self.orderbook;
+        for order_vec in [
+            &mut book.yes_buy_orders,
+            &mut book.yes_sell_orders,
+            &mut book.no_buy_orders,
+            &mut book.no_sell_orders,
+        ] {
+            let (own, rest): (Vec<Order>, Vec<Order>) =
+                order_vec.iter().partition(|o| o.user_key == authority_key);
+            *order_vec = rest;
+            cancelled.extend(own);
+        }
+        if cancelled.is_empty() {
+            return Ok(());
+        }
+        self.orderbook.refresh_book_hash();
+
+        let authority_stats = self
+            .authority_stats
+            .as_mut()
+            .ok_or(PredictionMarketError::RefundAccountsRequired)?;
+        let token_program = self
+            .token_program
+            .as_ref()
+            .ok_or(PredictionMarketError::RefundAccountsRequired)?;
+
+        let market = &mut self.market;
+        let market_id_bytes = market.market_id.to_le_bytes();
+        let bump = market.bump;
+        let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[bump]];
+        let now = Clock::get()?.unix_timestamp;
+
+        for order in cancelled.iter() {
+            self.orderbook
+                .record_closed(*order, ClosedOrderReason::Cancelled, now);
+            let unfilled_quantity = order
+                .quantity
+                .checked_sub(order.filledquantity)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+
+            let (from, to, amount, mint) = match (order.side, order.token_type) {
+                (OrderSide::Buy, _) => {
+                    let refund = Order::collateral_for(unfilled_quantity, order.price)?;
+                    authority_stats.locked_collateral = authority_stats
+                        .locked_collateral
+                        .checked_sub(refund)
+                        .ok_or(PredictionMarketError::MathOverflow)?;
+                    market.total_collateral_locked = market
+                        .total_collateral_locked
+                        .checked_sub(refund)
+                        .ok_or(PredictionMarketError::MathOverflow)?;
+                    (
+                        &self.collateral_vault,
+                        &self.authority_collateral,
+                        refund,
+                        None,
+                    )
+                }
+                (OrderSide::Sell, TokenType::Yes) => {
+                    authority_stats.locked_yes = authority_stats
+                        .locked_yes
+                        .checked_sub(unfilled_quantity)
+                        .ok_or(PredictionMarketError::MathOverflow)?;
+                    (
+                        &self.yes_escrow,
+                        &self.authority_outcome_yes,
+                        unfilled_quantity,
+                        self.outcome_yes_mint.as_ref(),
+                    )
+                }
+                (OrderSide::Sell, TokenType::No) => {
+                    authority_stats.locked_no = authority_stats
+                        .locked_no
+                        .checked_sub(unfilled_quantity)
+                        .ok_or(PredictionMarketError::MathOverflow)?;
+                    (
+                        &self.no_escrow,
+                        &self.authority_outcome_no,
+                        unfilled_quantity,
+                        self.outcome_no_mint.as_ref(),
+                    )
+                }
+            };
+
+            if amount > 0 {
+                let from = from
+                    .as_ref()
+                    .ok_or(PredictionMarketError::RefundAccountsRequired)?;
+                let to = to
+                    .as_ref()
+                    .ok_or(PredictionMarketError::RefundAccountsRequired)?;
+                // Outcome refunds land in the authority's own, possibly frozen, token accounts
+                let is_outcome = order.side == OrderSide::Sell;
+                if is_outcome {
+                    thaw_if_restricted(
+                        market,
+                        token_program,
+                        to,
+                        mint.map(|m| m.to_account_info()),
+                    )?;
+                }
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        Transfer {
+                            from: from.to_account_info(),
+                            to: to.to_account_info(),
+                            authority: market.to_account_info(),
+                        },
+                        &[seeds],
+                    ),
+                    amount,
+                )?;
+                if is_outcome {
+                    freeze_if_restricted(
+                        market,
+                        token_program,
+                        to,
+                        mint.map(|m| m.to_account_info()),
+                    )?;
+                }
+            }
+
+            emit!(OrderCancelled {
+                market_id,
+                order_id: order.id,
+                user: authority_key,
+                side: order.side,
+                token_type: order.token_type,
+                remaining_quantity: unfilled_quantity,
+                timestamp: now,
+            });
+        }
+
+        msg!(
+            "Cancelled {} authority orders before close",
+            cancelled.len()
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,106 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct DepositCollateral<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    // Created by setup_user or the user's first order
+    #[account(
+        mut,
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump = user_stats_account.bump
+    )]
+    pub user_stats_account: Box<Account<'info, UserStats>>,
+
+    #[account(
+        mut,
+        constraint = user_collateral.mint == market.collateral_mint,
+        constraint = user_collateral.owner == user.key(),
+        constraint = user_collateral.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
+    )]
+    pub user_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
+        constraint = collateral_vault.key() == market.collateral_vault,
+        constraint = collateral_vault.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
+    )]
+    pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> DepositCollateral<'info> {
+    /// Move collateral into the vault once and credit `available_collateral`,
+    /// buy orders placed afterwards lock from it without a token transfer each
+    pub fn handler(&mut self, market_id: u32, amount: u64) -> Result<()> {
+        require!(amount > 0, PredictionMarketError::InvalidAmount);
+        require!(
+            !self.market.is_settled,
+            PredictionMarketError::MarketAlreadySettled
+        );
+        require!(
+            self.user_collateral.amount >= amount,
+            PredictionMarketError::NotEnoughBalance
+        );
+        self.market.check_collateral_cap(amount)?;
+
+        token::transfer(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                Transfer {
+                    from: self.user_collateral.to_account_info(),
+                    to: self.collateral_vault.to_account_info(),
+                    authority: self.user.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let user_stats = &mut self.user_stats_account;
+        user_stats.available_collateral = user_stats
+            .available_collateral
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        self.market.total_collateral_locked = self
+            .market
+            .total_collateral_locked
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        msg!(
+            "Deposited {}, available collateral is now {}",
+            amount,
+            user_stats.available_collateral
+        );
+
+        emit!(CollateralDeposited {
+            market_id,
+            user: self.user.key(),
+            amount,
+            available_collateral: user_stats.available_collateral,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
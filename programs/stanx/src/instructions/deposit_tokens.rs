@@ -0,0 +1,158 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+use crate::transfer_lock::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct DepositTokens<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    // Created by setup_user or the user's first order
+    #[account(
+        mut,
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump = user_stats_account.bump
+    )]
+    pub user_stats_account: Box<Account<'info, UserStats>>,
+
+    // Mints are only used to thaw/freeze the user's account on restricted-transfer markets
+    #[account(
+        constraint = outcome_yes_mint.key() == market.outcome_yes_mint,
+        constraint = outcome_yes_mint.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
+    )]
+    pub outcome_yes_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        constraint = outcome_no_mint.key() == market.outcome_no_mint,
+        constraint = outcome_no_mint.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
+    )]
+    pub outcome_no_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = user_outcome_yes.mint == market.outcome_yes_mint @ PredictionMarketError::OutcomeAccountMismatch,
+        constraint = user_outcome_yes.owner == user.key() @ PredictionMarketError::OutcomeAccountMismatch,
+        constraint = user_outcome_yes.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
+    )]
+    pub user_outcome_yes: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_outcome_no.mint == market.outcome_no_mint @ PredictionMarketError::OutcomeAccountMismatch,
+        constraint = user_outcome_no.owner == user.key() @ PredictionMarketError::OutcomeAccountMismatch,
+        constraint = user_outcome_no.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
+    )]
+    pub user_outcome_no: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, market.market_id.to_le_bytes().as_ref(), market.outcome_yes_mint.as_ref()],
+        bump = market.yes_escrow_bump,
+        constraint = yes_escrow.key() == market.yes_escrow
+    )]
+    pub yes_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, market.market_id.to_le_bytes().as_ref(), market.outcome_no_mint.as_ref()],
+        bump = market.no_escrow_bump,
+        constraint = no_escrow.key() == market.no_escrow
+    )]
+    pub no_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> DepositTokens<'info> {
+    /// Move YES/NO into escrow once and credit `available_yes`/`available_no`,
+    /// sell orders placed afterwards lock from it without a token transfer each
+    pub fn handler(&mut self, market_id: u32, token_type: TokenType, amount: u64) -> Result<()> {
+        require!(amount > 0, PredictionMarketError::InvalidAmount);
+        require!(
+            !self.market.is_settled,
+            PredictionMarketError::MarketAlreadySettled
+        );
+
+        let (user_token_account, token_escrow, mint) = match token_type {
+            TokenType::Yes => (
+                &self.user_outcome_yes,
+                &self.yes_escrow,
+                &self.outcome_yes_mint,
+            ),
+            TokenType::No => (
+                &self.user_outcome_no,
+                &self.no_escrow,
+                &self.outcome_no_mint,
+            ),
+        };
+
+        require!(
+            user_token_account.amount >= amount,
+            PredictionMarketError::NotEnoughBalance
+        );
+
+        thaw_if_restricted(
+            &self.market,
+            &self.token_program,
+            user_token_account,
+            Some(mint.to_account_info()),
+        )?;
+        token::transfer(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                Transfer {
+                    from: user_token_account.to_account_info(),
+                    to: token_escrow.to_account_info(),
+                    authority: self.user.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        freeze_if_restricted(
+            &self.market,
+            &self.token_program,
+            user_token_account,
+            Some(mint.to_account_info()),
+        )?;
+
+        let user_stats = &mut self.user_stats_account;
+        let available = match token_type {
+            TokenType::Yes => &mut user_stats.available_yes,
+            TokenType::No => &mut user_stats.available_no,
+        };
+        *available = available
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        let available = *available;
+
+        msg!(
+            "Deposited {} tokens, available is now {}",
+            amount,
+            available
+        );
+
+        emit!(TokensDeposited {
+            market_id,
+            user: self.user.key(),
+            token_type,
+            amount,
+            available,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
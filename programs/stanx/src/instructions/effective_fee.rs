@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct GetEffectiveFee<'info> {
+    #[account(
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> GetEffectiveFee<'info> {
+    /// Read-only view of the trading fee (bps) the next fill pays, returned through return data
+    pub fn handler(&self) -> Result<u16> {
+        Ok(self.market.effective_fee_bps())
+    }
+}
@@ -0,0 +1,159 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::instructions::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct ForceSettleAndClose<'info> {
+    pub close: CloseMarket<'info>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, market_id.to_le_bytes().as_ref()],
+        bump = close.market.vault_bump,
+        constraint = collateral_vault.key() == close.market.collateral_vault
+    )]
+    pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> ForceSettleAndClose<'info> {
+    /// Pays every passed user their unclaimed collateral, then closes the market like
+    /// `close_market`
+    ///
+    /// - `remaining_accounts` are `[user_stats, user_collateral]` pairs, `user_collateral`
+    ///   must be a collateral token account owned by that user, so funds only go to their owner
+    /// - Each user gets `claimable_collateral + available_collateral`, scaled down when the
+    ///   market socializes a vault shortfall (see `Market::socialized_payout`)
+    /// - The close step still requires `total_collateral_locked == 0`, so if any user holding
+    ///   collateral (or unredeemed winning tokens) was left out the whole transaction fails
+    ///   and nobody's funds are skipped
+    /// - Outcome token balances aren't swept, tokens that still have value keep collateral
+    ///   locked and have to be redeemed with `claim_rewards` first
+    pub fn handler(
+        &mut self,
+        market_id: u32,
+        remaining_accounts: &[AccountInfo<'info>],
+        program_id: &Pubkey,
+    ) -> Result<()> {
+        require!(
+            self.close.market.is_settled,
+            PredictionMarketError::MarketNotSettled
+        );
+        require!(
+            remaining_accounts.len() % 2 == 0,
+            PredictionMarketError::InvalidAccountOwner
+        );
+
+        let market_id_bytes = market_id.to_le_bytes();
+        let bump = self.close.market.bump;
+        let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[bump]];
+        let now = Clock::get()?.unix_timestamp;
+        // Tracked locally, the deserialized vault balance doesn't move with the transfers below
+        let mut vault_balance = self.collateral_vault.amount;
+
+        for pair in remaining_accounts.chunks(2) {
+            let (stats_info, collateral_info) = (&pair[0], &pair[1]);
+
+            require!(
+                stats_info.owner == program_id,
+                PredictionMarketError::InvalidAccountOwner
+            );
+            let mut stats = UserStats::try_deserialize(&mut &stats_info.try_borrow_data()?[..])?;
+            let expected = Pubkey::create_program_address(
+                &[
+                    USER_STATS_SEED,
+                    market_id_bytes.as_ref(),
+                    stats.user.as_ref(),
+                    &[stats.bump],
+                ],
+                program_id,
+            )
+            .map_err(|_| PredictionMarketError::InvalidAccountOwner)?;
+            require_keys_eq!(
+                expected,
+                stats_info.key(),
+                PredictionMarketError::InvalidAccountOwner
+            );
+
+            require!(
+                collateral_info.owner == &self.token_program.key(),
+                PredictionMarketError::TokenProgramMismatch
+            );
+            let user_collateral =
+                TokenAccount::try_deserialize(&mut &collateral_info.try_borrow_data()?[..])?;
+            require!(
+                user_collateral.owner == stats.user
+                    && user_collateral.mint == self.close.market.collateral_mint,
+                PredictionMarketError::InvalidAccountOwner
+            );
+
+            // A repeated pair reads the already zeroed balances and pays nothing
+            let amount = stats
+                .claimable_collateral
+                .checked_add(stats.available_collateral)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+            if amount == 0 {
+                continue;
+            }
+            let paid = self.close.market.socialized_payout(amount, vault_balance)?;
+            vault_balance = vault_balance
+                .checked_sub(paid)
+                .ok_or(PredictionMarketError::InsufficientEscrow)?;
+
+            token::transfer(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    Transfer {
+                        from: self.collateral_vault.to_account_info(),
+                        to: collateral_info.clone(),
+                        authority: self.close.market.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                paid,
+            )?;
+
+            stats.claimable_collateral = 0;
+            stats.available_collateral = 0;
+            {
+                let mut data = stats_info.try_borrow_mut_data()?;
+                let mut writer = &mut data[..];
+                stats.try_serialize(&mut writer)?;
+            }
+
+            self.close.market.total_collateral_locked = self
+                .close
+                .market
+                .total_collateral_locked
+                .checked_sub(amount)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+
+            if paid < amount {
+                emit!(LossSocialized {
+                    market_id,
+                    user: stats.user,
+                    nominal: amount,
+                    paid,
+                    timestamp: now,
+                });
+            }
+
+            emit!(FundsSwept {
+                market_id,
+                user: stats.user,
+                amount: paid,
+                timestamp: now,
+            });
+        }
+
+        self.close.handler(market_id, false)
+    }
+}
@@ -0,0 +1,98 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct FundIncentives<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(constraint = collateral_mint.key() == market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = authority_collateral.mint == market.collateral_mint,
+        constraint = authority_collateral.owner == authority.key()
+    )]
+    pub authority_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [INCENTIVE_VAULT_SEED, market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub incentive_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FundIncentives<'info> {
+    /// Top up the maker reward pool, creates the incentive vault on first use
+    pub fn handler(&mut self, market_id: u32, amount: u64) -> Result<()> {
+        require!(amount > 0, PredictionMarketError::InvalidAmount);
+        require!(
+            !self.market.is_settled,
+            PredictionMarketError::MarketAlreadySettled
+        );
+        require!(
+            self.authority_collateral.amount >= amount,
+            PredictionMarketError::NotEnoughBalance
+        );
+
+        token::transfer(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                Transfer {
+                    from: self.authority_collateral.to_account_info(),
+                    to: self.incentive_vault.to_account_info(),
+                    authority: self.authority.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        self.market.incentive_vault = self.incentive_vault.key();
+        self.market.incentive_pool = self
+            .market
+            .incentive_pool
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        msg!(
+            "Funded {} maker incentives, pool is now {}",
+            amount,
+            self.market.incentive_pool
+        );
+
+        emit!(IncentivesFunded {
+            market_id,
+            authority: self.authority.key(),
+            amount,
+            incentive_pool: self.market.incentive_pool,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
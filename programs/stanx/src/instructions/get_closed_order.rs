@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::error::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct GetClosedOrder<'info> {
+    #[account(
+        seeds = [ORDERBOOK_SEED, market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+        constraint = orderbook.market_id == market_id
+    )]
+    pub orderbook: Account<'info, OrderBook>,
+}
+
+impl<'info> GetClosedOrder<'info> {
+    /// Read-only: final state of `order_id` and why it left the book, while it is still among
+    /// the last `CLOSED_ORDERS_CAPACITY` closed orders of this market
+    pub fn handler(&self, order_id: u64) -> Result<ClosedOrder> {
+        self.orderbook
+            .find_closed_order(order_id)
+            .copied()
+            .ok_or(PredictionMarketError::ClosedOrderNotRetained.into())
+    }
+}
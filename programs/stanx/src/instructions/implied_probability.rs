@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use crate::constants::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct GetImpliedProbability<'info> {
+    #[account(
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [ORDERBOOK_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+        constraint = orderbook.market_id == market_id
+    )]
+    pub orderbook: Account<'info, OrderBook>,
+
+    // Prices are quoted in its base units, its decimals define 1.0
+    #[account(constraint = collateral_mint.key() == market.collateral_mint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+}
+
+impl<'info> GetImpliedProbability<'info> {
+    /// Read-only view of each outcome's implied probability from its best bid/ask,
+    /// returned through return data
+    pub fn handler(&self) -> Result<ImpliedProbability> {
+        ImpliedProbability::new(&self.orderbook, self.collateral_mint.decimals)
+    }
+}
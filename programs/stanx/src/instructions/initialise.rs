@@ -1,27 +1,46 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Allocate, Assign, CreateAccount, Transfer};
 use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+};
 use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
 use crate::constants::*;
 use crate::error::*;
 use crate::events::*;
-use crate::state::{Market, OrderBook};
+use crate::state::{
+    BookMode, Config, Market, MarketKind, MarketRegistry, MarketSlug, OrderBook, RoundingMode,
+    StpPolicy,
+};
 
 #[derive(Accounts)]
 #[instruction(market_id: u32)]
 pub struct InitializeMarket<'info> {
+    // init_if_needed only so an existing market fails with MarketAlreadyExists instead of
+    // "account already in use". The constraint runs right after this account is loaded,
+    // before any other account is created, and a freshly created market always has a zeroed
+    // authority, so an initialized market can never be re-initialized.
     #[account(
-        init,
+        init_if_needed,
         payer = authority,
         space = 8 + Market::INIT_SPACE,
         seeds = [MARKET_SEED, market_id.to_le_bytes().as_ref()],
-        bump
+        bump,
+        constraint = market.authority == Pubkey::default() @ PredictionMarketError::MarketAlreadyExists
     )]
     pub market: Box<Account<'info, Market>>,
 
     #[account(mut)]
     pub authority: Signer<'info>,
 
+    /// CHECK: the Config PDA, which may not be initialized yet. Until it is, market creation
+    /// stays permissionless, see `check_creator`.
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: UncheckedAccount<'info>,
+
     pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
@@ -35,11 +54,20 @@ pub struct InitializeMarket<'info> {
     )]
     pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    // Only needed when seeding the market with collateral
+    #[account(
+        mut,
+        constraint = authority_collateral.mint == collateral_mint.key(),
+        constraint = authority_collateral.owner == authority.key()
+    )]
+    pub authority_collateral: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
     #[account(
         init,
         payer = authority,
-        mint::decimals = 6,
+        mint::decimals = OUTCOME_TOKEN_DECIMALS,
         mint::authority = market,
+        mint::freeze_authority = market,
         mint::token_program = token_program,
         seeds = [OUTCOME_YES_SEED, market_id.to_le_bytes().as_ref()],
         bump
@@ -48,8 +76,9 @@ pub struct InitializeMarket<'info> {
     #[account(
         init,
         payer = authority,
-        mint::decimals = 6,
+        mint::decimals = OUTCOME_TOKEN_DECIMALS,
         mint::authority = market,
+        mint::freeze_authority = market,
         mint::token_program = token_program,
         seeds = [OUTCOME_NO_SEED, market_id.to_le_bytes().as_ref()],
         bump
@@ -82,31 +111,237 @@ pub struct InitializeMarket<'info> {
         init,
         payer = authority,
         seeds = [ORDERBOOK_SEED, market_id.to_le_bytes().as_ref()],
-        space = OrderBook::space(MAX_ORDERS_PER_SIDE),
+        // Starts empty, place_order grows it in ORDERBOOK_GROWTH_BATCH steps
+        space = OrderBook::space(0),
         bump
     )]
     pub orderbook: Box<Account<'info, OrderBook>>,
 
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + MarketRegistry::INIT_SPACE,
+        seeds = [MARKET_REGISTRY_SEED, MarketRegistry::page_for(market_id).to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market_registry: Box<Account<'info, MarketRegistry>>,
+
+    // Seeded by the slug argument, which isn't part of `#[instruction]` (from-template passes
+    // its arguments in a different order), so it's derived and created in `create_market_slug`
+    /// CHECK: address and emptiness are validated in the handler
+    #[account(mut)]
+    pub market_slug: UncheckedAccount<'info>,
+
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
 }
 
 impl<'info> InitializeMarket<'info> {
+    /// Rejects signers missing from a non-empty creator allowlist and seeds below the
+    /// configured `min_seed_collateral`
+    fn check_creator(&self, seed_collateral: u64) -> Result<()> {
+        if self.config.data_is_empty() {
+            return Ok(());
+        }
+        let config = Config::try_deserialize(&mut &self.config.try_borrow_data()?[..])?;
+        require!(
+            config.allows_creator(&self.authority.key()),
+            PredictionMarketError::NotAuthorized
+        );
+        require!(
+            seed_collateral >= config.min_seed_collateral,
+            PredictionMarketError::SeedCollateralTooLow
+        );
+        Ok(())
+    }
+
+    /// Moves `seed_collateral` from the authority into the vault, where it stays locked
+    /// until `withdraw_seed` after settlement
+    fn seed_vault(&mut self, seed_collateral: u64) -> Result<()> {
+        if seed_collateral == 0 {
+            return Ok(());
+        }
+        let authority_collateral = self
+            .authority_collateral
+            .as_ref()
+            .ok_or(PredictionMarketError::SeedCollateralAccountRequired)?;
+        require!(
+            authority_collateral.amount >= seed_collateral,
+            PredictionMarketError::NotEnoughBalance
+        );
+
+        token::transfer(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                token::Transfer {
+                    from: authority_collateral.to_account_info(),
+                    to: self.collateral_vault.to_account_info(),
+                    authority: self.authority.to_account_info(),
+                },
+            ),
+            seed_collateral,
+        )?;
+
+        self.market.seed_collateral = seed_collateral;
+        self.market.total_collateral_locked = seed_collateral;
+        Ok(())
+    }
+
+    /// Rejects Token-2022 collateral with an interest-bearing extension unless the market is
+    /// created `rebasing`, its vault balance would drift from `total_collateral_locked`
+    fn check_collateral_mint(&self, rebasing: bool) -> Result<()> {
+        let mint_info = self.collateral_mint.to_account_info();
+        if rebasing || mint_info.owner != &spl_token_2022::ID {
+            return Ok(());
+        }
+        let data = mint_info.try_borrow_data()?;
+        let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+        require!(
+            !mint
+                .get_extension_types()?
+                .contains(&ExtensionType::InterestBearingConfig),
+            PredictionMarketError::RebasingCollateralNotEnabled
+        );
+        Ok(())
+    }
+
+    /// Creates the MarketSlug PDA for `slug`, failing with `SlugTaken` if it already exists
+    fn create_market_slug(&self, slug: &str, market_id: u32) -> Result<()> {
+        require!(
+            MarketSlug::is_valid(slug),
+            PredictionMarketError::InvalidSlug
+        );
+
+        let (expected, bump) =
+            Pubkey::find_program_address(&[MARKET_SLUG_SEED, slug.as_bytes()], &crate::ID);
+        require_keys_eq!(
+            self.market_slug.key(),
+            expected,
+            PredictionMarketError::InvalidSlug
+        );
+        require!(
+            self.market_slug.owner == &system_program::ID,
+            PredictionMarketError::SlugTaken
+        );
+
+        let space = MarketSlug::DISCRIMINATOR.len() + MarketSlug::INIT_SPACE;
+        let rent = Rent::get()?.minimum_balance(space);
+        let signer_seeds: &[&[&[u8]]] = &[&[MARKET_SLUG_SEED, slug.as_bytes(), &[bump]]];
+        let system = self.system_program.to_account_info();
+        let slug_info = self.market_slug.to_account_info();
+
+        // Same steps as Anchor's `init`: someone may have pre-funded the address, in which
+        // case create_account would fail, so top up, allocate and assign instead
+        let lamports = slug_info.lamports();
+        if lamports == 0 {
+            system_program::create_account(
+                CpiContext::new_with_signer(
+                    system.clone(),
+                    CreateAccount {
+                        from: self.authority.to_account_info(),
+                        to: slug_info.clone(),
+                    },
+                    signer_seeds,
+                ),
+                rent,
+                space as u64,
+                &crate::ID,
+            )?;
+        } else {
+            if lamports < rent {
+                system_program::transfer(
+                    CpiContext::new(
+                        system.clone(),
+                        Transfer {
+                            from: self.authority.to_account_info(),
+                            to: slug_info.clone(),
+                        },
+                    ),
+                    rent - lamports,
+                )?;
+            }
+            system_program::allocate(
+                CpiContext::new_with_signer(
+                    system.clone(),
+                    Allocate {
+                        account_to_allocate: slug_info.clone(),
+                    },
+                    signer_seeds,
+                ),
+                space as u64,
+            )?;
+            system_program::assign(
+                CpiContext::new_with_signer(
+                    system,
+                    Assign {
+                        account_to_assign: slug_info.clone(),
+                    },
+                    signer_seeds,
+                ),
+                &crate::ID,
+            )?;
+        }
+
+        let mut data = slug_info.try_borrow_mut_data()?;
+        MarketSlug { market_id, bump }.try_serialize(&mut &mut data[..])
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn initialise(
         &mut self,
         market_id: u32,
         settlement_deadline: i64,
         bumps: &InitializeMarketBumps,
         meta_data_url: String,
+        category: u16,
+        restricted_transfer: bool,
+        outcome_a_label: String,
+        outcome_b_label: String,
+        market_kind: MarketKind,
+        min_price: Option<u64>,
+        max_price: Option<u64>,
+        slug: String,
+        rebasing: bool,
+        seed_collateral: u64,
     ) -> Result<()> {
+        self.check_creator(seed_collateral)?;
+        self.check_collateral_mint(rebasing)?;
+        self.create_market_slug(&slug, market_id)?;
+
         require!(
             settlement_deadline > Clock::get()?.unix_timestamp,
             PredictionMarketError::InvalidSettlementDeadline
         );
+        require!(
+            meta_data_url.len() <= 200,
+            PredictionMarketError::InvalidMetadata
+        );
+        require!(
+            outcome_a_label.len() <= 32 && outcome_b_label.len() <= 32,
+            PredictionMarketError::InvalidOutcomeLabel
+        );
+        if let MarketKind::Scalar { low, high } = market_kind {
+            require!(low < high, PredictionMarketError::InvalidScalarBounds);
+        }
+
+        // Defaults to (0, 1.0): a whole outcome token can never be worth more than one
+        // whole collateral token
+        let min_price = min_price.unwrap_or(1);
+        let max_price = max_price.unwrap_or(
+            10u64
+                .checked_pow(self.collateral_mint.decimals as u32)
+                .ok_or(PredictionMarketError::MathOverflow)?,
+        );
+        require!(
+            min_price > 0 && min_price < max_price,
+            PredictionMarketError::InvalidPriceBounds
+        );
         self.market.set_inner(Market {
             authority: self.authority.key(),
             market_id,
+            category,
+            in_progress: false,
             settlement_deadline,
             collateral_mint: self.collateral_mint.key(),
             collateral_vault: self.collateral_vault.key(),
@@ -119,7 +354,50 @@ impl<'info> InitializeMarket<'info> {
             winning_outcome: None,
             total_collateral_locked: 0,
             bump: bumps.market,
+            max_collateral: 0,
+            wash_trade_guard: false,
+            reward_rate: 0,
+            incentive_vault: Pubkey::default(),
+            incentive_pool: 0,
+            restricted_transfer,
+            rounding_mode: RoundingMode::FavorProtocol,
+            outcome_a_label,
+            outcome_b_label,
+            vault_bump: bumps.collateral_vault,
+            yes_escrow_bump: bumps.yes_escrow,
+            no_escrow_bump: bumps.no_escrow,
+            price_cumulative: 0,
+            last_price_update: 0,
+            market_kind,
+            settlement_value: 0,
+            yes_redeemed: 0,
+            no_redeemed: 0,
+            min_price,
+            max_price,
+            last_metadata_update: Clock::get()?.unix_timestamp,
+            total_staked: 0,
+            trading_cutoff_buffer: 0,
+            slug,
+            stp_policy: StpPolicy::Skip,
+            lot_size: 0,
+            yes_minted: 0,
+            no_minted: 0,
+            max_order_quantity: 0,
+            book_mode: BookMode::Clob,
+            socialized_loss: false,
+            yes_trading_paused: false,
+            no_trading_paused: false,
+            total_trades: 0,
+            fee_schedule: None,
+            rebasing,
+            treasury_balance: 0,
+            seed_collateral: 0,
+            last_yes_price: 0,
+            last_yes_price_at: 0,
+            last_no_price: 0,
+            last_no_price_at: 0,
         });
+        self.seed_vault(seed_collateral)?;
 
         self.orderbook.set_inner(OrderBook {
             bump: bumps.orderbook,
@@ -129,7 +407,18 @@ impl<'info> InitializeMarket<'info> {
             yes_sell_orders: Vec::new(),
             no_buy_orders: Vec::new(),
             no_sell_orders: Vec::new(),
+            book_hash: [0u8; 32],
+            closed_orders: Vec::new(),
+            closed_orders_head: 0,
         });
+        self.orderbook.refresh_book_hash();
+
+        let registry = &mut self.market_registry;
+        if registry.entries.is_empty() {
+            registry.page = MarketRegistry::page_for(market_id);
+            registry.bump = bumps.market_registry;
+        }
+        registry.list(market_id);
 
         msg!("Market initialized: {}", market_id);
 
@@ -141,6 +430,14 @@ impl<'info> InitializeMarket<'info> {
             outcome_yes_mint: self.outcome_yes_mint.key(),
             outcome_no_mint: self.outcome_no_mint.key(),
             meta_data_url: self.market.meta_data_url.clone(),
+            category,
+            restricted_transfer,
+            outcome_a_label: self.market.outcome_a_label.clone(),
+            outcome_b_label: self.market.outcome_b_label.clone(),
+            market_kind,
+            slug: self.market.slug.clone(),
+            rebasing,
+            seed_collateral,
             timestamp: Clock::get()?.unix_timestamp,
         });
 
@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Config::INIT_SPACE,
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeConfig<'info> {
+    /// One-time setup, the signer becomes the admin. Starts with an empty creator
+    /// allowlist, so market creation stays open until a creator is added, and no
+    /// minimum seed collateral.
+    pub fn handler(&mut self, bumps: &InitializeConfigBumps) -> Result<()> {
+        self.config.set_inner(Config {
+            admin: self.admin.key(),
+            bump: bumps.config,
+            creators: Vec::new(),
+            min_seed_collateral: 0,
+        });
+
+        msg!("Config initialized, admin: {}", self.admin.key());
+
+        Ok(())
+    }
+}
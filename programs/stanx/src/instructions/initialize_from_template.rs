@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::error::*;
+use crate::instructions::*;
+use crate::state::*;
+
+// `init` reads the new market_id from the first instruction argument, so it comes before
+// the template id
+#[derive(Accounts)]
+#[instruction(market_id: u32, template_market_id: u32)]
+pub struct InitializeMarketFromTemplate<'info> {
+    // A closed template no longer deserializes, so it has to still exist
+    #[account(
+        seeds = [MARKET_SEED, template_market_id.to_le_bytes().as_ref()],
+        bump = template.bump,
+        constraint = template.authority == init.authority.key() @ PredictionMarketError::InvalidTemplate,
+        constraint = template.collateral_mint == init.collateral_mint.key() @ PredictionMarketError::InvalidTemplate
+    )]
+    pub template: Box<Account<'info, Market>>,
+
+    pub init: InitializeMarket<'info>,
+}
+
+impl<'info> InitializeMarketFromTemplate<'info> {
+    /// Creates a market with fresh mints, vault, escrows and orderbook, copying the
+    /// template's metadata, labels, kind, price bounds, collateral cap, wash-trade guard,
+    /// rounding mode, trading cutoff, STP policy, lot size, max order quantity, book mode,
+    /// socialized loss, fee schedule and rebasing flag.
+    /// Only the settlement deadline, slug and seed collateral are new.
+    pub fn handler(
+        &mut self,
+        market_id: u32,
+        settlement_deadline: i64,
+        slug: String,
+        seed_collateral: u64,
+        bumps: &InitializeMarketBumps,
+    ) -> Result<()> {
+        let template = &self.template;
+
+        self.init.initialise(
+            market_id,
+            settlement_deadline,
+            bumps,
+            template.meta_data_url.clone(),
+            template.category,
+            template.restricted_transfer,
+            template.outcome_a_label.clone(),
+            template.outcome_b_label.clone(),
+            template.market_kind,
+            Some(template.min_price),
+            Some(template.max_price),
+            slug,
+            template.rebasing,
+            seed_collateral,
+        )?;
+
+        let market = &mut self.init.market;
+        market.max_collateral = template.max_collateral;
+        market.wash_trade_guard = template.wash_trade_guard;
+        market.rounding_mode = template.rounding_mode;
+        market.trading_cutoff_buffer = template.trading_cutoff_buffer;
+        market.stp_policy = template.stp_policy;
+        market.lot_size = template.lot_size;
+        market.max_order_quantity = template.max_order_quantity;
+        market.book_mode = template.book_mode;
+        market.socialized_loss = template.socialized_loss;
+        market.fee_schedule = template.fee_schedule;
+
+        msg!(
+            "Market {} initialized from template {}",
+            market_id,
+            template.market_id
+        );
+
+        Ok(())
+    }
+}
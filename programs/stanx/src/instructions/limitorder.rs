@@ -1,14 +1,16 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program;
 use anchor_spl::{
     associated_token::AssociatedToken,
     token::{self, Transfer},
-    token_interface::{TokenAccount, TokenInterface},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
 use crate::constants::*;
 use crate::error::*;
 use crate::events::*;
 use crate::state::*;
+use crate::transfer_lock::*;
 
 #[derive(Accounts)]
 #[instruction(market_id:u32)]
@@ -34,14 +36,18 @@ pub struct PlaceOrder<'info> {
 
     #[account(
         mut,
-        constraint = collateral_vault.key() == market.collateral_vault
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
+        constraint = collateral_vault.key() == market.collateral_vault,
+        constraint = collateral_vault.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
     )]
     pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         constraint = user_collateral.mint == market.collateral_mint,
-        constraint = user_collateral.owner == user.key()
+        constraint = user_collateral.owner == user.key(),
+        constraint = user_collateral.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
     )]
     pub user_collateral: InterfaceAccount<'info, TokenAccount>,
 
@@ -56,15 +62,27 @@ pub struct PlaceOrder<'info> {
 
     // Declaring them Optional because we don't need them in case of Buy Order, we are only dealing with collateral account &
     // UserStats Account
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = user_outcome_yes.mint == market.outcome_yes_mint @ PredictionMarketError::OutcomeAccountMismatch,
+        constraint = user_outcome_yes.owner == user.key() @ PredictionMarketError::OutcomeAccountMismatch,
+        constraint = user_outcome_yes.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
+    )]
     pub user_outcome_yes: Option<InterfaceAccount<'info, TokenAccount>>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = user_outcome_no.mint == market.outcome_no_mint @ PredictionMarketError::OutcomeAccountMismatch,
+        constraint = user_outcome_no.owner == user.key() @ PredictionMarketError::OutcomeAccountMismatch,
+        constraint = user_outcome_no.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
+    )]
     pub user_outcome_no: Option<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
         constraint = yes_escrow.mint == market.outcome_yes_mint,
+        seeds = [ESCROW_SEED, market.market_id.to_le_bytes().as_ref(), market.outcome_yes_mint.as_ref()],
+        bump = market.yes_escrow_bump,
         constraint = yes_escrow.key() == market.yes_escrow
     )]
     pub yes_escrow: InterfaceAccount<'info, TokenAccount>,
@@ -72,10 +90,33 @@ pub struct PlaceOrder<'info> {
     #[account(
         mut,
         constraint = no_escrow.mint == market.outcome_no_mint,
+        seeds = [ESCROW_SEED, market.market_id.to_le_bytes().as_ref(), market.outcome_no_mint.as_ref()],
+        bump = market.no_escrow_bump,
         constraint = no_escrow.key() == market.no_escrow
     )]
     pub no_escrow: InterfaceAccount<'info, TokenAccount>,
 
+    // Only needed on restricted-transfer markets, to thaw/freeze the outcome account being used
+    #[account(
+        constraint = outcome_mint.key() == market.outcome_yes_mint
+            || outcome_mint.key() == market.outcome_no_mint
+    )]
+    pub outcome_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    // Only needed while the market pays maker rewards (reward_rate > 0)
+    #[account(
+        mut,
+        constraint = incentive_vault.key() == market.incentive_vault
+    )]
+    pub incentive_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // Only needed by stakers, gives the order its stake tier as priority at equal price
+    #[account(
+        constraint = stake_account.market_id == market_id,
+        constraint = stake_account.user == user.key()
+    )]
+    pub stake_account: Option<Box<Account<'info, StakeAccount>>>,
+
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
@@ -93,6 +134,15 @@ impl<'info> PlaceOrder<'info> {
     ///   - If the qty left after all the matching, there are 2 cases, Orderbook Exceeded => remaning Qty is deposited in the claimable assest or
     ///     in the other case, the order is just simply appended to the orderbook
     ///   - Person whose order is on the orderbook first can withdraw collateral from vault separately
+    /// - Order id:
+    ///   - `client_nonce = Some(n)` => id is `Order::client_order_id(user, n)`, known before the tx lands,
+    ///     rejected with `OrderIdCollision` if an order with that id is already resting
+    ///   - `client_nonce = None` => id comes from the global `next_order_id` counter
+    /// - `maker_quote`: post-only, fails with `OrderWouldCross` if it would match anything and
+    ///   with `OrderBookFull` if it can't rest. Otherwise, on a `MakerOnly` book the order has
+    ///   to fill completely and resting a remainder fails with `MakerOnlyBook`.
+    /// - Returns a `PlaceOrderResult` (status, filled and resting quantity) as return data
+    #[allow(clippy::too_many_arguments)]
     pub fn handler(
         &mut self,
         market_id: u32,
@@ -101,34 +151,73 @@ impl<'info> PlaceOrder<'info> {
         quantity: u64,
         price: u64,
         max_iteration: u64,
+        client_nonce: Option<u64>,
+        maker_quote: bool,
         bumps: &PlaceOrderBumps,
         remaining_accounts: &[AccountInfo<'info>],
         program_id: &Pubkey,
-    ) -> Result<()> {
+    ) -> Result<PlaceOrderResult> {
         let market = &mut self.market;
         let orderbook = &mut self.orderbook;
 
-        require!(
-            Clock::get()?.unix_timestamp < market.settlement_deadline,
-            PredictionMarketError::MarketExpired
-        );
-
+        // Checked before the clock so a settled market is always rejected as such, even if
+        // the deadline check would still pass
         require!(
             !market.is_settled,
             PredictionMarketError::MarketAlreadySettled
         );
+        market.check_distinct_holdings()?;
+        market.check_trading_open(Clock::get()?.unix_timestamp)?;
+        market.check_side_open(token_type)?;
 
         require!(
-            max_iteration > 0,
+            max_iteration > 0 && max_iteration <= MAX_ITERATION_CAP,
             PredictionMarketError::InvalidIterationLimit
         );
 
         require!(quantity > 0, PredictionMarketError::InvalidOrderQuantity);
         require!(price > 0, PredictionMarketError::InvalidOrderPrice);
+        market.check_price(price)?;
         require!(
             quantity >= MIN_ORDER_QUANTITY,
             PredictionMarketError::OrderTooSmall
         );
+        market.check_lot_size(quantity)?;
+        market.check_max_order_quantity(quantity)?;
+
+        if maker_quote {
+            let (best_opposite, own_side_len) = match (token_type, side) {
+                (TokenType::Yes, OrderSide::Buy) => (
+                    orderbook.yes_sell_orders.first().map(|o| o.price),
+                    orderbook.yes_buy_orders.len(),
+                ),
+                (TokenType::Yes, OrderSide::Sell) => (
+                    orderbook.yes_buy_orders.first().map(|o| o.price),
+                    orderbook.yes_sell_orders.len(),
+                ),
+                (TokenType::No, OrderSide::Buy) => (
+                    orderbook.no_sell_orders.first().map(|o| o.price),
+                    orderbook.no_buy_orders.len(),
+                ),
+                (TokenType::No, OrderSide::Sell) => (
+                    orderbook.no_buy_orders.first().map(|o| o.price),
+                    orderbook.no_sell_orders.len(),
+                ),
+            };
+            if let Some(best) = best_opposite {
+                require!(
+                    match side {
+                        OrderSide::Buy => price < best,
+                        OrderSide::Sell => price > best,
+                    },
+                    PredictionMarketError::OrderWouldCross
+                );
+            }
+            require!(
+                own_side_len < MAX_ORDERS_PER_SIDE,
+                PredictionMarketError::OrderBookFull
+            );
+        }
 
         // Initialising the user stats account
         let user_stats = &mut self.user_stats_account;
@@ -144,13 +233,28 @@ impl<'info> PlaceOrder<'info> {
             user_stats.bump = bumps.user_stats_account;
         }
 
-        // quantity is in base units (10^6 per display token).
-        // Dividing by TOKEN_DECIMALS_SCALE converts the product to micro USDC.
-        let amount = quantity
-            .checked_mul(price)
-            .ok_or(PredictionMarketError::MathOverflow)?
-            .checked_div(TOKEN_DECIMALS_SCALE)
-            .ok_or(PredictionMarketError::MathOverflow)?;
+        // Fail before locking anything if a counterparty's UserStats wasn't passed
+        let makers = orderbook.preview_makers(
+            &self.user.key(),
+            side,
+            token_type,
+            quantity,
+            price,
+            max_iteration,
+        );
+        require_counterparty_stats(
+            &makers,
+            market_id,
+            remaining_accounts,
+            program_id,
+            match side {
+                OrderSide::Buy => PredictionMarketError::SellerStatsAccountNotProvided,
+                OrderSide::Sell => PredictionMarketError::BuyerStatsAccountNotProvided,
+            },
+        )?;
+
+        // quantity is in base units, price is per whole token, rounded down
+        let amount = Order::collateral_for(quantity, price)?;
 
         require!(
             amount > 0,
@@ -159,56 +263,83 @@ impl<'info> PlaceOrder<'info> {
 
         // Lock funds immediately when placing order
         // For Buyer: lock collateral in Vault, no outcome ATAs needed
-        // For Seller: lock YES/NO tokens in Escrow, outcome ATAs must exist
+        // For Seller: lock YES/NO tokens in Escrow. Deposited tokens are drawn first, they're
+        // already in escrow, the outcome ATA is only needed for the shortfall
         if side == OrderSide::Sell {
-            // Unwrap the relevant outcome account — SELL callers must provide it
-            let (user_token_account, token_escrow) = match token_type {
-                TokenType::Yes => (
-                    self.user_outcome_yes
-                        .as_ref()
-                        .ok_or(PredictionMarketError::OutcomeAccountRequired)?,
-                    &self.yes_escrow,
-                ),
-                TokenType::No => (
-                    self.user_outcome_no
-                        .as_ref()
-                        .ok_or(PredictionMarketError::OutcomeAccountRequired)?,
-                    &self.no_escrow,
-                ),
+            let available = match token_type {
+                TokenType::Yes => self.user_stats_account.available_yes,
+                TokenType::No => self.user_stats_account.available_no,
             };
+            let from_available = available.min(quantity);
+            let to_transfer = quantity - from_available;
+
+            if to_transfer > 0 {
+                // Unwrap the relevant outcome account — SELL callers must provide it
+                let (user_token_account, token_escrow) = match token_type {
+                    TokenType::Yes => (
+                        self.user_outcome_yes
+                            .as_ref()
+                            .ok_or(PredictionMarketError::OutcomeAccountRequired)?,
+                        &self.yes_escrow,
+                    ),
+                    TokenType::No => (
+                        self.user_outcome_no
+                            .as_ref()
+                            .ok_or(PredictionMarketError::OutcomeAccountRequired)?,
+                        &self.no_escrow,
+                    ),
+                };
+
+                require!(
+                    user_token_account.owner == self.user.key(),
+                    PredictionMarketError::InvalidAccountOwner
+                );
+                require!(
+                    user_token_account.mint
+                        == match token_type {
+                            TokenType::Yes => market.outcome_yes_mint,
+                            TokenType::No => market.outcome_no_mint,
+                        },
+                    PredictionMarketError::InvalidMint
+                );
 
-            require!(
-                user_token_account.owner == self.user.key(),
-                PredictionMarketError::InvalidAccountOwner
-            );
-            require!(
-                user_token_account.mint
-                    == match token_type {
-                        TokenType::Yes => market.outcome_yes_mint,
-                        TokenType::No => market.outcome_no_mint,
-                    },
-                PredictionMarketError::InvalidMint
-            );
-
-            require!(
-                user_token_account.amount >= quantity,
-                PredictionMarketError::NotEnoughBalance
-            );
+                require!(
+                    user_token_account.amount >= to_transfer,
+                    PredictionMarketError::NotEnoughBalance
+                );
 
-            token::transfer(
-                CpiContext::new(
-                    self.token_program.to_account_info(),
-                    Transfer {
-                        from: user_token_account.to_account_info(),
-                        to: token_escrow.to_account_info(),
-                        authority: self.user.to_account_info(),
-                    },
-                ),
-                quantity,
-            )?;
+                thaw_if_restricted(
+                    market,
+                    &self.token_program,
+                    user_token_account,
+                    self.outcome_mint.as_ref().map(|m| m.to_account_info()),
+                )?;
+                token::transfer(
+                    CpiContext::new(
+                        self.token_program.to_account_info(),
+                        Transfer {
+                            from: user_token_account.to_account_info(),
+                            to: token_escrow.to_account_info(),
+                            authority: self.user.to_account_info(),
+                        },
+                    ),
+                    to_transfer,
+                )?;
+                freeze_if_restricted(
+                    market,
+                    &self.token_program,
+                    user_token_account,
+                    self.outcome_mint.as_ref().map(|m| m.to_account_info()),
+                )?;
+            }
 
             let user_stats = &mut self.user_stats_account;
 
+            match token_type {
+                TokenType::Yes => user_stats.available_yes -= from_available,
+                TokenType::No => user_stats.available_no -= from_available,
+            }
+
             match token_type {
                 TokenType::Yes => {
                     user_stats.locked_yes = user_stats
@@ -224,39 +355,50 @@ impl<'info> PlaceOrder<'info> {
                 }
             }
         } else {
-            require!(
-                self.user_collateral.amount >= amount,
-                PredictionMarketError::NotEnoughBalance
-            );
-
-            token::transfer(
-                CpiContext::new(
-                    self.token_program.to_account_info(),
-                    Transfer {
-                        from: self.user_collateral.to_account_info(),
-                        to: self.collateral_vault.to_account_info(),
-                        authority: self.user.to_account_info(),
-                    },
-                ),
-                amount,
-            )?;
+            // Draw on deposited collateral first, it's already in the vault,
+            // only the shortfall is transferred
+            let from_available = self.user_stats_account.available_collateral.min(amount);
+            let to_transfer = amount - from_available;
+
+            if to_transfer > 0 {
+                require!(
+                    self.user_collateral.amount >= to_transfer,
+                    PredictionMarketError::NotEnoughBalance
+                );
+                market.check_collateral_cap(to_transfer)?;
+
+                token::transfer(
+                    CpiContext::new(
+                        self.token_program.to_account_info(),
+                        Transfer {
+                            from: self.user_collateral.to_account_info(),
+                            to: self.collateral_vault.to_account_info(),
+                            authority: self.user.to_account_info(),
+                        },
+                    ),
+                    to_transfer,
+                )?;
+
+                // Track vault-level collateral for close_market safety check
+                market.total_collateral_locked = market
+                    .total_collateral_locked
+                    .checked_add(to_transfer)
+                    .ok_or(PredictionMarketError::MathOverflow)?;
+            }
 
             // Locking the collateral
             let user_stats = &mut self.user_stats_account;
+            user_stats.available_collateral -= from_available;
             user_stats.locked_collateral = user_stats
                 .locked_collateral
                 .checked_add(amount)
                 .ok_or(PredictionMarketError::MathOverflow)?;
-
-            // Track vault-level collateral for close_market safety check
-            market.total_collateral_locked = market
-                .total_collateral_locked
-                .checked_add(amount)
-                .ok_or(PredictionMarketError::MathOverflow)?;
         }
 
+        let order_id = orderbook.assign_order_id(&self.user.key(), client_nonce)?;
+
         let mut order = Order {
-            id: orderbook.next_order_id,
+            id: order_id,
             market_id: market.market_id,
             user_key: self.user.key(),
             side,
@@ -265,6 +407,7 @@ impl<'info> PlaceOrder<'info> {
             quantity,
             filledquantity: 0,
             timestamp: Clock::get()?.unix_timestamp,
+            priority: self.stake_account.as_ref().map_or(0, |stake| stake.tier()),
         };
 
         emit!(OrderPlaced {
@@ -278,13 +421,22 @@ impl<'info> PlaceOrder<'info> {
             timestamp: order.timestamp,
         });
 
-        orderbook.next_order_id = orderbook
-            .next_order_id
-            .checked_add(1)
-            .ok_or(PredictionMarketError::MathOverflow)?;
-
         let mut idx = 0;
         let mut iteration = 0;
+        let mut rewards_accrued: u64 = 0;
+        let now = Clock::get()?.unix_timestamp;
+        // Mid before any fill, for the TWAP accumulator
+        let twap_mid = orderbook.yes_mid_price();
+        // Resting orders leaving the book, recorded once the loop releases the borrow on it
+        let mut closed_makers: Vec<(Order, ClosedOrderReason)> = Vec::new();
+        // Set when the STP policy cancels the incoming order, its remainder doesn't rest
+        let mut stp_cancelled = false;
+        // Each maker's fills, written to their UserStats once after the loop
+        let mut maker_credits: Vec<MakerCredit> = Vec::new();
+        // Tokens and collateral exchanged by the incoming order, for LimitOrderExecuted
+        let mut filled_quantity: u64 = 0;
+        let mut filled_collateral: u64 = 0;
+        let mut fills: u64 = 0;
 
         // Get the appropriate order vectors based on token type and side
         let (matching_orders, is_buy_order) = match (token_type, side) {
@@ -296,7 +448,14 @@ impl<'info> PlaceOrder<'info> {
 
         // Iterating through all order to find matching order
         while idx < matching_orders.len() && iteration < max_iteration {
-            let (book_price, book_qty, book_filled_qty, maker_order_id, maker_pubkey) = {
+            let (
+                book_price,
+                book_qty,
+                book_filled_qty,
+                maker_order_id,
+                maker_pubkey,
+                book_timestamp,
+            ) = {
                 let book_order = &matching_orders[idx];
                 (
                     book_order.price,
@@ -304,6 +463,7 @@ impl<'info> PlaceOrder<'info> {
                     book_order.filledquantity,
                     book_order.id,
                     book_order.user_key,
+                    book_order.timestamp,
                 )
             };
 
@@ -315,9 +475,102 @@ impl<'info> PlaceOrder<'info> {
             };
 
             if price_matches {
-                // user cannot match their own orders
+                // user cannot match their own orders, the market's STP policy decides what happens
                 if matching_orders[idx].user_key == self.user.key() {
-                    idx += 1;
+                    let our_left_qty = order
+                        .quantity
+                        .checked_sub(order.filledquantity)
+                        .ok_or(PredictionMarketError::MathOverflow)?;
+                    let book_left_qty = book_qty
+                        .checked_sub(book_filled_qty)
+                        .ok_or(PredictionMarketError::MathOverflow)?;
+                    if our_left_qty == 0 {
+                        break;
+                    }
+
+                    // Quantity taken off the resting order
+                    let prevented_qty = match market.stp_policy {
+                        StpPolicy::Skip => {
+                            idx += 1;
+                            continue;
+                        }
+                        StpPolicy::CancelNewest => {
+                            stp_cancelled = true;
+                            0
+                        }
+                        StpPolicy::CancelOldest => book_left_qty,
+                        StpPolicy::DecrementBoth => {
+                            let qty = our_left_qty.min(book_left_qty);
+                            let release = match side {
+                                OrderSide::Buy => {
+                                    Order::lock_release(our_left_qty, qty, order.price)?
+                                }
+                                OrderSide::Sell => qty,
+                            };
+                            self.user_stats_account
+                                .unlock_to_claimable(side, token_type, release)?;
+                            order.quantity -= qty;
+                            qty
+                        }
+                    };
+
+                    emit!(SelfTradePrevented {
+                        market_id,
+                        user: self.user.key(),
+                        stp_policy: market.stp_policy,
+                        resting_order_id: maker_order_id,
+                        quantity: prevented_qty,
+                        timestamp: now,
+                    });
+
+                    if stp_cancelled {
+                        break;
+                    }
+
+                    // The resting order's part comes off like a cancel: lock to claimable
+                    // plus the maker reward it earned resting
+                    let book_side = matching_orders[idx].side;
+                    let release = match book_side {
+                        OrderSide::Buy => {
+                            Order::lock_release(book_left_qty, prevented_qty, book_price)?
+                        }
+                        OrderSide::Sell => prevented_qty,
+                    };
+                    self.user_stats_account
+                        .unlock_to_claimable(book_side, token_type, release)?;
+
+                    let maker_reward =
+                        market.accrue_maker_reward(prevented_qty, book_timestamp, now)?;
+                    if maker_reward > 0 {
+                        self.user_stats_account.credit_maker_reward(maker_reward)?;
+                        rewards_accrued = rewards_accrued
+                            .checked_add(maker_reward)
+                            .ok_or(PredictionMarketError::MathOverflow)?;
+                        emit!(MakerRewardAccrued {
+                            market_id,
+                            order_id: maker_order_id,
+                            maker: maker_pubkey,
+                            amount: maker_reward,
+                            timestamp: now,
+                        });
+                    }
+
+                    if prevented_qty == book_left_qty {
+                        let resting = matching_orders.remove(idx);
+                        emit!(OrderCancelled {
+                            market_id,
+                            order_id: resting.id,
+                            user: resting.user_key,
+                            side: resting.side,
+                            token_type: resting.token_type,
+                            remaining_quantity: book_left_qty,
+                            timestamp: now,
+                        });
+                        closed_makers.push((resting, ClosedOrderReason::Cancelled));
+                    } else {
+                        matching_orders[idx].quantity -= prevented_qty;
+                        idx += 1;
+                    }
                     continue;
                 }
 
@@ -337,17 +590,39 @@ impl<'info> PlaceOrder<'info> {
 
                 // If book order is empty, remove it and continue
                 if book_left_qty == 0 {
-                    matching_orders.remove(idx);
+                    closed_makers.push((matching_orders.remove(idx), ClosedOrderReason::Filled));
                     continue;
                 }
 
                 let min_qty = our_left_qty.min(book_left_qty);
 
-                let collateral_amount = min_qty
-                    .checked_mul(book_price)
-                    .ok_or(PredictionMarketError::MathOverflow)?
-                    .checked_div(TOKEN_DECIMALS_SCALE)
-                    .ok_or(PredictionMarketError::MathOverflow)?;
+                // The buyer is us on a buy and the resting order otherwise
+                let (buyer_remaining, buyer_price) = if is_buy_order {
+                    (our_left_qty, order.price)
+                } else {
+                    (book_left_qty, book_price)
+                };
+                // The fee comes out of the seller's proceeds, so only the seller's exemption counts
+                let seller_fee_exempt = if is_buy_order {
+                    is_fee_exempt(&maker_pubkey, market_id, remaining_accounts, program_id)?
+                } else {
+                    self.user_stats_account.fee_exempt
+                };
+                let fee_bps = if seller_fee_exempt {
+                    0
+                } else {
+                    market.effective_fee_bps()
+                };
+                let fill = Order::settle_buy_fill(
+                    buyer_remaining,
+                    min_qty,
+                    buyer_price,
+                    book_price,
+                    fee_bps,
+                    market.rounding_mode,
+                    is_buy_order,
+                )?;
+                let collateral_amount = fill.seller_proceeds;
 
                 // Skip if rounding yields zero collateral (prevents free-token exploit)
                 if collateral_amount == 0 {
@@ -365,20 +640,33 @@ impl<'info> PlaceOrder<'info> {
                     .checked_add(min_qty)
                     .ok_or(PredictionMarketError::MathOverflow)?;
 
-                // Credit the appropriate user stats based on whether this is a buy or sell order
-                if is_buy_order {
-                    // collateral the buyer locked for min_qty tokens at their bid price
-                    let locked_at_our_price = min_qty
-                        .checked_mul(order.price)
-                        .ok_or(PredictionMarketError::MathOverflow)?
-                        .checked_div(TOKEN_DECIMALS_SCALE)
-                        .ok_or(PredictionMarketError::MathOverflow)?;
+                market.update_twap(twap_mid, now);
+                market.record_trade(token_type, book_price, now)?;
+                filled_quantity = filled_quantity
+                    .checked_add(min_qty)
+                    .ok_or(PredictionMarketError::MathOverflow)?;
+                filled_collateral = filled_collateral
+                    .checked_add(fill.paid())
+                    .ok_or(PredictionMarketError::MathOverflow)?;
+                fills += 1;
 
-                    // Price improvement surplus: buyer offered more than the fill price
-                    let surplus = locked_at_our_price
-                        .checked_sub(collateral_amount)
+                // Liquidity-mining reward for the maker quantity leaving the book
+                let maker_reward = market.accrue_maker_reward(min_qty, book_timestamp, now)?;
+                if maker_reward > 0 {
+                    rewards_accrued = rewards_accrued
+                        .checked_add(maker_reward)
                         .ok_or(PredictionMarketError::MathOverflow)?;
+                    emit!(MakerRewardAccrued {
+                        market_id,
+                        order_id: maker_order_id,
+                        maker: maker_pubkey,
+                        amount: maker_reward,
+                        timestamp: now,
+                    });
+                }
 
+                // Credit the appropriate user stats based on whether this is a buy or sell order
+                if is_buy_order {
                     match token_type {
                         TokenType::Yes => {
                             self.user_stats_account.claimable_yes = self
@@ -400,75 +688,31 @@ impl<'info> PlaceOrder<'info> {
                     self.user_stats_account.locked_collateral = self
                         .user_stats_account
                         .locked_collateral
-                        .checked_sub(locked_at_our_price)
+                        .checked_sub(fill.released)
                         .ok_or(PredictionMarketError::MathOverflow)?;
 
-                    // Refund the surplus as claimable collateral
-                    if surplus > 0 {
+                    // Refund the price improvement surplus as claimable collateral
+                    if fill.buyer_refund > 0 {
                         self.user_stats_account.claimable_collateral = self
                             .user_stats_account
                             .claimable_collateral
-                            .checked_add(surplus)
+                            .checked_add(fill.buyer_refund)
                             .ok_or(PredictionMarketError::MathOverflow)?;
                     }
 
-                    // Credit SELLER (from matching order) with collateral
-                    // This is a very expensive task,
-                    // to find the PDA, find_program_address (PDA calc) →  ~1,500 CU  ← expensive !
-                    let seller_pubkey = matching_orders[idx].user_key;
-                    let seller_stats_pda = Pubkey::find_program_address(
-                        &[
-                            USER_STATS_SEED,
-                            market.market_id.to_le_bytes().as_ref(),
-                            seller_pubkey.as_ref(),
-                        ],
-                        program_id,
-                    )
-                    .0;
-
-                    let mut seller_credited = false;
-                    for account_info in remaining_accounts.iter() {
-                        if account_info.key == &seller_stats_pda {
-                            require!(
-                                account_info.owner == program_id,
-                                PredictionMarketError::InvalidAccountOwner
-                            );
-                            let mut data = account_info.try_borrow_mut_data()?;
-                            let mut seller_stats = UserStats::try_deserialize(&mut &data[..])?;
-
-                            seller_stats.claimable_collateral = seller_stats
-                                .claimable_collateral
-                                .checked_add(collateral_amount)
-                                .ok_or(PredictionMarketError::MathOverflow)?;
-
-                            // Reduce seller's locked tokens since order was filled
-                            match token_type {
-                                TokenType::Yes => {
-                                    seller_stats.locked_yes = seller_stats
-                                        .locked_yes
-                                        .checked_sub(min_qty)
-                                        .ok_or(PredictionMarketError::MathOverflow)?;
-                                }
-                                TokenType::No => {
-                                    seller_stats.locked_no = seller_stats
-                                        .locked_no
-                                        .checked_sub(min_qty)
-                                        .ok_or(PredictionMarketError::MathOverflow)?;
-                                }
-                            }
-
-                            let mut writer = &mut data[..];
-                            seller_stats.try_serialize(&mut writer)?;
-
-                            seller_credited = true;
-                            break;
-                        }
-                    }
-
-                    require!(
-                        seller_credited,
-                        PredictionMarketError::SellerStatsAccountNotProvided
-                    );
+                    // Credit SELLER (from matching order) with collateral, written after the loop
+                    MakerCredit::record(
+                        &mut maker_credits,
+                        MakerCredit {
+                            maker: maker_pubkey,
+                            quantity: min_qty,
+                            collateral: collateral_amount,
+                            released: 0,
+                            refund: 0,
+                            reward: maker_reward,
+                            fee: fill.fee,
+                        },
+                    )?;
 
                     msg!(
                         "Trade: Buyer +{} claimable {:?}, Seller +{} claimable collateral",
@@ -502,62 +746,22 @@ impl<'info> PlaceOrder<'info> {
                         }
                     }
 
-                    // Credit BUYER (from matching order) with YES/NO tokens
-                    let buyer_pubkey = matching_orders[idx].user_key;
-                    let buyer_stats_pda = Pubkey::find_program_address(
-                        &[
-                            USER_STATS_SEED,
-                            market.market_id.to_le_bytes().as_ref(),
-                            buyer_pubkey.as_ref(),
-                        ],
-                        program_id,
-                    )
-                    .0;
-
-                    let mut buyer_credited = false;
-                    for account_info in remaining_accounts.iter() {
-                        if account_info.key == &buyer_stats_pda {
-                            require!(
-                                account_info.owner == program_id,
-                                PredictionMarketError::InvalidAccountOwner
-                            );
-                            let mut data = account_info.try_borrow_mut_data()?;
-                            let mut buyer_stats = UserStats::try_deserialize(&mut &data[..])?;
-
-                            match token_type {
-                                TokenType::Yes => {
-                                    buyer_stats.claimable_yes = buyer_stats
-                                        .claimable_yes
-                                        .checked_add(min_qty)
-                                        .ok_or(PredictionMarketError::MathOverflow)?;
-                                }
-                                TokenType::No => {
-                                    buyer_stats.claimable_no = buyer_stats
-                                        .claimable_no
-                                        .checked_add(min_qty)
-                                        .ok_or(PredictionMarketError::MathOverflow)?;
-                                }
-                            }
-
-                            // collateral_amount = min_qty * book_price = what the buyer locked per token.
-                            // The buyer IS the book order, so book_price == their bid price.
-                            buyer_stats.locked_collateral = buyer_stats
-                                .locked_collateral
-                                .checked_sub(collateral_amount)
-                                .ok_or(PredictionMarketError::MathOverflow)?;
-
-                            let mut writer = &mut data[..];
-                            buyer_stats.try_serialize(&mut writer)?;
-
-                            buyer_credited = true;
-                            break;
-                        }
-                    }
-
-                    require!(
-                        buyer_credited,
-                        PredictionMarketError::BuyerStatsAccountNotProvided
-                    );
+                    // Credit BUYER (from matching order) with YES/NO tokens, written after the
+                    // loop. The buyer IS the book order, so book_price == their bid price: the
+                    // lock for the filled part is released and rounding dust beyond what the
+                    // seller is paid goes back to the buyer as claimable.
+                    MakerCredit::record(
+                        &mut maker_credits,
+                        MakerCredit {
+                            maker: maker_pubkey,
+                            quantity: min_qty,
+                            collateral: collateral_amount,
+                            released: fill.released,
+                            refund: fill.buyer_refund,
+                            reward: maker_reward,
+                            fee: 0,
+                        },
+                    )?;
 
                     msg!(
                         "Trade: Seller +{} claimable collateral, Buyer +{} claimable {:?}",
@@ -580,9 +784,27 @@ impl<'info> PlaceOrder<'info> {
                     timestamp: Clock::get()?.unix_timestamp,
                 });
 
+                // The fee comes out of the seller's proceeds and moves to the treasury
+                if fill.fee > 0 {
+                    market.collect_fee(fill.fee)?;
+                    let (payer, kind) = if is_buy_order {
+                        (maker_pubkey, FeeKind::Maker)
+                    } else {
+                        self.user_stats_account.pay_fee(fill.fee)?;
+                        (self.user.key(), FeeKind::Taker)
+                    };
+                    emit!(FeeCollected {
+                        market_id,
+                        payer,
+                        amount: fill.fee,
+                        kind,
+                        timestamp: Clock::get()?.unix_timestamp,
+                    });
+                }
+
                 // Remove completed orders or advance to next
                 if matching_orders[idx].filledquantity >= matching_orders[idx].quantity {
-                    matching_orders.remove(idx);
+                    closed_makers.push((matching_orders.remove(idx), ClosedOrderReason::Filled));
                     // Don't increment idx since we removed the element
                 } else {
                     idx += 1;
@@ -596,9 +818,69 @@ impl<'info> PlaceOrder<'info> {
             }
         }
 
+        for credit in maker_credits.iter() {
+            credit.flush(
+                market.market_id,
+                token_type,
+                is_buy_order,
+                remaining_accounts,
+                program_id,
+            )?;
+        }
+
+        for (maker_order, reason) in closed_makers {
+            orderbook.record_closed(maker_order, reason, now);
+        }
+
+        if filled_quantity > 0 {
+            emit!(LimitOrderExecuted {
+                market_id,
+                order_id: order.id,
+                user: self.user.key(),
+                side,
+                token_type,
+                price,
+                initial_quantity: quantity,
+                filled_quantity,
+                orders_matched: fills,
+                avg_fill_price: Order::average_price(filled_collateral, filled_quantity)?,
+                timestamp: now,
+            });
+        }
+
+        // Move the maker rewards credited above from the incentive vault into the collateral
+        // vault, where claimable_collateral is paid out from
+        if rewards_accrued > 0 {
+            let incentive_vault = self
+                .incentive_vault
+                .as_ref()
+                .ok_or(PredictionMarketError::IncentiveVaultRequired)?;
+            let market_id_bytes = market.market_id.to_le_bytes();
+            let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[market.bump]];
+
+            token::transfer(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    Transfer {
+                        from: incentive_vault.to_account_info(),
+                        to: self.collateral_vault.to_account_info(),
+                        authority: market.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                rewards_accrued,
+            )?;
+
+            market.total_collateral_locked = market
+                .total_collateral_locked
+                .checked_add(rewards_accrued)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+        }
+
         // If order is not fully filled
         // 1. If orderbook side is full, Transfer unfilled quantity to claimable
         // 2. If orderbook side is not full, append the unfilled quantity on the book
+        let mut resting_quantity = 0;
         if order.filledquantity < order.quantity {
             let unfilled_qty = order
                 .quantity
@@ -612,14 +894,11 @@ impl<'info> PlaceOrder<'info> {
                 (TokenType::No, OrderSide::Sell) => &mut orderbook.no_sell_orders,
             };
 
-            // Transfer the assets to claimable if orderbook side is full
-            if order_vec.len() >= MAX_ORDERS_PER_SIDE {
+            // Transfer the assets to claimable if orderbook side is full, or the STP policy
+            // cancelled the incoming order
+            if stp_cancelled || order_vec.len() >= MAX_ORDERS_PER_SIDE {
                 if side == OrderSide::Buy {
-                    let unfilled_collateral = unfilled_qty
-                        .checked_mul(order.price)
-                        .ok_or(PredictionMarketError::MathOverflow)?
-                        .checked_div(TOKEN_DECIMALS_SCALE)
-                        .ok_or(PredictionMarketError::MathOverflow)?;
+                    let unfilled_collateral = Order::collateral_for(unfilled_qty, order.price)?;
 
                     self.user_stats_account.locked_collateral = self
                         .user_stats_account
@@ -664,27 +943,344 @@ impl<'info> PlaceOrder<'info> {
                 }
 
                 msg!(
-                    "Orderbook full: {} unfilled quantity moved to claimable (IOC cancelled)",
+                    "{}: {} unfilled quantity moved to claimable (IOC cancelled)",
+                    if stp_cancelled {
+                        "Self-trade prevented"
+                    } else {
+                        "Orderbook full"
+                    },
                     unfilled_qty
                 );
             } else {
+                require!(
+                    maker_quote || market.book_mode == BookMode::Clob,
+                    PredictionMarketError::MakerOnlyBook
+                );
                 order_vec.push(order);
+                resting_quantity = unfilled_qty;
 
-                // Keeping buy orders sorted highest price first, sell orders lowest price first
-                if side == OrderSide::Buy {
-                    order_vec.sort_by(|a, b| b.price.cmp(&a.price));
-                } else {
-                    order_vec.sort_by(|a, b| a.price.cmp(&b.price));
-                }
+                self.user_stats_account.last_order_slot = Clock::get()?.slot;
+                self.user_stats_account.last_order_side = side;
+                self.user_stats_account.last_order_token_type = token_type;
+
+                OrderBook::sort_side(order_vec, side);
             }
         }
 
+        orderbook.refresh_book_hash();
+
+        grow_orderbook_if_needed(
+            orderbook,
+            self.user.to_account_info(),
+            self.system_program.to_account_info(),
+            market_id,
+        )?;
+
         msg!(
             "Order processed: {} filled, {} remaining",
             order.filledquantity,
             order.quantity - order.filledquantity
         );
 
+        Ok(PlaceOrderResult::new(
+            order.quantity,
+            order.filledquantity,
+            resting_quantity,
+        ))
+    }
+}
+
+/// Fails with `missing_error` unless `remaining_accounts` holds the UserStats of every maker,
+/// logging the PDAs still to be passed. Only a presence check to fail before any funds move,
+/// the matching loop still checks each account against its PDA.
+pub fn require_counterparty_stats(
+    makers: &[Pubkey],
+    market_id: u32,
+    remaining_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    missing_error: PredictionMarketError,
+) -> Result<()> {
+    let mut provided: Vec<Pubkey> = Vec::new();
+    for account_info in remaining_accounts.iter() {
+        if account_info.owner != program_id {
+            continue;
+        }
+        if let Ok(stats) = UserStats::try_deserialize(&mut &account_info.try_borrow_data()?[..]) {
+            if stats.market_id == market_id {
+                provided.push(stats.user);
+            }
+        }
+    }
+
+    let missing: Vec<Pubkey> = makers
+        .iter()
+        .filter(|maker| !provided.contains(maker))
+        .map(|maker| {
+            Pubkey::find_program_address(
+                &[
+                    USER_STATS_SEED,
+                    market_id.to_le_bytes().as_ref(),
+                    maker.as_ref(),
+                ],
+                program_id,
+            )
+            .0
+        })
+        .collect();
+
+    if !missing.is_empty() {
+        msg!("Missing counterparty UserStats accounts: {:?}", missing);
+        return Err(missing_error.into());
+    }
+
+    Ok(())
+}
+
+/// Whether `user`'s UserStats in `remaining_accounts` is marked `fee_exempt`. A user whose
+/// UserStats isn't passed counts as not exempt, crediting their fill fails later anyway.
+pub fn is_fee_exempt(
+    user: &Pubkey,
+    market_id: u32,
+    remaining_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> Result<bool> {
+    let stats_pda = Pubkey::find_program_address(
+        &[
+            USER_STATS_SEED,
+            market_id.to_le_bytes().as_ref(),
+            user.as_ref(),
+        ],
+        program_id,
+    )
+    .0;
+    for account_info in remaining_accounts.iter() {
+        if account_info.key == &stats_pda && account_info.owner == program_id {
+            let stats = UserStats::try_deserialize(&mut &account_info.try_borrow_data()?[..])?;
+            return Ok(stats.fee_exempt);
+        }
+    }
+    Ok(false)
+}
+
+/// One maker's fills in a matching loop, not yet written to their UserStats
+///
+/// `place_order` and `market_order` add up every fill against the same maker and write the
+/// totals once after the loop, instead of deserializing and serializing the maker's UserStats
+/// per fill. Every field only adds up, so the result is the same as crediting each fill.
+pub struct MakerCredit {
+    pub maker: Pubkey,
+    // Outcome base units traded
+    pub quantity: u64,
+    // Collateral paid to the seller
+    pub collateral: u64,
+    // Collateral released from a maker buyer's lock
+    pub released: u64,
+    // Part of `released` that goes back to a maker buyer as claimable collateral
+    pub refund: u64,
+    pub reward: u64,
+    // Trading fee a maker seller paid out of `collateral`
+    pub fee: u64,
+}
+
+impl MakerCredit {
+    /// Adds one fill's amounts to the entry for `fill.maker` in `credits`, creating it on the
+    /// first fill
+    pub fn record(credits: &mut Vec<MakerCredit>, fill: MakerCredit) -> Result<()> {
+        let Some(credit) = credits.iter_mut().find(|c| c.maker == fill.maker) else {
+            credits.push(fill);
+            return Ok(());
+        };
+        credit.quantity = credit
+            .quantity
+            .checked_add(fill.quantity)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        credit.collateral = credit
+            .collateral
+            .checked_add(fill.collateral)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        credit.released = credit
+            .released
+            .checked_add(fill.released)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        credit.refund = credit
+            .refund
+            .checked_add(fill.refund)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        credit.reward = credit
+            .reward
+            .checked_add(fill.reward)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        credit.fee = credit
+            .fee
+            .checked_add(fill.fee)
+            .ok_or(PredictionMarketError::MathOverflow)?;
         Ok(())
     }
+
+    /// Writes the totals to the maker's UserStats, passed in `remaining_accounts`
+    ///
+    /// `maker_is_seller` is true when the taker bought: the maker gets collateral for locked
+    /// tokens. Otherwise the maker bought and gets tokens for locked collateral.
+    pub fn flush<'info>(
+        &self,
+        market_id: u32,
+        token_type: TokenType,
+        maker_is_seller: bool,
+        remaining_accounts: &[AccountInfo<'info>],
+        program_id: &Pubkey,
+    ) -> Result<()> {
+        // This is a very expensive task,
+        // to find the PDA, find_program_address (PDA calc) →  ~1,500 CU  ← expensive !
+        let maker_stats_pda = Pubkey::find_program_address(
+            &[
+                USER_STATS_SEED,
+                market_id.to_le_bytes().as_ref(),
+                self.maker.as_ref(),
+            ],
+            program_id,
+        )
+        .0;
+
+        let account_info = remaining_accounts
+            .iter()
+            .find(|account_info| account_info.key == &maker_stats_pda)
+            .ok_or(if maker_is_seller {
+                PredictionMarketError::SellerStatsAccountNotProvided
+            } else {
+                PredictionMarketError::BuyerStatsAccountNotProvided
+            })?;
+        require!(
+            account_info.owner == program_id,
+            PredictionMarketError::InvalidAccountOwner
+        );
+        let mut data = account_info.try_borrow_mut_data()?;
+        let mut maker_stats = UserStats::try_deserialize(&mut &data[..])?;
+
+        if maker_is_seller {
+            // Credit Seller (from matching order) with collateral
+            maker_stats.claimable_collateral = maker_stats
+                .claimable_collateral
+                .checked_add(self.collateral)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+
+            let locked_field = match token_type {
+                TokenType::Yes => &mut maker_stats.locked_yes,
+                TokenType::No => &mut maker_stats.locked_no,
+            };
+            *locked_field = locked_field
+                .checked_sub(self.quantity)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+        } else {
+            // Credit BUYER (maker) with YES/NO tokens and release their locked collateral.
+            let claimable_field = match token_type {
+                TokenType::Yes => &mut maker_stats.claimable_yes,
+                TokenType::No => &mut maker_stats.claimable_no,
+            };
+            *claimable_field = claimable_field
+                .checked_add(self.quantity)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+
+            // Rounding dust beyond what the seller is paid goes back to the buyer as claimable.
+            maker_stats.locked_collateral = maker_stats
+                .locked_collateral
+                .checked_sub(self.released)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+            maker_stats.claimable_collateral = maker_stats
+                .claimable_collateral
+                .checked_add(self.refund)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+        }
+        maker_stats.pay_fee(self.fee)?;
+        maker_stats.credit_maker_reward(self.reward)?;
+
+        let mut writer = &mut data[..];
+        maker_stats.try_serialize(&mut writer)
+    }
+}
+
+/// Grow the orderbook account in ORDERBOOK_GROWTH_BATCH steps once the resting orders no
+/// longer fit, `payer` covers the extra rent
+pub fn grow_orderbook_if_needed<'info>(
+    orderbook: &Account<'info, OrderBook>,
+    payer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    market_id: u32,
+) -> Result<()> {
+    let orderbook_info = orderbook.to_account_info();
+    let old_space = orderbook_info.data_len();
+    if orderbook.current_space_needed() <= old_space {
+        return Ok(());
+    }
+
+    let new_space = orderbook
+        .space_with_growth(ORDERBOOK_GROWTH_BATCH)
+        .min(OrderBook::space(MAX_ORDERS_PER_SIDE));
+    let rent_delta = Rent::get()?
+        .minimum_balance(new_space)
+        .saturating_sub(orderbook_info.lamports());
+
+    if rent_delta > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program,
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: orderbook_info.clone(),
+                },
+            ),
+            rent_delta,
+        )?;
+    }
+
+    resize_orderbook(orderbook, market_id, new_space)?;
+
+    emit!(OrderBookResized {
+        market_id,
+        old_space: old_space as u64,
+        new_space: new_space as u64,
+        payer: payer.key(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Resizes the orderbook account to `new_space` bytes, keeping its state
+///
+/// `resize` only changes the data length, the deserialized `orderbook` is written back over
+/// it on exit, so `market_id`, `next_order_id` and `bump` carry over. Every realloc path goes
+/// through here and never rebuilds the account with `set_inner`: a reset `next_order_id` would
+/// hand out ids that resting and closed orders already use.
+///
+/// Checks the stored header before resizing: the account must belong to `market_id` and the
+/// in-memory `next_order_id` may only have moved forward.
+pub fn resize_orderbook<'info>(
+    orderbook: &Account<'info, OrderBook>,
+    market_id: u32,
+    new_space: usize,
+) -> Result<()> {
+    let orderbook_info = orderbook.to_account_info();
+    {
+        // Header after the discriminator: market_id (u32) then next_order_id (u64)
+        let data = orderbook_info.try_borrow_data()?;
+        let stored_market_id = u32::from_le_bytes(
+            data[8..12]
+                .try_into()
+                .map_err(|_| PredictionMarketError::OrderBookStateMismatch)?,
+        );
+        let stored_next_order_id = u64::from_le_bytes(
+            data[12..20]
+                .try_into()
+                .map_err(|_| PredictionMarketError::OrderBookStateMismatch)?,
+        );
+        require!(
+            orderbook.market_id == market_id
+                && stored_market_id == market_id
+                && orderbook.next_order_id >= stored_next_order_id,
+            PredictionMarketError::OrderBookStateMismatch
+        );
+    }
+
+    orderbook_info.resize(new_space)?;
+    Ok(())
 }
@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct GetMarketConfig<'info> {
+    #[account(
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> GetMarketConfig<'info> {
+    /// Read-only view of the market's trading parameters, returned through return data
+    pub fn handler(&self) -> Result<MarketConfig> {
+        Ok(MarketConfig::new(&self.market))
+    }
+}
@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct GetMarketStatus<'info> {
+    #[account(
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [ORDERBOOK_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+        constraint = orderbook.market_id == market_id
+    )]
+    pub orderbook: Account<'info, OrderBook>,
+}
+
+impl<'info> GetMarketStatus<'info> {
+    /// Read-only view of settlement state and whether `close_market` would succeed,
+    /// returned through return data
+    pub fn handler(&self) -> Result<MarketStatus> {
+        Ok(MarketStatus::new(&self.market, &self.orderbook))
+    }
+}
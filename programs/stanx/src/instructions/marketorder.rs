@@ -1,7 +1,7 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{self, Transfer},
+    token::{self, MintTo, Transfer},
     token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
@@ -9,6 +9,8 @@ use crate::constants::*;
 use crate::error::*;
 use crate::state::*;
 use crate::events::*;
+use crate::transfer_lock::*;
+use crate::instructions::{grow_orderbook_if_needed, is_fee_exempt, MakerCredit};
 
 #[derive(Accounts)]
 #[instruction(market_id:u32)]
@@ -34,6 +36,8 @@ pub struct MarketOrder<'info> {
 
     #[account(
         mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
         constraint = collateral_vault.key() == market.collateral_vault
     )]
     pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
@@ -41,7 +45,8 @@ pub struct MarketOrder<'info> {
     #[account(
         mut,
         constraint = user_collateral.mint == market.collateral_mint,
-        constraint = user_collateral.owner == user.key()
+        constraint = user_collateral.owner == user.key(),
+        constraint = user_collateral.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
     )]
     pub user_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
 
@@ -54,10 +59,19 @@ pub struct MarketOrder<'info> {
     )]
     pub user_stats_account: Box<Account<'info, UserStats>>,
 
-    #[account(constraint = outcome_yes_mint.key() == market.outcome_yes_mint)]
+    // mut for auto_mint
+    #[account(
+        mut,
+        constraint = outcome_yes_mint.key() == market.outcome_yes_mint,
+        constraint = outcome_yes_mint.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
+    )]
     pub outcome_yes_mint: Box<InterfaceAccount<'info, Mint>>,
 
-    #[account(constraint = outcome_no_mint.key() == market.outcome_no_mint)]
+    #[account(
+        mut,
+        constraint = outcome_no_mint.key() == market.outcome_no_mint,
+        constraint = outcome_no_mint.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
+    )]
     pub outcome_no_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
@@ -81,6 +95,8 @@ pub struct MarketOrder<'info> {
     #[account(
         mut,
         constraint = yes_escrow.mint == market.outcome_yes_mint,
+        seeds = [ESCROW_SEED, market.market_id.to_le_bytes().as_ref(), market.outcome_yes_mint.as_ref()],
+        bump = market.yes_escrow_bump,
         constraint = yes_escrow.key() == market.yes_escrow
     )]
     pub yes_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
@@ -88,16 +104,35 @@ pub struct MarketOrder<'info> {
     #[account(
         mut,
         constraint = no_escrow.mint == market.outcome_no_mint,
+        seeds = [ESCROW_SEED, market.market_id.to_le_bytes().as_ref(), market.outcome_no_mint.as_ref()],
+        bump = market.no_escrow_bump,
         constraint = no_escrow.key() == market.no_escrow
     )]
     pub no_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    // Only needed while the market pays maker rewards (reward_rate > 0)
+    #[account(
+        mut,
+        constraint = incentive_vault.key() == market.incentive_vault
+    )]
+    pub incentive_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
 }
 
 impl<'info> MarketOrder<'info> {
+    /// Fills `order_amount` (collateral for a buy, tokens for a sell) against the book and
+    /// refunds what isn't filled, unless one of the remainder options is set:
+    /// - `auto_mint` (buys only): the unfilled collateral is split into YES+NO pairs like
+    ///   `split_tokens`, so the taker gets the requested token at a price of 1.0 plus the
+    ///   opposite leg
+    /// - `rest_remainder_at`: the remainder rests as a limit order at that price, like the
+    ///   remainder of a `place_order`. It's still refunded when the book side is full, the
+    ///   remainder is below `MIN_ORDER_QUANTITY` at that price, or it would cross orders
+    ///   matching left on the opposite side (`max_iteration` ran out or levels were skipped)
+    #[allow(clippy::too_many_arguments)]
     pub fn handler(
         &mut self,
         market_id: u32,
@@ -105,6 +140,8 @@ impl<'info> MarketOrder<'info> {
         token_type: TokenType,
         order_amount: u64,
         max_iteration: u64,
+        auto_mint: bool,
+        rest_remainder_at: Option<u64>,
         bumps: &MarketOrderBumps,
         remaining_accounts: &[AccountInfo<'info>],
         program_id: &Pubkey,
@@ -112,18 +149,18 @@ impl<'info> MarketOrder<'info> {
         let market = &mut self.market;
         let orderbook = &mut self.orderbook;
 
-        require!(
-            Clock::get()?.unix_timestamp < market.settlement_deadline,
-            PredictionMarketError::MarketExpired
-        );
-
+        // Checked before the clock so a settled market is always rejected as such, even if
+        // the deadline check would still pass
         require!(
             !market.is_settled,
             PredictionMarketError::MarketAlreadySettled
         );
+        market.check_distinct_holdings()?;
+        market.check_trading_open(Clock::get()?.unix_timestamp)?;
+        market.check_side_open(token_type)?;
 
         require!(
-            max_iteration > 0,
+            max_iteration > 0 && max_iteration <= MAX_ITERATION_CAP,
             PredictionMarketError::InvalidIterationLimit
         );
 
@@ -132,6 +169,24 @@ impl<'info> MarketOrder<'info> {
             PredictionMarketError::InvalidAmount
         );
 
+        require!(
+            !auto_mint || side == OrderSide::Buy,
+            PredictionMarketError::AutoMintBuyOnly
+        );
+
+        require!(
+            !auto_mint || rest_remainder_at.is_none(),
+            PredictionMarketError::ConflictingRemainderOptions
+        );
+
+        if let Some(rest_price) = rest_remainder_at {
+            market.check_price(rest_price)?;
+            require!(
+                market.book_mode == BookMode::Clob,
+                PredictionMarketError::MakerOnlyBook
+            );
+        }
+
         // For SELL orders order_amount is the quantity of YES/NO tokens in base units.
         // Enforce minimum to prevent amount/TOKEN_DECIMALS_SCALE truncating to zero.
         if side == OrderSide::Sell {
@@ -139,6 +194,7 @@ impl<'info> MarketOrder<'info> {
                 order_amount >= MIN_ORDER_QUANTITY,
                 PredictionMarketError::OrderTooSmall
             );
+            market.check_lot_size(order_amount)?;
         }
 
         let user_stats: &mut Box<Account<'_, UserStats>> = &mut self.user_stats_account;
@@ -154,6 +210,20 @@ impl<'info> MarketOrder<'info> {
             user_stats.bump = bumps.user_stats_account;
         }
 
+        // Wash-trade cooldown (opt-in per market): reject crossing the side of your own order that
+        // was rested in this slot or the previous one. It's only a heuristic, it catches the
+        // same-wallet place-then-cross pattern but not wallets controlled by the same person,
+        // and waiting two slots gets around it.
+        if market.wash_trade_guard && user_stats.last_order_slot != 0 {
+            let current_slot = Clock::get()?.slot;
+            let crosses_own_order = user_stats.last_order_token_type == token_type
+                && user_stats.last_order_side != side;
+            require!(
+                !crosses_own_order || current_slot > user_stats.last_order_slot.saturating_add(1),
+                PredictionMarketError::WashTradeCooldown
+            );
+        }
+
         // Checking balance in account before locking funds
         match side {
             OrderSide::Buy => {
@@ -161,20 +231,35 @@ impl<'info> MarketOrder<'info> {
                     self.user_collateral.amount >= order_amount,
                     PredictionMarketError::NotEnoughBalance
                 );
+                market.check_collateral_cap(order_amount)?;
             }
             OrderSide::Sell => {
-                let user_token_account = match token_type {
-                    TokenType::Yes => &self.user_outcome_yes,
-                    TokenType::No => &self.user_outcome_no,
+                let (user_token_account, available) = match token_type {
+                    TokenType::Yes => (&self.user_outcome_yes, user_stats.available_yes),
+                    TokenType::No => (&self.user_outcome_no, user_stats.available_no),
                 };
 
                 require!(
-                    user_token_account.amount >= order_amount,
+                    user_token_account.amount >= order_amount.saturating_sub(available),
                     PredictionMarketError::NotEnoughBalance
                 );
             }
         }
 
+        // Both outcome accounts can receive or send tokens below (fills, auto-mint, refunds)
+        thaw_if_restricted(
+            market,
+            &self.token_program,
+            &self.user_outcome_yes,
+            Some(self.outcome_yes_mint.to_account_info()),
+        )?;
+        thaw_if_restricted(
+            market,
+            &self.token_program,
+            &self.user_outcome_no,
+            Some(self.outcome_no_mint.to_account_info()),
+        )?;
+
         // Locking of Funds
         if side == OrderSide::Buy {
             // Locking the collateral in the Collateral Vault
@@ -203,25 +288,41 @@ impl<'info> MarketOrder<'info> {
                 .checked_add(order_amount)
                 .ok_or(PredictionMarketError::MathOverflow)?;
         } else {
-            // Locking the tokens in the Escrow
-            let (user_token_account, token_escrow) = match token_type {
-                TokenType::Yes => (&self.user_outcome_yes, &self.yes_escrow),
-                TokenType::No => (&self.user_outcome_no, &self.no_escrow),
+            // Locking the tokens in the Escrow, deposited tokens first
+            let (user_token_account, token_escrow, available) = match token_type {
+                TokenType::Yes => (
+                    &self.user_outcome_yes,
+                    &self.yes_escrow,
+                    self.user_stats_account.available_yes,
+                ),
+                TokenType::No => (
+                    &self.user_outcome_no,
+                    &self.no_escrow,
+                    self.user_stats_account.available_no,
+                ),
             };
+            let from_available = available.min(order_amount);
+            let to_transfer = order_amount - from_available;
 
-            token::transfer(
-                CpiContext::new(
-                    self.token_program.to_account_info(),
-                    Transfer {
-                        from: user_token_account.to_account_info(),
-                        to: token_escrow.to_account_info(),
-                        authority: self.user.to_account_info(),
-                    },
-                ),
-                order_amount,
-            )?;
+            if to_transfer > 0 {
+                token::transfer(
+                    CpiContext::new(
+                        self.token_program.to_account_info(),
+                        Transfer {
+                            from: user_token_account.to_account_info(),
+                            to: token_escrow.to_account_info(),
+                            authority: self.user.to_account_info(),
+                        },
+                    ),
+                    to_transfer,
+                )?;
+            }
 
             let user_stats = &mut self.user_stats_account;
+            match token_type {
+                TokenType::Yes => user_stats.available_yes -= from_available,
+                TokenType::No => user_stats.available_no -= from_available,
+            }
             let locked_field = match token_type {
                 TokenType::Yes => &mut user_stats.locked_yes,
                 TokenType::No => &mut user_stats.locked_no,
@@ -236,6 +337,9 @@ impl<'info> MarketOrder<'info> {
         // 1. we will iterate over the orderbook, & calculate the iterations, Then we will leave the order at that time
         // 2. Segreagte the logic & then generalise the Buy/Sell, Yes/No token
 
+        // Mid before any fill, for the TWAP accumulator
+        let twap_mid = orderbook.yes_mid_price();
+
         let (matching_orders, is_buy_order) = match (token_type, side) {
             (TokenType::Yes, OrderSide::Buy) => (&mut orderbook.yes_sell_orders, true),
             (TokenType::Yes, OrderSide::Sell) => (&mut orderbook.yes_buy_orders, false),
@@ -245,18 +349,39 @@ impl<'info> MarketOrder<'info> {
 
         let mut idx = 0;
         let mut iteration = 0;
+        let mut rewards_accrued: u64 = 0;
+        let now = Clock::get()?.unix_timestamp;
         let mut remaining_amount: u64 = order_amount;
+        // Collateral the fills exchanged, fees included, for the average price
+        let mut filled_collateral: u64 = 0;
         let mut fullfilled_qty: u64 = 0; // Tokens in case of Buy // Collateral in case of selling
+        // Resting orders leaving the book, recorded once the loop releases the borrow on it
+        let mut closed_makers: Vec<(Order, ClosedOrderReason)> = Vec::new();
+        // Each maker's fills, written to their UserStats once after the loop
+        let mut maker_credits: Vec<MakerCredit> = Vec::new();
+        // Set when the STP policy cancels the incoming order, its remainder is refunded
+        let mut stp_cancelled = false;
+        // What the caller asked for, order_amount shrinks if DecrementBoth takes some of it
+        let requested_amount = order_amount;
+        let mut order_amount = order_amount;
 
         while idx < matching_orders.len() && iteration < max_iteration && remaining_amount > 0 {
-            let (book_price, book_qty, book_filled_qty, maker_pubkey, maker_order_id) = {
+            let (
+                book_price,
+                book_qty,
+                book_filled_qty,
+                maker_pubkey,
+                maker_order_id,
+                book_timestamp,
+            ) = {
                 let book_order = &matching_orders[idx];
                 (
                     book_order.price,
                     book_order.quantity,
                     book_order.filledquantity,
                     book_order.user_key,
-                    book_order.id
+                    book_order.id,
+                    book_order.timestamp,
                 )
             };
 
@@ -266,13 +391,108 @@ impl<'info> MarketOrder<'info> {
 
             // Skip empty orders
             if book_remaining_qty == 0 {
-                matching_orders.remove(idx);
+                closed_makers.push((matching_orders.remove(idx), ClosedOrderReason::Filled));
                 continue;
             }
 
-            // Prevent self-trading — do NOT consume an iteration for skips
+            // Prevent self-trading, the market's STP policy decides what happens.
+            // Do NOT consume an iteration for these
             if matching_orders[idx].user_key == self.user.key() {
-                idx += 1;
+                // Quantity taken off the resting order
+                let prevented_qty = match market.stp_policy {
+                    StpPolicy::Skip => {
+                        idx += 1;
+                        continue;
+                    }
+                    StpPolicy::CancelNewest => {
+                        stp_cancelled = true;
+                        0
+                    }
+                    StpPolicy::CancelOldest => book_remaining_qty,
+                    StpPolicy::DecrementBoth => {
+                        // Same sizing as a fill at the resting price
+                        let qty = match side {
+                            OrderSide::Buy => remaining_amount
+                                .checked_mul(TOKEN_DECIMALS_SCALE)
+                                .ok_or(PredictionMarketError::MathOverflow)?
+                                .checked_div(book_price)
+                                .ok_or(PredictionMarketError::MathOverflow)?
+                                .min(book_remaining_qty),
+                            OrderSide::Sell => remaining_amount.min(book_remaining_qty),
+                        };
+                        let amount = match side {
+                            OrderSide::Buy => Order::collateral_for(qty, book_price)?,
+                            OrderSide::Sell => qty,
+                        };
+                        if amount == 0 {
+                            idx += 1;
+                            continue;
+                        }
+                        self.user_stats_account
+                            .unlock_to_claimable(side, token_type, amount)?;
+                        remaining_amount -= amount;
+                        order_amount -= amount;
+                        qty
+                    }
+                };
+
+                emit!(SelfTradePrevented {
+                    market_id,
+                    user: self.user.key(),
+                    stp_policy: market.stp_policy,
+                    resting_order_id: maker_order_id,
+                    quantity: prevented_qty,
+                    timestamp: now,
+                });
+
+                if stp_cancelled {
+                    break;
+                }
+
+                // The resting order's part comes off like a cancel: lock to claimable
+                // plus the maker reward it earned resting
+                let book_side = matching_orders[idx].side;
+                let release = match book_side {
+                    OrderSide::Buy => {
+                        Order::lock_release(book_remaining_qty, prevented_qty, book_price)?
+                    }
+                    OrderSide::Sell => prevented_qty,
+                };
+                self.user_stats_account
+                    .unlock_to_claimable(book_side, token_type, release)?;
+
+                let maker_reward =
+                    market.accrue_maker_reward(prevented_qty, book_timestamp, now)?;
+                if maker_reward > 0 {
+                    self.user_stats_account.credit_maker_reward(maker_reward)?;
+                    rewards_accrued = rewards_accrued
+                        .checked_add(maker_reward)
+                        .ok_or(PredictionMarketError::MathOverflow)?;
+                    emit!(MakerRewardAccrued {
+                        market_id,
+                        order_id: maker_order_id,
+                        maker: maker_pubkey,
+                        amount: maker_reward,
+                        timestamp: now,
+                    });
+                }
+
+                if prevented_qty == book_remaining_qty {
+                    let resting = matching_orders.remove(idx);
+                    emit!(OrderCancelled {
+                        market_id,
+                        order_id: resting.id,
+                        user: resting.user_key,
+                        side: resting.side,
+                        token_type: resting.token_type,
+                        remaining_quantity: book_remaining_qty,
+                        timestamp: now,
+                    });
+                    closed_makers.push((resting, ClosedOrderReason::Cancelled));
+                } else {
+                    matching_orders[idx].quantity -= prevented_qty;
+                    idx += 1;
+                }
                 continue;
             }
 
@@ -296,12 +516,42 @@ impl<'info> MarketOrder<'info> {
                 continue;
             }
 
-            // collateral = base_units × µUSDC_per_display_token / scale = µUSDC
-            let collateral_amount = book_price
-                .checked_mul(min_qty)
-                .ok_or(PredictionMarketError::MathOverflow)?
-                .checked_div(TOKEN_DECIMALS_SCALE)
-                .ok_or(PredictionMarketError::MathOverflow)?;
+            // The fee comes out of the seller's proceeds, so only the seller's exemption counts
+            let seller_fee_exempt = if is_buy_order {
+                is_fee_exempt(&maker_pubkey, market_id, remaining_accounts, program_id)?
+            } else {
+                self.user_stats_account.fee_exempt
+            };
+            let fee_bps = if seller_fee_exempt {
+                0
+            } else {
+                market.effective_fee_bps()
+            };
+            let fill = if is_buy_order {
+                // collateral = base_units × µUSDC_per_display_token / scale = µUSDC. A taker buy
+                // can always afford the rounded value (min_qty was sized from the budget)
+                let value = Order::compute_fill_value(
+                    min_qty,
+                    book_price,
+                    OUTCOME_TOKEN_DECIMALS,
+                    fee_bps,
+                    market.rounding_mode,
+                    is_buy_order,
+                )?;
+                BuyFill::split(value.gross, value)
+            } else {
+                // A maker buyer never pays more than its lock releases for this fill
+                Order::settle_buy_fill(
+                    book_remaining_qty,
+                    min_qty,
+                    book_price,
+                    book_price,
+                    fee_bps,
+                    market.rounding_mode,
+                    is_buy_order,
+                )?
+            };
+            let collateral_amount = fill.seller_proceeds;
 
             // Skip if rounding yields zero collateral (prevents free-token exploit)
             if collateral_amount == 0 {
@@ -317,7 +567,7 @@ impl<'info> MarketOrder<'info> {
             match side {
                 OrderSide::Buy => {
                     remaining_amount = remaining_amount
-                        .checked_sub(collateral_amount)
+                        .checked_sub(fill.paid())
                         .ok_or(PredictionMarketError::MathOverflow)?;
                     fullfilled_qty = fullfilled_qty
                         .checked_add(min_qty)
@@ -332,123 +582,48 @@ impl<'info> MarketOrder<'info> {
                         .ok_or(PredictionMarketError::MathOverflow)?;
                 }
             }
+            filled_collateral = filled_collateral
+                .checked_add(fill.paid())
+                .ok_or(PredictionMarketError::MathOverflow)?;
 
-            // Here transfering the Claimable assets to the other party only,
-            // For the user who has placed order, Assets will be directly transffered later
-            if is_buy_order {
-                // Credit Seller (from matching order) with collateral
-                let seller_pubkey = matching_orders[idx].user_key;
-                let seller_stats_pda = Pubkey::find_program_address(
-                    &[
-                        USER_STATS_SEED,
-                        market.market_id.to_le_bytes().as_ref(),
-                        seller_pubkey.as_ref(),
-                    ],
-                    program_id,
-                )
-                .0;
-                let mut seller_credited = false;
-
-                for account_info in remaining_accounts.iter() {
-                    if account_info.key == &seller_stats_pda {
-                        require!(
-                            account_info.owner == program_id,
-                            PredictionMarketError::InvalidAccountOwner
-                        );
-                        let mut data = account_info.try_borrow_mut_data()?;
-                        let mut seller_stats = UserStats::try_deserialize(&mut &data[..])?;
-
-                        seller_stats.claimable_collateral = seller_stats
-                            .claimable_collateral
-                            .checked_add(collateral_amount)
-                            .ok_or(PredictionMarketError::MathOverflow)?;
+            market.update_twap(twap_mid, now);
+            market.record_trade(token_type, book_price, now)?;
 
-                        match token_type {
-                            TokenType::Yes => {
-                                seller_stats.locked_yes = seller_stats
-                                    .locked_yes
-                                    .checked_sub(min_qty)
-                                    .ok_or(PredictionMarketError::MathOverflow)?;
-                            }
-                            TokenType::No => {
-                                seller_stats.locked_no = seller_stats
-                                    .locked_no
-                                    .checked_sub(min_qty)
-                                    .ok_or(PredictionMarketError::MathOverflow)?;
-                            }
-                        }
-                        let mut writer = &mut data[..];
-                        seller_stats.try_serialize(&mut writer)?;
+            // Liquidity-mining reward for the maker quantity leaving the book
+            let maker_reward = market.accrue_maker_reward(min_qty, book_timestamp, now)?;
+            if maker_reward > 0 {
+                rewards_accrued = rewards_accrued
+                    .checked_add(maker_reward)
+                    .ok_or(PredictionMarketError::MathOverflow)?;
+                emit!(MakerRewardAccrued {
+                    market_id,
+                    order_id: maker_order_id,
+                    maker: maker_pubkey,
+                    amount: maker_reward,
+                    timestamp: now,
+                });
+            }
 
-                        seller_credited = true;
-                        break;
-                    }
-                }
-                require!(
-                    seller_credited,
-                    PredictionMarketError::SellerStatsAccountNotProvided
-                );
+            // Here transfering the Claimable assets to the other party only,
+            // For the user who has placed order, Assets will be directly transffered later.
+            // A maker buyer gets back whatever its lock releases beyond what it paid
+            let (buyer_release, maker_refund) = if is_buy_order {
+                (0, 0)
             } else {
-                // Credit BUYER (maker) with YES/NO tokens and release their locked collateral.
-                // The buyer's order IS the book order — book_price is their bid price.
-                // So collateral_amount = min_qty * book_price is exactly what they locked.
-                let buyer_pubkey = matching_orders[idx].user_key;
-                let buyer_stats_pda = Pubkey::find_program_address(
-                    &[
-                        USER_STATS_SEED,
-                        market.market_id.to_le_bytes().as_ref(),
-                        buyer_pubkey.as_ref(),
-                    ],
-                    program_id,
-                )
-                .0;
-                let mut buyer_credited = false;
-
-                for account_info in remaining_accounts.iter() {
-                    if account_info.key == &buyer_stats_pda {
-                        require!(
-                            account_info.owner == program_id,
-                            PredictionMarketError::InvalidAccountOwner
-                        );
-                        let mut data = account_info.try_borrow_mut_data()?;
-                        let mut buyer_stats = UserStats::try_deserialize(&mut &data[..])?;
-
-                        match token_type {
-                            TokenType::Yes => {
-                                buyer_stats.claimable_yes = buyer_stats
-                                    .claimable_yes
-                                    .checked_add(min_qty)
-                                    .ok_or(PredictionMarketError::MathOverflow)?;
-                            }
-                            TokenType::No => {
-                                buyer_stats.claimable_no = buyer_stats
-                                    .claimable_no
-                                    .checked_add(min_qty)
-                                    .ok_or(PredictionMarketError::MathOverflow)?;
-                            }
-                        }
-
-                        // Release the collateral the buyer locked for this fill.
-                        // collateral_amount = min_qty * book_price = min_qty * buyer's bid price.
-                        buyer_stats.locked_collateral = buyer_stats
-                            .locked_collateral
-                            .checked_sub(collateral_amount)
-                            .ok_or(PredictionMarketError::MathOverflow)?;
-
-                        let mut writer = &mut data[..];
-                        buyer_stats.try_serialize(&mut writer)?;
-
-                        buyer_credited = true;
-                        break;
-                    }
-                }
-                // total_collateral_locked for the paid-out collateral is handled
-                // once in the post-loop via fullfilled_qty — do not decrement here.
-                require!(
-                    buyer_credited,
-                    PredictionMarketError::BuyerStatsAccountNotProvided
-                );
-            }
+                (fill.released, fill.buyer_refund)
+            };
+            MakerCredit::record(
+                &mut maker_credits,
+                MakerCredit {
+                    maker: maker_pubkey,
+                    quantity: min_qty,
+                    collateral: collateral_amount,
+                    released: buyer_release,
+                    refund: maker_refund,
+                    reward: maker_reward,
+                    fee: if is_buy_order { fill.fee } else { 0 },
+                },
+            )?;
 
             emit!(OrderMatched {
                 market_id,
@@ -463,9 +638,27 @@ impl<'info> MarketOrder<'info> {
                 timestamp: Clock::get()?.unix_timestamp,
             });
 
+            // The fee comes out of the seller's proceeds and moves to the treasury
+            if fill.fee > 0 {
+                market.collect_fee(fill.fee)?;
+                let (payer, kind) = if is_buy_order {
+                    (maker_pubkey, FeeKind::Maker)
+                } else {
+                    self.user_stats_account.pay_fee(fill.fee)?;
+                    (self.user.key(), FeeKind::Taker)
+                };
+                emit!(FeeCollected {
+                    market_id,
+                    payer,
+                    amount: fill.fee,
+                    kind,
+                    timestamp: Clock::get()?.unix_timestamp,
+                });
+            }
+
             // Remove completed orders or advance to next
             if matching_orders[idx].filledquantity >= matching_orders[idx].quantity {
-                matching_orders.remove(idx);
+                closed_makers.push((matching_orders.remove(idx), ClosedOrderReason::Filled));
                 // we will not increment idx, but we must continue to avoid incrementing it below
                 iteration += 1;
                 continue;
@@ -475,6 +668,223 @@ impl<'info> MarketOrder<'info> {
             iteration += 1;
         }
 
+        for credit in maker_credits.iter() {
+            credit.flush(
+                market.market_id,
+                token_type,
+                is_buy_order,
+                remaining_accounts,
+                program_id,
+            )?;
+        }
+
+        for (maker_order, reason) in closed_makers {
+            orderbook.record_closed(maker_order, reason, now);
+        }
+        orderbook.refresh_book_hash();
+
+        // What the fills consumed of order_amount, before the remainder is auto-minted,
+        // rested or refunded below
+        let matched_amount = order_amount - remaining_amount;
+        let filled_tokens = match side {
+            OrderSide::Buy => fullfilled_qty,
+            OrderSide::Sell => matched_amount,
+        };
+        let avg_price = Order::average_price(filled_collateral, filled_tokens)?;
+
+        // Move the maker rewards credited above from the incentive vault into the collateral
+        // vault, where claimable_collateral is paid out from
+        if rewards_accrued > 0 {
+            let incentive_vault = self
+                .incentive_vault
+                .as_ref()
+                .ok_or(PredictionMarketError::IncentiveVaultRequired)?;
+            let market_id_bytes = market.market_id.to_le_bytes();
+            let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[market.bump]];
+
+            token::transfer(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    Transfer {
+                        from: incentive_vault.to_account_info(),
+                        to: self.collateral_vault.to_account_info(),
+                        authority: market.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                rewards_accrued,
+            )?;
+
+            market.total_collateral_locked = market
+                .total_collateral_locked
+                .checked_add(rewards_accrued)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+        }
+
+        // Split what the book couldn't fill into pairs, the collateral is already in the vault
+        // and stays there backing them
+        if auto_mint && !stp_cancelled && remaining_amount > 0 {
+            Market::check_mint_supply(
+                self.outcome_yes_mint.supply,
+                self.outcome_no_mint.supply,
+                remaining_amount,
+            )?;
+            Market::check_mint_authority(
+                market.key(),
+                self.outcome_yes_mint.mint_authority.into(),
+                self.outcome_no_mint.mint_authority.into(),
+            )?;
+            let market_id_bytes = market.market_id.to_le_bytes();
+            let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[market.bump]];
+
+            for (mint, account) in [
+                (&self.outcome_yes_mint, &self.user_outcome_yes),
+                (&self.outcome_no_mint, &self.user_outcome_no),
+            ] {
+                token::mint_to(
+                    CpiContext::new_with_signer(
+                        self.token_program.to_account_info(),
+                        MintTo {
+                            mint: mint.to_account_info(),
+                            to: account.to_account_info(),
+                            authority: market.to_account_info(),
+                        },
+                        &[seeds],
+                    ),
+                    remaining_amount,
+                )?;
+            }
+
+            market.record_mint(remaining_amount)?;
+
+            msg!("Auto-minted {} outcome token pairs", remaining_amount);
+
+            emit!(TokensSplit {
+                market_id,
+                user: self.user.key(),
+                amount: remaining_amount,
+                timestamp: now,
+            });
+
+            // Counted as filled: released from the lock below with the spent collateral
+            remaining_amount = 0;
+
+            // Off by default, the reloads cost compute on every call
+            #[cfg(feature = "invariant-checks")]
+            {
+                self.outcome_yes_mint.reload()?;
+                self.outcome_no_mint.reload()?;
+                market.check_supply_balanced(
+                    self.outcome_yes_mint.supply,
+                    self.outcome_no_mint.supply,
+                )?;
+            }
+        }
+
+        // Rest the unfilled part instead of refunding it. The lock taken above already covers
+        // it, only the part the resting order doesn't need is refunded below.
+        let mut refund_amount = remaining_amount;
+        if let Some(rest_price) =
+            rest_remainder_at.filter(|_| !stp_cancelled && remaining_amount > 0)
+        {
+            // Whole lots up to the max order quantity only, the rest is refunded
+            let rest_qty = market.round_to_lot(
+                market.cap_order_quantity(match side {
+                    OrderSide::Buy => remaining_amount
+                        .checked_mul(TOKEN_DECIMALS_SCALE)
+                        .ok_or(PredictionMarketError::MathOverflow)?
+                        .checked_div(rest_price)
+                        .ok_or(PredictionMarketError::MathOverflow)?,
+                    OrderSide::Sell => remaining_amount,
+                }),
+            );
+            let rest_lock = match side {
+                OrderSide::Buy => Order::collateral_for(rest_qty, rest_price)?,
+                OrderSide::Sell => rest_qty,
+            };
+
+            let side_len = match (token_type, side) {
+                (TokenType::Yes, OrderSide::Buy) => orderbook.yes_buy_orders.len(),
+                (TokenType::Yes, OrderSide::Sell) => orderbook.yes_sell_orders.len(),
+                (TokenType::No, OrderSide::Buy) => orderbook.no_buy_orders.len(),
+                (TokenType::No, OrderSide::Sell) => orderbook.no_sell_orders.len(),
+            };
+
+            // Matching may have stopped before reaching every order at or better than
+            // rest_price, resting there would leave the book crossed
+            let crosses = match side {
+                OrderSide::Buy => orderbook
+                    .best_sell(token_type)
+                    .is_some_and(|best| rest_price >= best),
+                OrderSide::Sell => orderbook
+                    .best_buy(token_type)
+                    .is_some_and(|best| rest_price <= best),
+            };
+            if crosses {
+                msg!(
+                    "Remainder at {} would cross the book, refunding it",
+                    rest_price
+                );
+            }
+
+            if rest_qty >= MIN_ORDER_QUANTITY
+                && rest_lock > 0
+                && side_len < MAX_ORDERS_PER_SIDE
+                && !crosses
+            {
+                let order = Order {
+                    id: orderbook.assign_order_id(&self.user.key(), None)?,
+                    market_id: market.market_id,
+                    user_key: self.user.key(),
+                    side,
+                    token_type,
+                    price: rest_price,
+                    quantity: rest_qty,
+                    filledquantity: 0,
+                    timestamp: now,
+                    // Remainders of market orders don't get stake priority
+                    priority: 0,
+                };
+
+                emit!(OrderPlaced {
+                    market_id,
+                    order_id: order.id,
+                    user: self.user.key(),
+                    side,
+                    token_type,
+                    price: rest_price,
+                    quantity: rest_qty,
+                    timestamp: now,
+                });
+
+                let order_vec = match (token_type, side) {
+                    (TokenType::Yes, OrderSide::Buy) => &mut orderbook.yes_buy_orders,
+                    (TokenType::Yes, OrderSide::Sell) => &mut orderbook.yes_sell_orders,
+                    (TokenType::No, OrderSide::Buy) => &mut orderbook.no_buy_orders,
+                    (TokenType::No, OrderSide::Sell) => &mut orderbook.no_sell_orders,
+                };
+                order_vec.push(order);
+
+                OrderBook::sort_side(order_vec, side);
+                orderbook.refresh_book_hash();
+
+                let user_stats = &mut self.user_stats_account;
+                user_stats.last_order_slot = Clock::get()?.slot;
+                user_stats.last_order_side = side;
+                user_stats.last_order_token_type = token_type;
+
+                refund_amount = remaining_amount
+                    .checked_sub(rest_lock)
+                    .ok_or(PredictionMarketError::MathOverflow)?;
+
+                msg!(
+                    "Rested {} unfilled quantity at price {}",
+                    rest_qty,
+                    rest_price
+                );
+            }
+        }
+
         // Transfering assets to the user who has placed the order right away
         match side {
             OrderSide::Buy => {
@@ -512,9 +922,8 @@ impl<'info> MarketOrder<'info> {
                     .checked_sub(collateral_spent)
                     .ok_or(PredictionMarketError::MathOverflow)?;
 
-
                 // Returning remaining collateral if any remains
-                if remaining_amount > 0 {
+                if refund_amount > 0 {
                     let market_id_bytes = market.market_id.to_le_bytes();
                     let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[market.bump]];
 
@@ -528,22 +937,22 @@ impl<'info> MarketOrder<'info> {
                             },
                             &[seeds],
                         ),
-                        remaining_amount,
+                        refund_amount,
                     )?;
 
                     // Reduce locked collateral for the returned amount
                     user_stats.locked_collateral = user_stats
                         .locked_collateral
-                        .checked_sub(remaining_amount)
+                        .checked_sub(refund_amount)
                         .ok_or(PredictionMarketError::MathOverflow)?;
 
                     // Track vault-level collateral leaving
                     market.total_collateral_locked = market
                         .total_collateral_locked
-                        .checked_sub(remaining_amount)
+                        .checked_sub(refund_amount)
                         .ok_or(PredictionMarketError::MathOverflow)?;
 
-                    msg!("Returned {} remaining collateral to user", remaining_amount);
+                    msg!("Returned {} remaining collateral to user", refund_amount);
                 }
             }
             OrderSide::Sell => {
@@ -593,7 +1002,7 @@ impl<'info> MarketOrder<'info> {
                 }
 
                 // Returning remaining tokens if any remain
-                if remaining_amount > 0 {
+                if refund_amount > 0 {
                     let (user_token_account, token_escrow) = match token_type {
                         TokenType::Yes => (&self.user_outcome_yes, &self.yes_escrow),
                         TokenType::No => (&self.user_outcome_no, &self.no_escrow),
@@ -612,7 +1021,7 @@ impl<'info> MarketOrder<'info> {
                             },
                             &[seeds],
                         ),
-                        remaining_amount,
+                        refund_amount,
                     )?;
 
                     // Reduce locked tokens for the returned amount
@@ -620,41 +1029,61 @@ impl<'info> MarketOrder<'info> {
                         TokenType::Yes => {
                             user_stats.locked_yes = user_stats
                                 .locked_yes
-                                .checked_sub(remaining_amount)
+                                .checked_sub(refund_amount)
                                 .ok_or(PredictionMarketError::MathOverflow)?;
                         }
                         TokenType::No => {
                             user_stats.locked_no = user_stats
                                 .locked_no
-                                .checked_sub(remaining_amount)
+                                .checked_sub(refund_amount)
                                 .ok_or(PredictionMarketError::MathOverflow)?;
                         }
                     }
 
-                    msg!("Returned {} remaining tokens to user", remaining_amount);
+                    msg!("Returned {} remaining tokens to user", refund_amount);
                 }
             }
         }
 
         msg!(
-            "Market order: filled {}, remaining {}",
-            order_amount - remaining_amount,
-            remaining_amount
+            "Market order: filled {} at avg price {}, unmatched {}",
+            matched_amount,
+            avg_price,
+            order_amount - matched_amount
         );
 
-        
+        freeze_if_restricted(
+            &self.market,
+            &self.token_program,
+            &self.user_outcome_yes,
+            Some(self.outcome_yes_mint.to_account_info()),
+        )?;
+        freeze_if_restricted(
+            &self.market,
+            &self.token_program,
+            &self.user_outcome_no,
+            Some(self.outcome_no_mint.to_account_info()),
+        )?;
+
+        grow_orderbook_if_needed(
+            &self.orderbook,
+            self.user.to_account_info(),
+            self.system_program.to_account_info(),
+            market_id,
+        )?;
 
         emit!(MarketOrderExecuted {
             market_id,
             user: self.user.key(),
             side,
             token_type,
-            initial_quantity : order_amount,
-            filled_quantity : order_amount - remaining_amount,
+            initial_quantity : requested_amount,
+            filled_quantity : matched_amount,
             orders_matched: iteration,
+            avg_price,
             timestamp: Clock::get()?.unix_timestamp,
         });
 
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,71 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::error::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct MatchPreview<'info> {
+    /// CHECK: the taker, only used to skip their own resting orders
+    pub user: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [ORDERBOOK_SEED, market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+    )]
+    pub orderbook: Account<'info, OrderBook>,
+}
+
+impl<'info> MatchPreview<'info> {
+    /// Read-only walk of the book for a `place_order` with the same arguments, returning the
+    /// counterparty UserStats PDAs to pass in its `remaining_accounts`
+    ///
+    /// For a `market_order` pass the widest price (`u64::MAX` to buy, `0` to sell) and the
+    /// quantity in outcome base units. The book can change before the real order lands, so
+    /// the list is a snapshot.
+    #[allow(clippy::too_many_arguments)]
+    pub fn handler(
+        &self,
+        market_id: u32,
+        side: OrderSide,
+        token_type: TokenType,
+        quantity: u64,
+        price: u64,
+        max_iteration: u64,
+        program_id: &Pubkey,
+    ) -> Result<Vec<Pubkey>> {
+        require!(
+            max_iteration > 0 && max_iteration <= MAX_ITERATION_CAP,
+            PredictionMarketError::InvalidIterationLimit
+        );
+
+        let makers = self.orderbook.preview_makers(
+            &self.user.key(),
+            side,
+            token_type,
+            quantity,
+            price,
+            max_iteration,
+        );
+        require!(
+            makers.len() <= MAX_PREVIEW_COUNTERPARTIES,
+            PredictionMarketError::PreviewTooLarge
+        );
+
+        Ok(makers
+            .iter()
+            .map(|maker| {
+                Pubkey::find_program_address(
+                    &[
+                        USER_STATS_SEED,
+                        market_id.to_le_bytes().as_ref(),
+                        maker.as_ref(),
+                    ],
+                    program_id,
+                )
+                .0
+            })
+            .collect())
+    }
+}
@@ -8,6 +8,7 @@ use crate::constants::*;
 use crate::error::*;
 use crate::events::*;
 use crate::state::Market;
+use crate::transfer_lock::*;
 
 #[derive(Accounts)]
 #[instruction(market_id: u32)]
@@ -26,25 +27,30 @@ pub struct MergeTokens<'info> {
     #[account(
         mut,
         constraint = user_collateral.mint == market.collateral_mint,
-        constraint = user_collateral.owner == user.key()
+        constraint = user_collateral.owner == user.key(),
+        constraint = user_collateral.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
     )]
     pub user_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
         constraint = collateral_vault.key() == market.collateral_vault
     )]
     pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
-        constraint = outcome_yes_mint.key() == market.outcome_yes_mint
+        constraint = outcome_yes_mint.key() == market.outcome_yes_mint,
+        constraint = outcome_yes_mint.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
     )]
     pub outcome_yes_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
         mut,
-        constraint = outcome_no_mint.key() == market.outcome_no_mint
+        constraint = outcome_no_mint.key() == market.outcome_no_mint,
+        constraint = outcome_no_mint.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
     )]
     pub outcome_no_mint: Box<InterfaceAccount<'info, Mint>>,
 
@@ -65,6 +71,8 @@ pub struct MergeTokens<'info> {
 }
 
 impl<'info> MergeTokens<'info> {
+    /// Deliberately not gated on `is_settled`: after a `Neither` resolution merging is how
+    /// holders of paired tokens get their collateral back
     pub fn merge_tokens(&mut self, _market_id: u32, amount: u64) -> Result<()> {
         // User specifies exactly how many YES+NO token pairs to merge back into collateral.
         // Both balances must be >= amount, since we burn equal quantities of each.
@@ -78,6 +86,19 @@ impl<'info> MergeTokens<'info> {
             PredictionMarketError::NotEnoughBalance
         );
 
+        thaw_if_restricted(
+            &self.market,
+            &self.token_program,
+            &self.user_outcome_yes,
+            Some(self.outcome_yes_mint.to_account_info()),
+        )?;
+        thaw_if_restricted(
+            &self.market,
+            &self.token_program,
+            &self.user_outcome_no,
+            Some(self.outcome_no_mint.to_account_info()),
+        )?;
+
         token::burn(
             CpiContext::new(
                 self.token_program.to_account_info(),
@@ -100,6 +121,29 @@ impl<'info> MergeTokens<'info> {
             ),
             amount,
         )?;
+        self.market.record_burn(amount, amount)?;
+
+        freeze_if_restricted(
+            &self.market,
+            &self.token_program,
+            &self.user_outcome_yes,
+            Some(self.outcome_yes_mint.to_account_info()),
+        )?;
+        freeze_if_restricted(
+            &self.market,
+            &self.token_program,
+            &self.user_outcome_no,
+            Some(self.outcome_no_mint.to_account_info()),
+        )?;
+
+        let nominal = Market::merge_collateral_return(
+            amount,
+            0, // no merge fee yet
+            self.market.rounding_mode,
+        )?;
+        let collateral_returned = self
+            .market
+            .rebased_payout(nominal, self.collateral_vault.amount)?;
 
         let market_id_bytes = self.market.market_id.to_le_bytes();
         let market_bump = self.market.bump;
@@ -115,13 +159,13 @@ impl<'info> MergeTokens<'info> {
                 },
                 &[seeds],
             ),
-            amount,
+            collateral_returned,
         )?;
 
         self.market.total_collateral_locked = self
             .market
             .total_collateral_locked
-            .checked_sub(amount)
+            .checked_sub(nominal)
             .ok_or(PredictionMarketError::MathOverflow)?;
 
         msg!(
@@ -129,10 +173,20 @@ impl<'info> MergeTokens<'info> {
             amount
         );
 
+        // Off by default, the reloads cost compute on every call
+        #[cfg(feature = "invariant-checks")]
+        {
+            self.outcome_yes_mint.reload()?;
+            self.outcome_no_mint.reload()?;
+            self.market
+                .check_supply_balanced(self.outcome_yes_mint.supply, self.outcome_no_mint.supply)?;
+        }
+
         emit!(TokensMerged {
             market_id: self.market.market_id,
             user: self.user.key(),
-            amount,
+            burned: amount,
+            collateral_returned,
             timestamp: Clock::get()?.unix_timestamp,
         });
 
@@ -0,0 +1,146 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+use anchor_spl::token_interface::Mint;
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::{LegacyMarket, Market};
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct MigrateMarket<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    // Kept unchecked because a market created before `category` doesn't deserialize as
+    // `Account<Market>`: every field after `market_id` has moved
+    /// CHECK: seeds + owner are validated here, discriminator is validated in the handler
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [MARKET_SEED, market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: UncheckedAccount<'info>,
+
+    // Checked against the legacy market in the handler
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+    pub outcome_yes_mint: InterfaceAccount<'info, Mint>,
+    pub outcome_no_mint: InterfaceAccount<'info, Mint>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateMarket<'info> {
+    /// Rewrite a market created before `category` in the current `Market` layout
+    ///
+    /// - Legacy fields keep their values, every newer field starts at the default
+    ///   `initialize_market` uses, with the price bounds derived from the collateral decimals
+    ///   and `yes_minted`/`no_minted` taken from the outcome mint supplies
+    /// - The payer covers the rent difference
+    /// - Calling it on an account already in the current layout is a no-op
+    pub fn handler(&mut self, market_id: u32) -> Result<()> {
+        let market_info = self.market.to_account_info();
+        let legacy_space = Market::DISCRIMINATOR.len() + LegacyMarket::INIT_SPACE;
+        let target_space = Market::DISCRIMINATOR.len() + Market::INIT_SPACE;
+
+        let legacy = {
+            let data = market_info.try_borrow_data()?;
+            require!(
+                data.len() >= Market::DISCRIMINATOR.len()
+                    && &data[..Market::DISCRIMINATOR.len()] == Market::DISCRIMINATOR,
+                anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+            );
+
+            if data.len() >= target_space {
+                msg!("Market already up to date ({} bytes)", data.len());
+                return Ok(());
+            }
+            require!(
+                data.len() == legacy_space,
+                PredictionMarketError::UnknownMarketLayout
+            );
+            LegacyMarket::deserialize(&mut &data[Market::DISCRIMINATOR.len()..])?
+        };
+
+        require!(
+            legacy.collateral_mint == self.collateral_mint.key()
+                && legacy.outcome_yes_mint == self.outcome_yes_mint.key()
+                && legacy.outcome_no_mint == self.outcome_no_mint.key(),
+            PredictionMarketError::InvalidMint
+        );
+
+        let market_id_bytes = market_id.to_le_bytes();
+        let (_, vault_bump) =
+            Pubkey::find_program_address(&[VAULT_SEED, market_id_bytes.as_ref()], &crate::ID);
+        let (_, yes_escrow_bump) = Pubkey::find_program_address(
+            &[
+                ESCROW_SEED,
+                market_id_bytes.as_ref(),
+                legacy.outcome_yes_mint.as_ref(),
+            ],
+            &crate::ID,
+        );
+        let (_, no_escrow_bump) = Pubkey::find_program_address(
+            &[
+                ESCROW_SEED,
+                market_id_bytes.as_ref(),
+                legacy.outcome_no_mint.as_ref(),
+            ],
+            &crate::ID,
+        );
+        let max_price = 10u64
+            .checked_pow(self.collateral_mint.decimals as u32)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        let mut market = Market::from_legacy(
+            legacy,
+            vault_bump,
+            yes_escrow_bump,
+            no_escrow_bump,
+            max_price,
+            Clock::get()?.unix_timestamp,
+        );
+        market.yes_minted = self.outcome_yes_mint.supply;
+        market.no_minted = self.outcome_no_mint.supply;
+
+        let required_lamports = Rent::get()?.minimum_balance(target_space);
+        let rent_delta = required_lamports.saturating_sub(market_info.lamports());
+
+        if rent_delta > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    Transfer {
+                        from: self.payer.to_account_info(),
+                        to: market_info.clone(),
+                    },
+                ),
+                rent_delta,
+            )?;
+        }
+
+        market_info.resize(target_space)?;
+        {
+            let mut data = market_info.try_borrow_mut_data()?;
+            market.try_serialize(&mut &mut data[..])?;
+        }
+
+        msg!(
+            "Market migrated from {} to {} bytes",
+            legacy_space,
+            target_space
+        );
+
+        emit!(MarketMigrated {
+            market_id,
+            payer: self.payer.key(),
+            old_space: legacy_space as u64,
+            new_space: target_space as u64,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
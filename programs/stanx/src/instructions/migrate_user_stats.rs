@@ -0,0 +1,91 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+
+use crate::constants::*;
+use crate::events::*;
+use crate::state::UserStats;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct MigrateUserStats<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: only used to derive the UserStats PDA, anyone can pay to migrate a user's account
+    pub user: UncheckedAccount<'info>,
+
+    // Kept unchecked because accounts created before new fields were added are too small
+    // to deserialize as `Account<UserStats>`
+    /// CHECK: seeds + owner are validated here, discriminator is validated in the handler
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub user_stats_account: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateUserStats<'info> {
+    /// Grow an existing UserStats account to the current `UserStats` layout
+    ///
+    /// - New bytes are zero-initialized, so every appended field starts at its default
+    /// - The payer covers the rent difference
+    /// - Calling it on an already up-to-date account is a no-op
+    pub fn handler(&mut self, market_id: u32) -> Result<()> {
+        let stats_info = self.user_stats_account.to_account_info();
+        let target_space = UserStats::DISCRIMINATOR.len() + UserStats::INIT_SPACE;
+
+        {
+            let data = stats_info.try_borrow_data()?;
+            require!(
+                data.len() >= UserStats::DISCRIMINATOR.len()
+                    && &data[..UserStats::DISCRIMINATOR.len()] == UserStats::DISCRIMINATOR,
+                anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+            );
+        }
+
+        let old_space = stats_info.data_len();
+        if old_space >= target_space {
+            msg!("UserStats already up to date ({} bytes)", old_space);
+            return Ok(());
+        }
+
+        let required_lamports = Rent::get()?.minimum_balance(target_space);
+        let rent_delta = required_lamports.saturating_sub(stats_info.lamports());
+
+        if rent_delta > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    Transfer {
+                        from: self.payer.to_account_info(),
+                        to: stats_info.clone(),
+                    },
+                ),
+                rent_delta,
+            )?;
+        }
+
+        stats_info.resize(target_space)?;
+
+        msg!(
+            "UserStats migrated from {} to {} bytes",
+            old_space,
+            target_space
+        );
+
+        emit!(UserStatsMigrated {
+            market_id,
+            user: self.user.key(),
+            payer: self.payer.key(),
+            old_space: old_space as u64,
+            new_space: target_space as u64,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -1,23 +1,137 @@
+pub mod cancel_and_claim;
+pub mod cancel_in_range;
 pub mod cancelorder;
+pub mod claim_collateral;
 pub mod claimfunds;
 pub mod claimrewards;
 pub mod closemarket;
+pub mod deposit_collateral;
+pub mod deposit_tokens;
+pub mod effective_fee;
+pub mod force_settle_and_close;
+pub mod fund_incentives;
+pub mod get_closed_order;
+pub mod implied_probability;
 pub mod initialise;
+pub mod initialize_config;
+pub mod initialize_from_template;
 pub mod limitorder;
+pub mod market_config;
+pub mod market_status;
 pub mod marketorder;
+pub mod match_preview;
 pub mod mergetoken;
+pub mod migrate_market;
+pub mod migrate_user_stats;
+pub mod open_interest;
+pub mod orderbook_rent_for;
+pub mod prune_expired_orders;
+pub mod reconcile_user;
+pub mod redeem_all;
+pub mod relist_claimable;
+pub mod replace_order;
+pub mod reserve_orderbook_capacity;
+pub mod reset_orderbook;
+pub mod resolve_slug;
+pub mod resync_collateral;
+pub mod set_book_mode;
+pub mod set_claim_delegate;
+pub mod set_collateral_cap;
+pub mod set_fee_exempt;
+pub mod set_fee_schedule;
+pub mod set_lot_size;
+pub mod set_max_order_quantity;
+pub mod set_min_seed_collateral;
+pub mod set_reward_rate;
+pub mod set_rounding_mode;
+pub mod set_side_paused;
+pub mod set_socialized_loss;
+pub mod set_stp_policy;
+pub mod set_trading_cutoff;
+pub mod set_wash_trade_guard;
+pub mod settle_orderbook;
+pub mod setup_user;
 pub mod setwinner;
+pub mod split_and_quote;
 pub mod splittoken;
+pub mod stake;
+pub mod transfer_order;
+pub mod unstake;
+pub mod update_creator_allowlist;
 pub mod update_metadata;
+pub mod user_position;
+pub mod verify_supply_invariant;
+pub mod withdraw_collateral;
+pub mod withdraw_incentives;
+pub mod withdraw_seed;
+pub mod withdraw_tokens;
+pub mod withdraw_treasury;
 
+pub use cancel_and_claim::*;
+pub use cancel_in_range::*;
 pub use cancelorder::*;
+pub use claim_collateral::*;
 pub use claimfunds::*;
 pub use claimrewards::*;
 pub use closemarket::*;
+pub use deposit_collateral::*;
+pub use deposit_tokens::*;
+pub use effective_fee::*;
+pub use force_settle_and_close::*;
+pub use fund_incentives::*;
+pub use get_closed_order::*;
+pub use implied_probability::*;
 pub use initialise::*;
+pub use initialize_config::*;
+pub use initialize_from_template::*;
 pub use limitorder::*;
+pub use market_config::*;
+pub use market_status::*;
 pub use marketorder::*;
+pub use match_preview::*;
 pub use mergetoken::*;
+pub use migrate_market::*;
+pub use migrate_user_stats::*;
+pub use open_interest::*;
+pub use orderbook_rent_for::*;
+pub use prune_expired_orders::*;
+pub use reconcile_user::*;
+pub use redeem_all::*;
+pub use relist_claimable::*;
+pub use replace_order::*;
+pub use reserve_orderbook_capacity::*;
+pub use reset_orderbook::*;
+pub use resolve_slug::*;
+pub use resync_collateral::*;
+pub use set_book_mode::*;
+pub use set_claim_delegate::*;
+pub use set_collateral_cap::*;
+pub use set_fee_exempt::*;
+pub use set_fee_schedule::*;
+pub use set_lot_size::*;
+pub use set_max_order_quantity::*;
+pub use set_min_seed_collateral::*;
+pub use set_reward_rate::*;
+pub use set_rounding_mode::*;
+pub use set_side_paused::*;
+pub use set_socialized_loss::*;
+pub use set_stp_policy::*;
+pub use set_trading_cutoff::*;
+pub use set_wash_trade_guard::*;
+pub use settle_orderbook::*;
+pub use setup_user::*;
 pub use setwinner::*;
+pub use split_and_quote::*;
 pub use splittoken::*;
+pub use stake::*;
+pub use transfer_order::*;
+pub use unstake::*;
+pub use update_creator_allowlist::*;
 pub use update_metadata::*;
+pub use user_position::*;
+pub use verify_supply_invariant::*;
+pub use withdraw_collateral::*;
+pub use withdraw_incentives::*;
+pub use withdraw_seed::*;
+pub use withdraw_tokens::*;
+pub use withdraw_treasury::*;
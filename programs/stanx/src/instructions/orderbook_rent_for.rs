@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::error::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct GetOrderBookRent<'info> {
+    #[account(
+        seeds = [ORDERBOOK_SEED, market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+        constraint = orderbook.market_id == market_id
+    )]
+    pub orderbook: Account<'info, OrderBook>,
+}
+
+impl<'info> GetOrderBookRent<'info> {
+    /// Read-only view of the lamports the orderbook still needs to be rent exempt at
+    /// `OrderBook::space(orders_per_side)`, 0 if it already holds enough
+    pub fn handler(&self, orders_per_side: u32) -> Result<u64> {
+        let orders_per_side = orders_per_side as usize;
+        require!(
+            orders_per_side <= MAX_ORDERS_PER_SIDE,
+            PredictionMarketError::InvalidOrderBookCapacity
+        );
+
+        Ok(Rent::get()?
+            .minimum_balance(OrderBook::space(orders_per_side))
+            .saturating_sub(self.orderbook.to_account_info().lamports()))
+    }
+}
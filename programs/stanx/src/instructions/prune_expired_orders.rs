@@ -0,0 +1,104 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::instructions::release_resting_orders;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct PruneExpiredOrders<'info> {
+    pub caller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        seeds = [ORDERBOOK_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+        constraint = orderbook.market_id == market_id
+    )]
+    pub orderbook: Account<'info, OrderBook>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
+        constraint = collateral_vault.key() == market.collateral_vault
+    )]
+    pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Only needed while the market pays maker rewards (reward_rate > 0)
+    #[account(
+        mut,
+        constraint = incentive_vault.key() == market.incentive_vault
+    )]
+    pub incentive_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> PruneExpiredOrders<'info> {
+    /// Clears up to `max_iteration` resting orders once the market is past its settlement
+    /// deadline, settled or not, into their owners' claimable balances
+    ///
+    /// Permissionless, for keepers. Trading stops at the deadline, so orders still resting
+    /// can no longer fill and only tie up their owners' funds. Works like `settle_orderbook`:
+    /// only orders whose owner's UserStats is passed in `remaining_accounts` are removed.
+    pub fn handler(
+        &mut self,
+        market_id: u32,
+        max_iteration: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+        program_id: &Pubkey,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= self.market.settlement_deadline,
+            PredictionMarketError::SettlementDeadlineNotReached
+        );
+        require!(
+            max_iteration > 0 && max_iteration <= MAX_ITERATION_CAP,
+            PredictionMarketError::InvalidIterationLimit
+        );
+
+        let orders_pruned = release_resting_orders(
+            &mut self.market,
+            &mut self.orderbook,
+            &self.collateral_vault,
+            self.incentive_vault.as_deref(),
+            &self.token_program,
+            market_id,
+            max_iteration as usize,
+            ClosedOrderReason::Expired,
+            remaining_accounts,
+            program_id,
+        )?;
+
+        let orders_remaining = self.orderbook.total_orders() as u32;
+
+        msg!(
+            "Pruned {} expired orders, {} left on the book",
+            orders_pruned,
+            orders_remaining
+        );
+
+        emit!(ExpiredOrdersPruned {
+            market_id,
+            orders_pruned,
+            orders_remaining,
+            caller: self.caller.key(),
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::constants::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct ReconcileUser<'info> {
+    /// CHECK: only used to derive the UserStats PDA
+    pub user: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [MARKET_SEED, market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [ORDERBOOK_SEED, market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+    )]
+    pub orderbook: Account<'info, OrderBook>,
+
+    #[account(
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump = user_stats_account.bump,
+    )]
+    pub user_stats_account: Account<'info, UserStats>,
+
+    #[account(constraint = collateral_vault.key() == market.collateral_vault)]
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(constraint = yes_escrow.key() == market.yes_escrow)]
+    pub yes_escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(constraint = no_escrow.key() == market.no_escrow)]
+    pub no_escrow: InterfaceAccount<'info, TokenAccount>,
+}
+
+impl<'info> ReconcileUser<'info> {
+    /// Read-only accounting check for ops monitoring, returned through return data
+    pub fn handler(&self) -> Result<ReconcileReport> {
+        let report = ReconcileReport::new(
+            &self.user_stats_account,
+            &self.orderbook,
+            self.market.backing_balance(self.collateral_vault.amount),
+            self.yes_escrow.amount,
+            self.no_escrow.amount,
+        )?;
+
+        if !report.consistent {
+            msg!(
+                "UserStats for {} is inconsistent: {:?}",
+                report.user,
+                report
+            );
+        }
+
+        Ok(report)
+    }
+}
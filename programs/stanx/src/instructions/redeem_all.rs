@@ -0,0 +1,216 @@
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+use crate::transfer_lock::*;
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::{
+    token::{self, Burn, Transfer},
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+#[instruction(market_id:u32)]
+pub struct RedeemAll<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(constraint = collateral_mint.key() == market.collateral_mint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_collateral: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
+        constraint = collateral_vault.key() == market.collateral_vault
+    )]
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = outcome_yes_mint.key() == market.outcome_yes_mint
+    )]
+    pub outcome_yes_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = outcome_no_mint.key() == market.outcome_no_mint
+    )]
+    pub outcome_no_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_outcome_yes.mint == market.outcome_yes_mint,
+        constraint = user_outcome_yes.owner == user.key()
+    )]
+    pub user_outcome_yes: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = user_outcome_no.mint == market.outcome_no_mint,
+        constraint = user_outcome_no.owner == user.key()
+    )]
+    pub user_outcome_no: InterfaceAccount<'info, TokenAccount>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> RedeemAll<'info> {
+    /// One post-settlement exit for every holder, dispatching on the outcome:
+    /// - OutcomeA/OutcomeB: pays the winning balance 1:1 and burns both sides, the losing
+    ///   side being worthless
+    /// - Neither: redeems matched YES+NO pairs 1:1, unpaired tokens are left in place
+    /// - Scalar markets: burns both sides for their settled value
+    pub fn handler(&mut self, _market_id: u32) -> Result<()> {
+        require!(
+            self.market.is_settled,
+            PredictionMarketError::MarketNotSettled
+        );
+
+        let winner = self
+            .market
+            .winning_outcome
+            .ok_or(PredictionMarketError::WinningOutcomeNotSet)?;
+
+        let yes_balance = self.user_outcome_yes.amount;
+        let no_balance = self.user_outcome_no.amount;
+
+        let (yes_burn, no_burn, redemption) =
+            if matches!(self.market.market_kind, MarketKind::Scalar { .. }) {
+                let redemption = self.market.scalar_payout(yes_balance, no_balance)?;
+                (yes_balance, no_balance, redemption)
+            } else {
+                let (yes_burn, no_burn, nominal) = match winner {
+                    WinningOutcome::OutcomeA => (yes_balance, no_balance, yes_balance),
+                    WinningOutcome::OutcomeB => (yes_balance, no_balance, no_balance),
+                    WinningOutcome::Neither => {
+                        let pairs = yes_balance.min(no_balance);
+                        (pairs, pairs, pairs)
+                    }
+                };
+                // Binary payouts are whole units, nothing is rounded
+                let redemption = ScalarPayout {
+                    payout: nominal,
+                    retired: nominal,
+                };
+                (yes_burn, no_burn, redemption)
+            };
+        let nominal = redemption.payout;
+
+        require!(
+            yes_burn > 0 || no_burn > 0,
+            PredictionMarketError::NothingToClaim
+        );
+
+        for (amount, mint, account) in [
+            (yes_burn, &self.outcome_yes_mint, &self.user_outcome_yes),
+            (no_burn, &self.outcome_no_mint, &self.user_outcome_no),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+
+            // Market is settled, so the account is left thawed afterwards
+            thaw_if_restricted(
+                &self.market,
+                &self.token_program,
+                account,
+                Some(mint.to_account_info()),
+            )?;
+
+            token::burn(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    Burn {
+                        mint: mint.to_account_info(),
+                        from: account.to_account_info(),
+                        authority: self.user.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+        }
+        self.market.record_burn(yes_burn, no_burn)?;
+
+        let payout = self
+            .market
+            .socialized_payout(nominal, self.collateral_vault.amount)?;
+        if payout > 0 {
+            let market_id_bytes = self.market.market_id.to_le_bytes();
+            let bump = self.market.bump;
+            let signer_seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[bump]];
+
+            require!(
+                self.collateral_vault.amount >= payout,
+                PredictionMarketError::InsufficientEscrow
+            );
+            token::transfer(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    Transfer {
+                        from: self.collateral_vault.to_account_info(),
+                        to: self.user_collateral.to_account_info(),
+                        authority: self.market.to_account_info(),
+                    },
+                    &[signer_seeds],
+                ),
+                payout,
+            )?;
+        }
+
+        let dust = self.market.retire_payout(redemption)?;
+        if dust > 0 {
+            msg!("{} collateral of rounding dust moved to the treasury", dust);
+        }
+
+        if payout < nominal {
+            emit!(LossSocialized {
+                market_id: self.market.market_id,
+                user: self.user.key(),
+                nominal,
+                paid: payout,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        }
+
+        let user_key = self.user.key();
+        msg!(
+            "User {} redeemed {} collateral (burned {} YES, {} NO)",
+            user_key,
+            payout,
+            yes_burn,
+            no_burn
+        );
+
+        emit!(RewardsClaimed {
+            market_id: self.market.market_id,
+            user: user_key,
+            collateral_amount: payout,
+            yes_tokens_burned: yes_burn,
+            no_tokens_burned: no_burn,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::instructions::grow_orderbook_if_needed;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct RelistClaimable<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        seeds = [ORDERBOOK_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+        constraint = orderbook.market_id == market_id
+    )]
+    pub orderbook: Account<'info, OrderBook>,
+
+    #[account(
+        mut,
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump = user_stats_account.bump,
+        constraint = user_stats_account.user == user.key()
+    )]
+    pub user_stats_account: Box<Account<'info, UserStats>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> RelistClaimable<'info> {
+    /// Turn claimable YES/NO back into a resting sell order without claiming them first
+    ///
+    /// - Claimable tokens already sit in escrow, so only UserStats moves them from
+    ///   claimable to locked, no token transfer happens
+    /// - The order only rests, it never matches: a price at or below the best bid is
+    ///   rejected with `OrderWouldCross` (use `place_order` to sell into the book)
+    pub fn handler(
+        &mut self,
+        market_id: u32,
+        token_type: TokenType,
+        quantity: u64,
+        price: u64,
+    ) -> Result<()> {
+        let market = &self.market;
+        let orderbook = &mut self.orderbook;
+
+        require!(
+            Clock::get()?.unix_timestamp < market.settlement_deadline,
+            PredictionMarketError::MarketExpired
+        );
+        market.check_side_open(token_type)?;
+        require!(
+            !market.is_settled,
+            PredictionMarketError::MarketAlreadySettled
+        );
+        require!(price > 0, PredictionMarketError::InvalidOrderPrice);
+        market.check_price(price)?;
+        require!(
+            quantity >= MIN_ORDER_QUANTITY,
+            PredictionMarketError::OrderTooSmall
+        );
+        market.check_lot_size(quantity)?;
+        market.check_max_order_quantity(quantity)?;
+
+        let (best_bid, sell_orders_len) = match token_type {
+            TokenType::Yes => (
+                orderbook.yes_buy_orders.first().map(|o| o.price),
+                orderbook.yes_sell_orders.len(),
+            ),
+            TokenType::No => (
+                orderbook.no_buy_orders.first().map(|o| o.price),
+                orderbook.no_sell_orders.len(),
+            ),
+        };
+        if let Some(bid) = best_bid {
+            require!(price > bid, PredictionMarketError::OrderWouldCross);
+        }
+        require!(
+            sell_orders_len < MAX_ORDERS_PER_SIDE,
+            PredictionMarketError::OrderBookFull
+        );
+
+        let user_stats: &mut UserStats = &mut self.user_stats_account;
+        let (claimable, locked) = match token_type {
+            TokenType::Yes => (&mut user_stats.claimable_yes, &mut user_stats.locked_yes),
+            TokenType::No => (&mut user_stats.claimable_no, &mut user_stats.locked_no),
+        };
+        require!(
+            *claimable >= quantity,
+            PredictionMarketError::NotEnoughBalance
+        );
+        *claimable -= quantity;
+        *locked = locked
+            .checked_add(quantity)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        user_stats.last_order_slot = Clock::get()?.slot;
+        user_stats.last_order_side = OrderSide::Sell;
+        user_stats.last_order_token_type = token_type;
+
+        let order = Order {
+            id: orderbook.assign_order_id(&self.user.key(), None)?,
+            market_id,
+            user_key: self.user.key(),
+            side: OrderSide::Sell,
+            token_type,
+            price,
+            quantity,
+            filledquantity: 0,
+            timestamp: Clock::get()?.unix_timestamp,
+            priority: 0,
+        };
+
+        let order_vec = match token_type {
+            TokenType::Yes => &mut orderbook.yes_sell_orders,
+            TokenType::No => &mut orderbook.no_sell_orders,
+        };
+        order_vec.push(order);
+        OrderBook::sort_side(order_vec, OrderSide::Sell);
+
+        orderbook.refresh_book_hash();
+
+        grow_orderbook_if_needed(
+            orderbook,
+            self.user.to_account_info(),
+            self.system_program.to_account_info(),
+            market_id,
+        )?;
+
+        msg!(
+            "Relisted {} claimable {:?} tokens at {}",
+            quantity,
+            token_type,
+            price
+        );
+
+        emit!(OrderPlaced {
+            market_id,
+            order_id: order.id,
+            user: self.user.key(),
+            side: OrderSide::Sell,
+            token_type,
+            price,
+            quantity,
+            timestamp: order.timestamp,
+        });
+
+        Ok(())
+    }
+}
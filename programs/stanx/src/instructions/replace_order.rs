@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+
+use crate::error::*;
+use crate::instructions::*;
+use crate::reentrancy_guard::*;
+use crate::state::*;
+
+// Union of the cancel and place contexts. Both name the same market, orderbook and user
+// accounts, the handler keeps the two copies in sync between the steps.
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct ReplaceOrder<'info> {
+    pub cancel: CancelOrder<'info>,
+    pub place: PlaceOrder<'info>,
+}
+
+impl<'info> ReplaceOrder<'info> {
+    /// Cancels `cancel_order_id` and places the new order in one transaction, so a maker is
+    /// never left without a quote in between. Fills from the new order use `remaining_accounts`
+    /// exactly like `place_order`. On a `MakerOnly` book the new order is a maker quote.
+    #[allow(clippy::too_many_arguments)]
+    pub fn handler(
+        &mut self,
+        market_id: u32,
+        cancel_order_id: u64,
+        side: OrderSide,
+        token_type: TokenType,
+        quantity: u64,
+        price: u64,
+        max_iteration: u64,
+        client_nonce: Option<u64>,
+        bumps: &PlaceOrderBumps,
+        remaining_accounts: &[AccountInfo<'info>],
+        program_id: &Pubkey,
+    ) -> Result<PlaceOrderResult> {
+        require_keys_eq!(
+            self.cancel.user.key(),
+            self.place.user.key(),
+            PredictionMarketError::ReplaceSignerMismatch
+        );
+
+        {
+            let _guard = ReentrancyGuard::enter(&self.cancel.market)?;
+            self.cancel.handler(market_id, cancel_order_id)?;
+        }
+
+        // Write the cancel's state and reload the place step's copies of the same accounts.
+        // On exit the cancel copies are written first, so the place step's state is final.
+        self.cancel.market.exit(program_id)?;
+        self.cancel.orderbook.exit(program_id)?;
+        self.cancel.user_stats_account.exit(program_id)?;
+
+        self.place.market.reload()?;
+        self.place.orderbook.reload()?;
+        self.place.user_stats_account.reload()?;
+        self.place.collateral_vault.reload()?;
+        self.place.user_collateral.reload()?;
+        self.place.yes_escrow.reload()?;
+        self.place.no_escrow.reload()?;
+        if let Some(account) = self.place.user_outcome_yes.as_mut() {
+            account.reload()?;
+        }
+        if let Some(account) = self.place.user_outcome_no.as_mut() {
+            account.reload()?;
+        }
+        if let Some(account) = self.place.incentive_vault.as_mut() {
+            account.reload()?;
+        }
+
+        // The cancel step's exit wrote its cleared flag, so the place step guards on its own
+        let _guard = ReentrancyGuard::enter(&self.place.market)?;
+        let maker_quote = self.place.market.book_mode == BookMode::MakerOnly;
+        self.place.handler(
+            market_id,
+            side,
+            token_type,
+            quantity,
+            price,
+            max_iteration,
+            client_nonce,
+            maker_quote,
+            bumps,
+            remaining_accounts,
+            program_id,
+        )
+    }
+}
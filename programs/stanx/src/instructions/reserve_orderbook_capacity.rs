@@ -0,0 +1,83 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::instructions::resize_orderbook;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct ReserveOrderBookCapacity<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [ORDERBOOK_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+        constraint = orderbook.market_id == market_id
+    )]
+    pub orderbook: Account<'info, OrderBook>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ReserveOrderBookCapacity<'info> {
+    /// Grows the orderbook to `OrderBook::space(orders_per_side)` in one go, with the authority
+    /// paying the rent, so early traders don't pay for growth batches on their orders
+    pub fn handler(&mut self, market_id: u32, orders_per_side: u32) -> Result<()> {
+        let orders_per_side = orders_per_side as usize;
+        let orderbook_info = self.orderbook.to_account_info();
+        let old_space = orderbook_info.data_len();
+        let new_space = OrderBook::space(orders_per_side);
+
+        require!(
+            orders_per_side <= MAX_ORDERS_PER_SIDE && new_space > old_space,
+            PredictionMarketError::InvalidOrderBookCapacity
+        );
+
+        let rent_delta = Rent::get()?
+            .minimum_balance(new_space)
+            .saturating_sub(orderbook_info.lamports());
+
+        if rent_delta > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: self.authority.to_account_info(),
+                        to: orderbook_info.clone(),
+                    },
+                ),
+                rent_delta,
+            )?;
+        }
+
+        resize_orderbook(&self.orderbook, market_id, new_space)?;
+
+        msg!(
+            "Orderbook capacity reserved for {} orders per side",
+            orders_per_side
+        );
+
+        emit!(OrderBookResized {
+            market_id,
+            old_space: old_space as u64,
+            new_space: new_space as u64,
+            payer: self.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::instructions::{release_resting_orders, resize_orderbook};
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct ResetOrderBook<'info> {
+    // Receives the rent freed by shrinking the orderbook
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        seeds = [ORDERBOOK_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+        constraint = orderbook.market_id == market_id
+    )]
+    pub orderbook: Account<'info, OrderBook>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
+        constraint = collateral_vault.key() == market.collateral_vault
+    )]
+    pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Only needed while the market pays maker rewards (reward_rate > 0)
+    #[account(
+        mut,
+        constraint = incentive_vault.key() == market.incentive_vault
+    )]
+    pub incentive_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> ResetOrderBook<'info> {
+    /// Break-glass recovery for a book that normal cancel/settle paths can't clear
+    ///
+    /// Authority only, after settlement. Every resting order is released into its owner's
+    /// claimable balances exactly like `settle_orderbook`, so the UserStats of every owner must
+    /// be in `remaining_accounts`: a single missing owner fails the whole reset rather than
+    /// leave their funds locked. The emptied book is then shrunk to `OrderBook::space(0)` and
+    /// the freed rent goes to the authority.
+    pub fn handler(
+        &mut self,
+        market_id: u32,
+        remaining_accounts: &[AccountInfo<'info>],
+        program_id: &Pubkey,
+    ) -> Result<()> {
+        require!(
+            self.market.is_settled,
+            PredictionMarketError::MarketNotSettled
+        );
+
+        let orders_cleared = if self.orderbook.total_orders() > 0 {
+            release_resting_orders(
+                &mut self.market,
+                &mut self.orderbook,
+                &self.collateral_vault,
+                self.incentive_vault.as_deref(),
+                &self.token_program,
+                market_id,
+                usize::MAX,
+                ClosedOrderReason::Settled,
+                remaining_accounts,
+                program_id,
+            )?
+        } else {
+            0
+        };
+
+        require!(
+            self.orderbook.total_orders() == 0,
+            PredictionMarketError::OrderBookResetIncomplete
+        );
+
+        // Drops whatever capacity the vectors still hold before the account is shrunk
+        self.orderbook.yes_buy_orders = Vec::new();
+        self.orderbook.yes_sell_orders = Vec::new();
+        self.orderbook.no_buy_orders = Vec::new();
+        self.orderbook.no_sell_orders = Vec::new();
+        self.orderbook.refresh_book_hash();
+
+        let orderbook_info = self.orderbook.to_account_info();
+        let old_space = orderbook_info.data_len();
+        let new_space = OrderBook::space(0);
+        let mut rent_refunded = 0;
+        if old_space > new_space {
+            resize_orderbook(&self.orderbook, market_id, new_space)?;
+
+            rent_refunded = orderbook_info
+                .lamports()
+                .saturating_sub(Rent::get()?.minimum_balance(new_space));
+            **orderbook_info.try_borrow_mut_lamports()? -= rent_refunded;
+            **self.authority.to_account_info().try_borrow_mut_lamports()? += rent_refunded;
+        }
+
+        msg!(
+            "ORDERBOOK RESET: market {} cleared {} orders, space {} -> {}",
+            market_id,
+            orders_cleared,
+            old_space,
+            new_space
+        );
+
+        emit!(OrderBookReset {
+            market_id,
+            orders_cleared,
+            old_space: old_space as u64,
+            new_space: new_space as u64,
+            rent_refunded,
+            authority: self.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::state::MarketSlug;
+
+#[derive(Accounts)]
+#[instruction(slug: String)]
+pub struct ResolveSlug<'info> {
+    #[account(
+        seeds = [MARKET_SLUG_SEED, slug.as_bytes()],
+        bump = market_slug.bump
+    )]
+    pub market_slug: Account<'info, MarketSlug>,
+}
+
+impl<'info> ResolveSlug<'info> {
+    /// Read-only: market_id of the market using `slug`, fails if no market does
+    pub fn handler(&self) -> Result<u32> {
+        Ok(self.market_slug.market_id)
+    }
+}
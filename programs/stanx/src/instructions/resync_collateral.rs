@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct ResyncCollateral<'info> {
+    pub caller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        seeds = [ORDERBOOK_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+        constraint = orderbook.market_id == market_id
+    )]
+    pub orderbook: Account<'info, OrderBook>,
+
+    #[account(
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
+        constraint = collateral_vault.key() == market.collateral_vault
+    )]
+    pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+impl<'info> ResyncCollateral<'info> {
+    /// Lowers `total_collateral_locked` to the vault's actual balance, less the treasury, once
+    /// the market is settled and its book is empty, so rounding drift can't keep `close_market`
+    /// blocked
+    ///
+    /// Permissionless. The tracked value only ever goes down: a vault holding more than is
+    /// tracked is left alone, raising the figure could let claims pay out more than was owed.
+    pub fn handler(&mut self, market_id: u32) -> Result<()> {
+        require!(
+            self.market.is_settled,
+            PredictionMarketError::MarketNotSettled
+        );
+        require!(
+            self.orderbook.total_orders() == 0,
+            PredictionMarketError::OrdersStillPending
+        );
+
+        let previous = self.market.total_collateral_locked;
+        let resynced = previous.min(self.market.backing_balance(self.collateral_vault.amount));
+        self.market.total_collateral_locked = resynced;
+
+        msg!(
+            "total_collateral_locked resynced from {} to {}",
+            previous,
+            resynced
+        );
+
+        emit!(CollateralResynced {
+            market_id,
+            caller: self.caller.key(),
+            previous,
+            resynced,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use crate::constants::*;
+use crate::events::*;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SetBookMode<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> SetBookMode<'info> {
+    /// Orders already resting stay on the book when switching to `MakerOnly`
+    pub fn handler(&mut self, _market_id: u32, book_mode: BookMode) -> Result<()> {
+        self.market.book_mode = book_mode;
+
+        msg!("Book mode set to {:?}", book_mode);
+
+        emit!(BookModeUpdated {
+            market_id: self.market.market_id,
+            authority: self.authority.key(),
+            book_mode,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
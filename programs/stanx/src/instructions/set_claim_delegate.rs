@@ -0,0 +1,36 @@
+use crate::constants::*;
+use crate::events::*;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SetClaimDelegate<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump = user_stats.bump,
+        constraint = user_stats.user == user.key()
+    )]
+    pub user_stats: Account<'info, UserStats>,
+}
+
+impl<'info> SetClaimDelegate<'info> {
+    /// Lets `delegate` sign claim_funds/claim_rewards for the user, `None` revokes it
+    pub fn handler(&mut self, market_id: u32, delegate: Option<Pubkey>) -> Result<()> {
+        self.user_stats.claim_delegate = delegate;
+
+        msg!("Claim delegate set to {:?}", delegate);
+
+        emit!(ClaimDelegateUpdated {
+            market_id,
+            user: self.user.key(),
+            delegate,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
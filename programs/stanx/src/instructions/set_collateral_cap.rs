@@ -0,0 +1,39 @@
+use crate::constants::*;
+use crate::events::*;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SetCollateralCap<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> SetCollateralCap<'info> {
+    /// Set the maximum collateral the market vault may hold, 0 removes the cap.
+    /// Lowering the cap below the current `total_collateral_locked` only blocks new deposits.
+    pub fn handler(&mut self, _market_id: u32, max_collateral: u64) -> Result<()> {
+        self.market.max_collateral = max_collateral;
+
+        msg!("Market collateral cap set to: {}", max_collateral);
+
+        emit!(CollateralCapUpdated {
+            market_id: self.market.market_id,
+            authority: self.authority.key(),
+            max_collateral,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use crate::constants::*;
+use crate::events::*;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SetFeeExempt<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: only used to derive the UserStats PDA
+    pub user: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump = user_stats.bump,
+        constraint = user_stats.user == user.key()
+    )]
+    pub user_stats: Account<'info, UserStats>,
+}
+
+impl<'info> SetFeeExempt<'info> {
+    /// Exempts `user` from trading fees on their sells, as maker or taker. Buyers never pay
+    /// a fee, so an exempt buyer's counterparty is still charged.
+    pub fn handler(&mut self, market_id: u32, exempt: bool) -> Result<()> {
+        self.user_stats.fee_exempt = exempt;
+
+        msg!("User {} fee exempt: {}", self.user.key(), exempt);
+
+        emit!(FeeExemptionUpdated {
+            market_id,
+            authority: self.authority.key(),
+            user: self.user.key(),
+            exempt,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SetFeeSchedule<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> SetFeeSchedule<'info> {
+    /// Switch the market to a trade-count based fee, `None` goes back to the flat
+    /// `TRADING_FEE_BPS`. The decay keeps counting from the market's current `total_trades`.
+    pub fn handler(&mut self, _market_id: u32, fee_schedule: Option<FeeSchedule>) -> Result<()> {
+        if let Some(schedule) = fee_schedule {
+            require!(
+                schedule.decay_step > 0
+                    && schedule.min_fee_bps <= schedule.base_fee_bps
+                    && schedule.base_fee_bps <= 10_000,
+                PredictionMarketError::InvalidFeeSchedule
+            );
+        }
+
+        self.market.fee_schedule = fee_schedule;
+
+        let effective_fee_bps = self.market.effective_fee_bps();
+        msg!(
+            "Fee schedule updated, effective fee: {} bps",
+            effective_fee_bps
+        );
+
+        emit!(FeeScheduleUpdated {
+            market_id: self.market.market_id,
+            authority: self.authority.key(),
+            fee_schedule,
+            effective_fee_bps,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use crate::constants::*;
+use crate::events::*;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SetLotSize<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> SetLotSize<'info> {
+    /// Applies to new orders only, orders already resting keep their quantity
+    pub fn handler(&mut self, _market_id: u32, lot_size: u64) -> Result<()> {
+        self.market.lot_size = lot_size;
+
+        msg!("Lot size set to {}", lot_size);
+
+        emit!(LotSizeUpdated {
+            market_id: self.market.market_id,
+            authority: self.authority.key(),
+            lot_size,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use crate::constants::*;
+use crate::events::*;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SetMaxOrderQuantity<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> SetMaxOrderQuantity<'info> {
+    /// Applies to new orders only, orders already resting keep their quantity, 0 lifts the cap
+    pub fn handler(&mut self, _market_id: u32, max_order_quantity: u64) -> Result<()> {
+        self.market.max_order_quantity = max_order_quantity;
+
+        msg!("Max order quantity set to {}", max_order_quantity);
+
+        emit!(MaxOrderQuantityUpdated {
+            market_id: self.market.market_id,
+            authority: self.authority.key(),
+            max_order_quantity,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
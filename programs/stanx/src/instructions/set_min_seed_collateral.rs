@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct SetMinSeedCollateral<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ PredictionMarketError::NotAuthorized
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> SetMinSeedCollateral<'info> {
+    /// Every market created from now on must lock at least `min_seed_collateral` in its vault,
+    /// 0 makes seeding optional again. Existing markets are unaffected.
+    pub fn handler(&mut self, min_seed_collateral: u64) -> Result<()> {
+        self.config.min_seed_collateral = min_seed_collateral;
+
+        msg!("Minimum seed collateral set to {}", min_seed_collateral);
+
+        emit!(MinSeedCollateralUpdated {
+            admin: self.admin.key(),
+            min_seed_collateral,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+use crate::constants::*;
+use crate::events::*;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SetRewardRate<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> SetRewardRate<'info> {
+    /// Set the maker reward rate (micro collateral per display token per second rested), 0 turns rewards off.
+    /// The new rate applies to everything accrued from now on, including orders already resting.
+    pub fn handler(&mut self, _market_id: u32, reward_rate: u64) -> Result<()> {
+        self.market.reward_rate = reward_rate;
+
+        msg!("Maker reward rate set to: {}", reward_rate);
+
+        emit!(RewardRateUpdated {
+            market_id: self.market.market_id,
+            authority: self.authority.key(),
+            reward_rate,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
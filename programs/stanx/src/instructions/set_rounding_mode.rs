@@ -0,0 +1,37 @@
+use crate::constants::*;
+use crate::events::*;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SetRoundingMode<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> SetRoundingMode<'info> {
+    pub fn handler(&mut self, _market_id: u32, rounding_mode: RoundingMode) -> Result<()> {
+        self.market.rounding_mode = rounding_mode;
+
+        msg!("Rounding mode set to {:?}", rounding_mode);
+
+        emit!(RoundingModeUpdated {
+            market_id: self.market.market_id,
+            authority: self.authority.key(),
+            rounding_mode,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
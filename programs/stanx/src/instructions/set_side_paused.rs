@@ -0,0 +1,43 @@
+use crate::constants::*;
+use crate::events::*;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SetSidePaused<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> SetSidePaused<'info> {
+    /// Only new orders on that outcome are rejected, its resting orders stay on the book and
+    /// can still be cancelled
+    pub fn handler(&mut self, _market_id: u32, token_type: TokenType, paused: bool) -> Result<()> {
+        match token_type {
+            TokenType::Yes => self.market.yes_trading_paused = paused,
+            TokenType::No => self.market.no_trading_paused = paused,
+        }
+
+        msg!("{:?} trading paused: {}", token_type, paused);
+
+        emit!(SideTradingPauseUpdated {
+            market_id: self.market.market_id,
+            authority: self.authority.key(),
+            token_type,
+            paused,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SetSocializedLoss<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> SetSocializedLoss<'info> {
+    /// Locked once settled, flipping it mid-claims would favour whoever claimed first
+    pub fn handler(&mut self, _market_id: u32, enabled: bool) -> Result<()> {
+        require!(
+            !self.market.is_settled,
+            PredictionMarketError::MarketAlreadySettled
+        );
+
+        self.market.socialized_loss = enabled;
+
+        msg!("Socialized loss enabled: {}", enabled);
+
+        emit!(SocializedLossUpdated {
+            market_id: self.market.market_id,
+            authority: self.authority.key(),
+            enabled,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
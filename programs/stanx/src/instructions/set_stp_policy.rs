@@ -0,0 +1,37 @@
+use crate::constants::*;
+use crate::events::*;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SetStpPolicy<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> SetStpPolicy<'info> {
+    pub fn handler(&mut self, _market_id: u32, stp_policy: StpPolicy) -> Result<()> {
+        self.market.stp_policy = stp_policy;
+
+        msg!("Self-trade prevention policy set to {:?}", stp_policy);
+
+        emit!(StpPolicyUpdated {
+            market_id: self.market.market_id,
+            authority: self.authority.key(),
+            stp_policy,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SetTradingCutoff<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> SetTradingCutoff<'info> {
+    /// place_order/market_order fail with `TradingWindowClosed` from
+    /// `settlement_deadline - trading_cutoff_buffer` on, resting orders can still be cancelled
+    pub fn handler(&mut self, _market_id: u32, trading_cutoff_buffer: i64) -> Result<()> {
+        require!(
+            trading_cutoff_buffer >= 0,
+            PredictionMarketError::InvalidTradingCutoff
+        );
+        self.market.trading_cutoff_buffer = trading_cutoff_buffer;
+
+        msg!("Trading cutoff buffer set to {}s", trading_cutoff_buffer);
+
+        emit!(TradingCutoffUpdated {
+            market_id: self.market.market_id,
+            authority: self.authority.key(),
+            trading_cutoff_buffer,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,286 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{self, Transfer},
+    token_interface::{TokenAccount, TokenInterface},
+};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SettleOrderBook<'info> {
+    pub caller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        seeds = [ORDERBOOK_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+        constraint = orderbook.market_id == market_id
+    )]
+    pub orderbook: Account<'info, OrderBook>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
+        constraint = collateral_vault.key() == market.collateral_vault
+    )]
+    pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Only needed while the market pays maker rewards (reward_rate > 0)
+    #[account(
+        mut,
+        constraint = incentive_vault.key() == market.incentive_vault
+    )]
+    pub incentive_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> SettleOrderBook<'info> {
+    /// Clears resting orders of a settled market into their owners' claimable balances
+    ///
+    /// Permissionless. Every order whose owner's UserStats is passed in `remaining_accounts`
+    /// is removed: a buy's locked collateral becomes claimable collateral and a sell's locked
+    /// tokens become claimable tokens. Funds stay in the vault/escrows and are paid out by
+    /// `claim_funds`. Large books can be cleared over several calls.
+    pub fn handler(
+        &mut self,
+        market_id: u32,
+        remaining_accounts: &[AccountInfo<'info>],
+        program_id: &Pubkey,
+    ) -> Result<()> {
+        require!(
+            self.market.is_settled,
+            PredictionMarketError::MarketNotSettled
+        );
+
+        let orders_settled = release_resting_orders(
+            &mut self.market,
+            &mut self.orderbook,
+            &self.collateral_vault,
+            self.incentive_vault.as_deref(),
+            &self.token_program,
+            market_id,
+            usize::MAX,
+            ClosedOrderReason::Settled,
+            remaining_accounts,
+            program_id,
+        )?;
+
+        let orderbook = &self.orderbook;
+        let now = Clock::get()?.unix_timestamp;
+        let orders_remaining = orderbook.total_orders() as u32;
+
+        msg!(
+            "Settled {} resting orders, {} left on the book",
+            orders_settled,
+            orders_remaining
+        );
+
+        emit!(OrderBookSettled {
+            market_id,
+            orders_settled,
+            orders_remaining,
+            caller: self.caller.key(),
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+}
+
+/// Removes up to `max_orders` resting orders whose owner's UserStats is in `remaining_accounts`,
+/// moving what they lock to the owners' claimable balances with the maker reward a cancel
+/// would pay. Shared by `settle_orderbook` and `prune_expired_orders`, returns how many
+/// orders were removed and fails with `OrdernotFound` if none were.
+#[allow(clippy::too_many_arguments)]
+pub fn release_resting_orders<'info>(
+    market: &mut Account<'info, Market>,
+    orderbook: &mut Account<'info, OrderBook>,
+    collateral_vault: &InterfaceAccount<'info, TokenAccount>,
+    incentive_vault: Option<&InterfaceAccount<'info, TokenAccount>>,
+    token_program: &Interface<'info, TokenInterface>,
+    market_id: u32,
+    max_orders: usize,
+    reason: ClosedOrderReason,
+    remaining_accounts: &[AccountInfo<'info>],
+    program_id: &Pubkey,
+) -> Result<u32> {
+    let mut owners: Vec<(&AccountInfo<'info>, UserStats)> = Vec::new();
+    for account_info in remaining_accounts.iter() {
+        // A repeated account would be written back twice, the stale copy last
+        if owners.iter().any(|(a, _)| a.key == account_info.key) {
+            continue;
+        }
+        require!(
+            account_info.owner == program_id,
+            PredictionMarketError::InvalidAccountOwner
+        );
+        let stats = UserStats::try_deserialize(&mut &account_info.try_borrow_data()?[..])?;
+        let expected = Pubkey::create_program_address(
+            &[
+                USER_STATS_SEED,
+                market_id.to_le_bytes().as_ref(),
+                stats.user.as_ref(),
+                &[stats.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| PredictionMarketError::InvalidAccountOwner)?;
+        require_keys_eq!(
+            expected,
+            account_info.key(),
+            PredictionMarketError::InvalidAccountOwner
+        );
+        owners.push((account_info, stats));
+    }
+
+    let mut settled: Vec<Order> = Vec::new();
+    let book: &mut OrderBook = &mut *orderbook;
+    for order_vec in [
+        &mut book.yes_buy_orders,
+        &mut book.yes_sell_orders,
+        &mut book.no_buy_orders,
+        &mut book.no_sell_orders,
+    ] {
+        let mut idx = 0;
+        while idx < order_vec.len() {
+            if settled.len() >= max_orders {
+                break;
+            }
+            if owners
+                .iter()
+                .any(|(_, s)| s.user == order_vec[idx].user_key)
+            {
+                settled.push(order_vec.remove(idx));
+            } else {
+                idx += 1;
+            }
+        }
+    }
+
+    require!(!settled.is_empty(), PredictionMarketError::OrdernotFound);
+
+    orderbook.refresh_book_hash();
+
+    let now = Clock::get()?.unix_timestamp;
+    let mut total_reward: u64 = 0;
+
+    for order in settled.iter() {
+        orderbook.record_closed(*order, reason, now);
+        let unfilled_quantity = order
+            .quantity
+            .checked_sub(order.filledquantity)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        let (_, stats) = owners
+            .iter_mut()
+            .find(|(_, s)| s.user == order.user_key)
+            .ok_or(PredictionMarketError::SellerStatsAccountNotProvided)?;
+
+        // Same amounts cancel_order would refund, moved from locked to claimable
+        match (order.side, order.token_type) {
+            (OrderSide::Buy, _) => {
+                let refund = Order::collateral_for(unfilled_quantity, order.price)?;
+                stats.locked_collateral = stats
+                    .locked_collateral
+                    .checked_sub(refund)
+                    .ok_or(PredictionMarketError::MathOverflow)?;
+                stats.claimable_collateral = stats
+                    .claimable_collateral
+                    .checked_add(refund)
+                    .ok_or(PredictionMarketError::MathOverflow)?;
+            }
+            (OrderSide::Sell, TokenType::Yes) => {
+                stats.locked_yes = stats
+                    .locked_yes
+                    .checked_sub(unfilled_quantity)
+                    .ok_or(PredictionMarketError::MathOverflow)?;
+                stats.claimable_yes = stats
+                    .claimable_yes
+                    .checked_add(unfilled_quantity)
+                    .ok_or(PredictionMarketError::MathOverflow)?;
+            }
+            (OrderSide::Sell, TokenType::No) => {
+                stats.locked_no = stats
+                    .locked_no
+                    .checked_sub(unfilled_quantity)
+                    .ok_or(PredictionMarketError::MathOverflow)?;
+                stats.claimable_no = stats
+                    .claimable_no
+                    .checked_add(unfilled_quantity)
+                    .ok_or(PredictionMarketError::MathOverflow)?;
+            }
+        }
+
+        let maker_reward = market.accrue_maker_reward(unfilled_quantity, order.timestamp, now)?;
+        if maker_reward > 0 {
+            stats.credit_maker_reward(maker_reward)?;
+            total_reward = total_reward
+                .checked_add(maker_reward)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+            emit!(MakerRewardAccrued {
+                market_id,
+                order_id: order.id,
+                maker: order.user_key,
+                amount: maker_reward,
+                timestamp: now,
+            });
+        }
+
+        emit!(OrderCancelled {
+            market_id,
+            order_id: order.id,
+            user: order.user_key,
+            side: order.side,
+            token_type: order.token_type,
+            remaining_quantity: unfilled_quantity,
+            timestamp: now,
+        });
+    }
+
+    for (account_info, stats) in owners.iter() {
+        let mut data = account_info.try_borrow_mut_data()?;
+        let mut writer = &mut data[..];
+        stats.try_serialize(&mut writer)?;
+    }
+
+    if total_reward > 0 {
+        let incentive_vault =
+            incentive_vault.ok_or(PredictionMarketError::IncentiveVaultRequired)?;
+        let market_id_bytes = market.market_id.to_le_bytes();
+        let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[market.bump]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                Transfer {
+                    from: incentive_vault.to_account_info(),
+                    to: collateral_vault.to_account_info(),
+                    authority: market.to_account_info(),
+                },
+                &[seeds],
+            ),
+            total_reward,
+        )?;
+
+        market.total_collateral_locked = market
+            .total_collateral_locked
+            .checked_add(total_reward)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+    }
+
+    Ok(settled.len() as u32)
+}
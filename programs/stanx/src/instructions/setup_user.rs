@@ -0,0 +1,97 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::constants::*;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SetupUser<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + UserStats::INIT_SPACE,
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub user_stats_account: Box<Account<'info, UserStats>>,
+
+    #[account(constraint = collateral_mint.key() == market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(constraint = outcome_yes_mint.key() == market.outcome_yes_mint)]
+    pub outcome_yes_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(constraint = outcome_no_mint.key() == market.outcome_no_mint)]
+    pub outcome_no_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = outcome_yes_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_outcome_yes: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = outcome_no_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_outcome_no: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> SetupUser<'info> {
+    /// One-time onboarding: creates the UserStats PDA and the collateral/YES/NO ATAs up front,
+    /// so later trades don't pay for `init_if_needed` on the critical path.
+    /// Safe to call again, existing accounts are left untouched.
+    pub fn handler(&mut self, market_id: u32, bumps: &SetupUserBumps) -> Result<()> {
+        let user_stats = &mut self.user_stats_account;
+        if user_stats.user != Pubkey::default() {
+            msg!("User already set up for market {}", market_id);
+            return Ok(());
+        }
+
+        user_stats.user = self.user.key();
+        user_stats.market_id = market_id;
+        user_stats.bump = bumps.user_stats_account;
+
+        msg!("User {} set up for market {}", self.user.key(), market_id);
+
+        emit!(UserSetup {
+            market_id,
+            user: self.user.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
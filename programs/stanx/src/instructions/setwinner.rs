@@ -6,7 +6,7 @@ use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::{
     token::{self, spl_token::instruction::AuthorityType, SetAuthority},
-    token_interface::{Mint, TokenInterface},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
 #[derive(Accounts)]
@@ -35,12 +35,39 @@ pub struct SetWinner<'info> {
         constraint = outcome_no_mint.key() == market.outcome_no_mint
     )]
     pub outcome_no_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
+        constraint = collateral_vault.key() == market.collateral_vault
+    )]
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + SettlementRecord::INIT_SPACE,
+        seeds = [SETTLEMENT_RECORD_SEED, market_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = settlement_record.resolver == Pubkey::default() @ PredictionMarketError::SettlementAlreadyRecorded
+    )]
+    pub settlement_record: Account<'info, SettlementRecord>,
+
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
 }
 
 impl<'info> SetWinner<'info> {
-    pub fn handler(&mut self, _market_id: u32, winning_outcome: WinningOutcome) -> Result<()> {
+    /// `settlement_value` is required for scalar markets (clamped to the market's bounds)
+    /// and ignored for binary ones
+    pub fn handler(
+        &mut self,
+        _market_id: u32,
+        winning_outcome: WinningOutcome,
+        settlement_value: Option<i64>,
+        bumps: &SetWinnerBumps,
+    ) -> Result<()> {
         require!(
             !self.market.is_settled,
             PredictionMarketError::MarketAlreadySettled
@@ -51,6 +78,25 @@ impl<'info> SetWinner<'info> {
             PredictionMarketError::SettlementDeadlineNotReached
         );
 
+        if let MarketKind::Scalar { low, high } = self.market.market_kind {
+            let value = settlement_value.ok_or(PredictionMarketError::SettlementValueRequired)?;
+            self.market.settlement_value = value.clamp(low, high);
+        }
+
+        // Last check before settlement becomes irreversible: a vault that can't pay every
+        // outstanding winning token means the accounting is already off somewhere. A rebasing
+        // vault pays pro rata and is expected to drift, so it's exempt.
+        let max_payout = self.market.max_settlement_payout(
+            winning_outcome,
+            self.outcome_yes_mint.supply,
+            self.outcome_no_mint.supply,
+        )?;
+        require!(
+            self.market.rebasing
+                || max_payout <= self.market.backing_balance(self.collateral_vault.amount),
+            PredictionMarketError::InsufficientCollateralForSettlement
+        );
+
         self.market.is_settled = true;
         self.market.winning_outcome = Some(winning_outcome);
 
@@ -86,14 +132,26 @@ impl<'info> SetWinner<'info> {
 
         let market_id_val = self.market.market_id;
         let authority_key = self.authority.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        self.settlement_record.set_inner(SettlementRecord {
+            market_id: market_id_val,
+            resolver: authority_key,
+            outcome: winning_outcome,
+            settled_at: now,
+            source: SETTLEMENT_SOURCE_AUTHORITY,
+            settlement_value: self.market.settlement_value,
+            bump: bumps.settlement_record,
+        });
 
         msg!("Market settled with winning outcome: {:?}", winning_outcome);
 
         emit!(WinningSideSet {
             market_id: market_id_val,
             winning_outcome,
+            settlement_value: self.market.settlement_value,
             authority: authority_key,
-            timestamp: Clock::get()?.unix_timestamp,
+            timestamp: now,
         });
 
         Ok(())
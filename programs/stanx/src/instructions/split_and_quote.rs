@@ -0,0 +1,173 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, MintTo, Transfer};
+use anchor_spl::token_interface::Mint;
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::instructions::*;
+use crate::state::*;
+
+// place_order's accounts plus the two outcome mints the pair is minted from
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct SplitAndQuote<'info> {
+    pub place: PlaceOrder<'info>,
+
+    #[account(
+        mut,
+        constraint = outcome_yes_mint.key() == place.market.outcome_yes_mint,
+        constraint = outcome_yes_mint.to_account_info().owner == &place.token_program.key() @ PredictionMarketError::TokenProgramMismatch
+    )]
+    pub outcome_yes_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = outcome_no_mint.key() == place.market.outcome_no_mint,
+        constraint = outcome_no_mint.to_account_info().owner == &place.token_program.key() @ PredictionMarketError::TokenProgramMismatch
+    )]
+    pub outcome_no_mint: Box<InterfaceAccount<'info, Mint>>,
+}
+
+impl<'info> SplitAndQuote<'info> {
+    /// Splits `amount` collateral into a YES/NO pair and offers both legs for sale, YES at
+    /// `yes_price` and NO at `no_price`, in one transaction.
+    ///
+    /// The pair is minted straight into the escrows and credited to `available_yes`/`available_no`,
+    /// so both sells lock from there like deposited tokens and no outcome accounts are needed.
+    /// Each leg is a regular `place_order` sell: it fills against crossing bids first (their
+    /// UserStats go in `remaining_accounts`) and rests what's left. On a `MakerOnly` book both
+    /// legs are maker quotes instead and fail with `OrderWouldCross` rather than fill.
+    #[allow(clippy::too_many_arguments)]
+    pub fn handler(
+        &mut self,
+        market_id: u32,
+        amount: u64,
+        yes_price: u64,
+        no_price: u64,
+        bumps: &PlaceOrderBumps,
+        remaining_accounts: &[AccountInfo<'info>],
+        program_id: &Pubkey,
+    ) -> Result<SplitAndQuoteResult> {
+        require!(amount > 0, PredictionMarketError::InvalidAmount);
+
+        // Both legs must be able to rest, otherwise an unfilled leg would end up claimable
+        // instead of quoted
+        require!(
+            self.place.orderbook.yes_sell_orders.len() < MAX_ORDERS_PER_SIDE
+                && self.place.orderbook.no_sell_orders.len() < MAX_ORDERS_PER_SIDE,
+            PredictionMarketError::OrderBookFull
+        );
+
+        let market = &mut self.place.market;
+        require!(
+            !market.is_settled,
+            PredictionMarketError::MarketAlreadySettled
+        );
+        market.check_trading_open(Clock::get()?.unix_timestamp)?;
+        require!(
+            self.place.user_collateral.amount >= amount,
+            PredictionMarketError::NotEnoughBalance
+        );
+        market.check_collateral_cap(amount)?;
+        Market::check_mint_supply(
+            self.outcome_yes_mint.supply,
+            self.outcome_no_mint.supply,
+            amount,
+        )?;
+        Market::check_mint_authority(
+            market.key(),
+            self.outcome_yes_mint.mint_authority.into(),
+            self.outcome_no_mint.mint_authority.into(),
+        )?;
+
+        token::transfer(
+            CpiContext::new(
+                self.place.token_program.to_account_info(),
+                Transfer {
+                    from: self.place.user_collateral.to_account_info(),
+                    to: self.place.collateral_vault.to_account_info(),
+                    authority: self.place.user.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let market_id_bytes = market.market_id.to_le_bytes();
+        let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[market.bump]];
+
+        for (mint, escrow) in [
+            (&self.outcome_yes_mint, &self.place.yes_escrow),
+            (&self.outcome_no_mint, &self.place.no_escrow),
+        ] {
+            token::mint_to(
+                CpiContext::new_with_signer(
+                    self.place.token_program.to_account_info(),
+                    MintTo {
+                        mint: mint.to_account_info(),
+                        to: escrow.to_account_info(),
+                        authority: market.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                amount,
+            )?;
+        }
+
+        market.total_collateral_locked = market
+            .total_collateral_locked
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        market.record_mint(amount)?;
+
+        // place_order fills in the rest of a new UserStats
+        let user_stats = &mut self.place.user_stats_account;
+        user_stats.available_yes = user_stats
+            .available_yes
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        user_stats.available_no = user_stats
+            .available_no
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        msg!("Minted {} outcome token pairs into escrow", amount);
+
+        emit!(TokensSplit {
+            market_id,
+            user: self.place.user.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        let maker_quote = self.place.market.book_mode == BookMode::MakerOnly;
+        let yes = self.place.handler(
+            market_id,
+            OrderSide::Sell,
+            TokenType::Yes,
+            amount,
+            yes_price,
+            MAX_ITERATION_CAP,
+            None,
+            maker_quote,
+            bumps,
+            remaining_accounts,
+            program_id,
+        )?;
+        let no = self.place.handler(
+            market_id,
+            OrderSide::Sell,
+            TokenType::No,
+            amount,
+            no_price,
+            MAX_ITERATION_CAP,
+            None,
+            maker_quote,
+            bumps,
+            remaining_accounts,
+            program_id,
+        )?;
+
+        Ok(SplitAndQuoteResult { yes, no })
+    }
+}
@@ -2,6 +2,7 @@ use crate::constants::*;
 use crate::error::*;
 use crate::events::*;
 use crate::state::{Market, UserStats};
+use crate::transfer_lock::*;
 use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token::{self, MintTo, Transfer};
@@ -23,24 +24,29 @@ pub struct SplitToken<'info> {
     #[account(
         mut,
         constraint = user_collateral.mint == market.collateral_mint,
-        constraint = user_collateral.owner == user.key()
+        constraint = user_collateral.owner == user.key(),
+        constraint = user_collateral.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
     )]
     pub user_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
         constraint = collateral_vault.key() == market.collateral_vault
     )]
     pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
-        constraint = outcome_yes_mint.key() == market.outcome_yes_mint
+        constraint = outcome_yes_mint.key() == market.outcome_yes_mint,
+        constraint = outcome_yes_mint.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
     )]
     pub outcome_yes_mint: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
-        constraint = outcome_no_mint.key() == market.outcome_no_mint
+        constraint = outcome_no_mint.key() == market.outcome_no_mint,
+        constraint = outcome_no_mint.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
     )]
     pub outcome_no_mint: Box<InterfaceAccount<'info, Mint>>,
 
@@ -92,6 +98,17 @@ impl<'info> SplitToken<'info> {
             Clock::get()?.unix_timestamp < self.market.settlement_deadline,
             PredictionMarketError::MarketExpired
         );
+        self.market.check_collateral_cap(amount)?;
+        Market::check_mint_supply(
+            self.outcome_yes_mint.supply,
+            self.outcome_no_mint.supply,
+            amount,
+        )?;
+        Market::check_mint_authority(
+            self.market.key(),
+            self.outcome_yes_mint.mint_authority.into(),
+            self.outcome_no_mint.mint_authority.into(),
+        )?;
 
         // Transferring the tokens from user account into Collateral Vault
         token::transfer(
@@ -111,6 +128,19 @@ impl<'info> SplitToken<'info> {
         let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[market_bump]];
 
         // Minting Outcome Tokens
+        thaw_if_restricted(
+            &self.market,
+            &self.token_program,
+            &self.user_outcome_yes,
+            Some(self.outcome_yes_mint.to_account_info()),
+        )?;
+        thaw_if_restricted(
+            &self.market,
+            &self.token_program,
+            &self.user_outcome_no,
+            Some(self.outcome_no_mint.to_account_info()),
+        )?;
+
         token::mint_to(
             CpiContext::new_with_signer(
                 self.token_program.to_account_info(),
@@ -137,11 +167,25 @@ impl<'info> SplitToken<'info> {
             amount,
         )?;
 
+        freeze_if_restricted(
+            &self.market,
+            &self.token_program,
+            &self.user_outcome_yes,
+            Some(self.outcome_yes_mint.to_account_info()),
+        )?;
+        freeze_if_restricted(
+            &self.market,
+            &self.token_program,
+            &self.user_outcome_no,
+            Some(self.outcome_no_mint.to_account_info()),
+        )?;
+
         self.market.total_collateral_locked = self
             .market
             .total_collateral_locked
             .checked_add(amount)
             .ok_or(PredictionMarketError::MathOverflow)?;
+        self.market.record_mint(amount)?;
 
         let user_stats = &mut self.user_stats_account;
         if user_stats.user == Pubkey::default() {
@@ -159,6 +203,15 @@ impl<'info> SplitToken<'info> {
 
         msg!("Minted {} outcome tokens for user", amount);
 
+        // Off by default, the reloads cost compute on every call
+        #[cfg(feature = "invariant-checks")]
+        {
+            self.outcome_yes_mint.reload()?;
+            self.outcome_no_mint.reload()?;
+            self.market
+                .check_supply_balanced(self.outcome_yes_mint.supply, self.outcome_no_mint.supply)?;
+        }
+
         emit!(TokensSplit {
             market_id,
             user: self.user.key(),
@@ -0,0 +1,115 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct Stake<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(constraint = collateral_mint.key() == market.collateral_mint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = user_collateral.mint == market.collateral_mint,
+        constraint = user_collateral.owner == user.key()
+    )]
+    pub user_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = StakeAccount::DISCRIMINATOR.len() + StakeAccount::INIT_SPACE,
+        seeds = [STAKE_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub stake_account: Box<Account<'info, StakeAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [STAKE_VAULT_SEED, market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub stake_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> Stake<'info> {
+    /// Lock collateral in the market's stake vault to raise the user's priority tier
+    ///
+    /// The tier is read when an order is placed, orders already resting keep the
+    /// priority they were placed with
+    pub fn handler(&mut self, market_id: u32, amount: u64, bumps: &StakeBumps) -> Result<()> {
+        require!(amount > 0, PredictionMarketError::InvalidAmount);
+        require!(
+            !self.market.is_settled,
+            PredictionMarketError::MarketAlreadySettled
+        );
+        require!(
+            self.user_collateral.amount >= amount,
+            PredictionMarketError::NotEnoughBalance
+        );
+
+        token::transfer(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                Transfer {
+                    from: self.user_collateral.to_account_info(),
+                    to: self.stake_vault.to_account_info(),
+                    authority: self.user.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let stake = &mut self.stake_account;
+        if stake.user == Pubkey::default() {
+            stake.market_id = market_id;
+            stake.user = self.user.key();
+            stake.bump = bumps.stake_account;
+        }
+        stake.amount = stake
+            .amount
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        self.market.total_staked = self
+            .market
+            .total_staked
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        msg!("Staked {}, stake is now {}", amount, stake.amount);
+
+        emit!(StakeUpdated {
+            market_id,
+            user: self.user.key(),
+            amount: stake.amount,
+            tier: stake.tier(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32, order_id: u64, new_owner: Pubkey)]
+pub struct TransferOrder<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        seeds = [ORDERBOOK_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+        constraint = orderbook.market_id == market_id
+    )]
+    pub orderbook: Account<'info, OrderBook>,
+
+    #[account(
+        mut,
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump = user_stats_account.bump
+    )]
+    pub user_stats_account: Box<Account<'info, UserStats>>,
+
+    // Paid for by the current owner if the new owner hasn't traded on this market yet
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + UserStats::INIT_SPACE,
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), new_owner.as_ref()],
+        bump
+    )]
+    pub new_owner_stats: Box<Account<'info, UserStats>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> TransferOrder<'info> {
+    /// Hands a resting order to `new_owner` in place, keeping its queue position
+    ///
+    /// For custodial migrations. The lock backing the unfilled quantity moves from the
+    /// signer's UserStats to the new owner's, no tokens or collateral move. Fills after the
+    /// transfer are credited to the new owner.
+    pub fn handler(
+        &mut self,
+        market_id: u32,
+        order_id: u64,
+        new_owner: Pubkey,
+        bumps: &TransferOrderBumps,
+    ) -> Result<()> {
+        let user_key = self.user.key();
+        require_keys_neq!(new_owner, user_key, PredictionMarketError::SameOrderOwner);
+
+        let book: &mut OrderBook = &mut self.orderbook;
+        let order = [
+            &mut book.yes_buy_orders,
+            &mut book.yes_sell_orders,
+            &mut book.no_buy_orders,
+            &mut book.no_sell_orders,
+        ]
+        .into_iter()
+        .flat_map(|orders| orders.iter_mut())
+        .find(|o| o.id == order_id)
+        .ok_or(PredictionMarketError::OrdernotFound)?;
+        require_keys_eq!(
+            order.user_key,
+            user_key,
+            PredictionMarketError::NotAuthorized
+        );
+        order.user_key = new_owner;
+        let order = *order;
+        self.orderbook.refresh_book_hash();
+
+        let new_stats = &mut self.new_owner_stats;
+        if new_stats.user == Pubkey::default() {
+            new_stats.user = new_owner;
+            new_stats.market_id = market_id;
+            new_stats.bump = bumps.new_owner_stats;
+        }
+
+        let unfilled_quantity = order
+            .quantity
+            .checked_sub(order.filledquantity)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        let old_stats = &mut self.user_stats_account;
+        let (from, to, amount) = match (order.side, order.token_type) {
+            (OrderSide::Buy, _) => (
+                &mut old_stats.locked_collateral,
+                &mut new_stats.locked_collateral,
+                Order::collateral_for(unfilled_quantity, order.price)?,
+            ),
+            (OrderSide::Sell, TokenType::Yes) => (
+                &mut old_stats.locked_yes,
+                &mut new_stats.locked_yes,
+                unfilled_quantity,
+            ),
+            (OrderSide::Sell, TokenType::No) => (
+                &mut old_stats.locked_no,
+                &mut new_stats.locked_no,
+                unfilled_quantity,
+            ),
+        };
+        *from = from
+            .checked_sub(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        *to = to
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        msg!("Order {} transferred to {}", order_id, new_owner);
+
+        emit!(OrderTransferred {
+            market_id,
+            order_id,
+            from: user_key,
+            to: new_owner,
+            remaining_quantity: unfilled_quantity,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
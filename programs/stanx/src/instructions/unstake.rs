@@ -0,0 +1,118 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct Unstake<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        seeds = [ORDERBOOK_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = orderbook.bump,
+        constraint = orderbook.market_id == market_id
+    )]
+    pub orderbook: Box<Account<'info, OrderBook>>,
+
+    #[account(
+        mut,
+        constraint = user_collateral.mint == market.collateral_mint,
+        constraint = user_collateral.owner == user.key()
+    )]
+    pub user_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [STAKE_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump = stake_account.bump
+    )]
+    pub stake_account: Box<Account<'info, StakeAccount>>,
+
+    #[account(
+        mut,
+        seeds = [STAKE_VAULT_SEED, market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub stake_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> Unstake<'info> {
+    /// Return staked collateral to the user
+    ///
+    /// Rejected while the user has orders resting on the book, otherwise an order could keep
+    /// a priority tier its owner no longer pays for
+    pub fn handler(&mut self, market_id: u32, amount: u64) -> Result<()> {
+        require!(amount > 0, PredictionMarketError::InvalidAmount);
+        require!(
+            self.stake_account.amount >= amount,
+            PredictionMarketError::NotEnoughBalance
+        );
+
+        let user = self.user.key();
+        let orderbook = &self.orderbook;
+        let has_resting_orders = orderbook
+            .yes_buy_orders
+            .iter()
+            .chain(orderbook.yes_sell_orders.iter())
+            .chain(orderbook.no_buy_orders.iter())
+            .chain(orderbook.no_sell_orders.iter())
+            .any(|order| order.user_key == user);
+        require!(
+            !has_resting_orders,
+            PredictionMarketError::StakeLockedByOrders
+        );
+
+        let market_id_bytes = self.market.market_id.to_le_bytes();
+        let bump = self.market.bump;
+        let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[bump]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                Transfer {
+                    from: self.stake_vault.to_account_info(),
+                    to: self.user_collateral.to_account_info(),
+                    authority: self.market.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+        )?;
+
+        let stake = &mut self.stake_account;
+        stake.amount -= amount;
+        self.market.total_staked = self
+            .market
+            .total_staked
+            .checked_sub(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        msg!("Unstaked {}, stake is now {}", amount, stake.amount);
+
+        emit!(StakeUpdated {
+            market_id,
+            user,
+            amount: stake.amount,
+            tier: stake.tier(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
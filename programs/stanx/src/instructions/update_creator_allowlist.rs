@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct UpdateCreatorAllowlist<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ PredictionMarketError::NotAuthorized
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> UpdateCreatorAllowlist<'info> {
+    pub fn add_creator(&mut self, creator: Pubkey) -> Result<()> {
+        let creators = &mut self.config.creators;
+        if !creators.contains(&creator) {
+            require!(
+                creators.len() < MAX_MARKET_CREATORS,
+                PredictionMarketError::CreatorAllowlistFull
+            );
+            creators.push(creator);
+        }
+
+        self.emit_update(creator, true)
+    }
+
+    /// Removing the last creator opens market creation to anyone again
+    pub fn remove_creator(&mut self, creator: Pubkey) -> Result<()> {
+        self.config.creators.retain(|c| *c != creator);
+
+        self.emit_update(creator, false)
+    }
+
+    fn emit_update(&self, creator: Pubkey, allowed: bool) -> Result<()> {
+        msg!("Market creator {} allowed: {}", creator, allowed);
+
+        emit!(CreatorAllowlistUpdated {
+            creator,
+            allowed,
+            admin: self.admin.key(),
+            creators: self.config.creators.len() as u32,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
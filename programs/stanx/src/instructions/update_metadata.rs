@@ -21,14 +21,46 @@ pub struct UpdateMetadata<'info> {
 }
 
 impl<'info> UpdateMetadata<'info> {
-    pub fn handler(&mut self, _market_id: u32, new_metadata_url: String) -> Result<()> {
+    /// Outcome labels are only changed when provided
+    pub fn handler(
+        &mut self,
+        _market_id: u32,
+        new_metadata_url: String,
+        new_outcome_a_label: Option<String>,
+        new_outcome_b_label: Option<String>,
+    ) -> Result<()> {
         require!(
             new_metadata_url.len() <= 200,
             PredictionMarketError::InvalidMetadata
         );
 
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= self
+                .market
+                .last_metadata_update
+                .saturating_add(METADATA_UPDATE_INTERVAL),
+            PredictionMarketError::MetadataUpdateTooFrequent
+        );
+        self.market.last_metadata_update = now;
+
         self.market.meta_data_url = new_metadata_url.clone();
 
+        if let Some(label) = new_outcome_a_label {
+            require!(
+                label.len() <= 32,
+                PredictionMarketError::InvalidOutcomeLabel
+            );
+            self.market.outcome_a_label = label;
+        }
+        if let Some(label) = new_outcome_b_label {
+            require!(
+                label.len() <= 32,
+                PredictionMarketError::InvalidOutcomeLabel
+            );
+            self.market.outcome_b_label = label;
+        }
+
         let market_id_val = self.market.market_id;
         let authority_key = self.authority.key();
 
@@ -38,7 +70,9 @@ impl<'info> UpdateMetadata<'info> {
             market_id: market_id_val,
             authority: authority_key,
             new_metadata_url,
-            timestamp: Clock::get()?.unix_timestamp,
+            outcome_a_label: self.market.outcome_a_label.clone(),
+            outcome_b_label: self.market.outcome_b_label.clone(),
+            timestamp: now,
         });
 
         Ok(())
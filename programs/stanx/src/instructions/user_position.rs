@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct GetUserPosition<'info> {
+    /// CHECK: only used to derive the UserStats PDA
+    pub user: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump = user_stats_account.bump,
+    )]
+    pub user_stats_account: Account<'info, UserStats>,
+}
+
+impl<'info> GetUserPosition<'info> {
+    /// Read-only view of a user's balances and fee/reward totals, returned through return data
+    pub fn handler(&self) -> Result<UserPosition> {
+        Ok(self.user_stats_account.position())
+    }
+}
@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use crate::constants::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct VerifySupplyInvariant<'info> {
+    #[account(
+        seeds = [MARKET_SEED, market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(constraint = outcome_yes_mint.key() == market.outcome_yes_mint)]
+    pub outcome_yes_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(constraint = outcome_no_mint.key() == market.outcome_no_mint)]
+    pub outcome_no_mint: InterfaceAccount<'info, Mint>,
+}
+
+impl<'info> VerifySupplyInvariant<'info> {
+    /// Read-only check that the YES and NO supplies are equal while the market is live,
+    /// fails with `SupplyInvariantViolated` otherwise. After settlement the supplies are
+    /// only reported, redemptions burn the sides unevenly.
+    pub fn handler(&self) -> Result<SupplyReport> {
+        let yes_supply = self.outcome_yes_mint.supply;
+        let no_supply = self.outcome_no_mint.supply;
+
+        self.market.check_supply_balanced(yes_supply, no_supply)?;
+
+        Ok(SupplyReport {
+            yes_supply,
+            no_supply,
+            is_settled: self.market.is_settled,
+        })
+    }
+}
@@ -0,0 +1,123 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct WithdrawCollateral<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        seeds = [USER_STATS_SEED, market_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump = user_stats_account.bump
+    )]
+    pub user_stats_account: Box<Account<'info, UserStats>>,
+
+    #[account(
+        mut,
+        constraint = user_collateral.mint == market.collateral_mint,
+        constraint = user_collateral.owner == user.key(),
+        constraint = user_collateral.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
+    )]
+    pub user_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
+        constraint = collateral_vault.key() == market.collateral_vault,
+        constraint = collateral_vault.to_account_info().owner == &token_program.key() @ PredictionMarketError::TokenProgramMismatch
+    )]
+    pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> WithdrawCollateral<'info> {
+    /// Return unused `available_collateral` to the user, allowed at any time
+    ///
+    /// Paid like any other claim (see `Market::socialized_payout`): under a socialized loss or a
+    /// rebasing vault the user receives their share of `amount`, while the full `amount` is
+    /// deducted from their balance and from `total_collateral_locked`
+    pub fn handler(&mut self, market_id: u32, amount: u64) -> Result<()> {
+        require!(amount > 0, PredictionMarketError::InvalidAmount);
+        require!(
+            self.user_stats_account.available_collateral >= amount,
+            PredictionMarketError::NotEnoughBalance
+        );
+
+        let paid = self
+            .market
+            .socialized_payout(amount, self.collateral_vault.amount)?;
+        require!(
+            self.collateral_vault.amount >= paid,
+            PredictionMarketError::InsufficientEscrow
+        );
+
+        let market_id_bytes = self.market.market_id.to_le_bytes();
+        let bump = self.market.bump;
+        let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[bump]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                Transfer {
+                    from: self.collateral_vault.to_account_info(),
+                    to: self.user_collateral.to_account_info(),
+                    authority: self.market.to_account_info(),
+                },
+                &[seeds],
+            ),
+            paid,
+        )?;
+
+        let user_stats = &mut self.user_stats_account;
+        user_stats.available_collateral -= amount;
+
+        self.market.total_collateral_locked = self
+            .market
+            .total_collateral_locked
+            .checked_sub(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        msg!(
+            "Withdrew {}, available collateral is now {}",
+            paid,
+            user_stats.available_collateral
+        );
+
+        if paid < amount {
+            emit!(LossSocialized {
+                market_id,
+                user: self.user.key(),
+                nominal: amount,
+                paid,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        }
+
+        emit!(CollateralWithdrawn {
+            market_id,
+            user: self.user.key(),
+            amount,
+            available_collateral: user_stats.available_collateral,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct WithdrawIncentives<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        constraint = authority_collateral.mint == market.collateral_mint,
+        constraint = authority_collateral.owner == authority.key()
+    )]
+    pub authority_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = incentive_vault.key() == market.incentive_vault
+    )]
+    pub incentive_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> WithdrawIncentives<'info> {
+    /// Return the unspent reward pool to the authority, only once trading is over
+    pub fn handler(&mut self, market_id: u32) -> Result<()> {
+        require!(
+            self.market.is_settled,
+            PredictionMarketError::MarketNotSettled
+        );
+
+        let amount = self.market.incentive_pool;
+        require!(amount > 0, PredictionMarketError::NothingToClaim);
+
+        let market_id_bytes = self.market.market_id.to_le_bytes();
+        let bump = self.market.bump;
+        let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[bump]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                Transfer {
+                    from: self.incentive_vault.to_account_info(),
+                    to: self.authority_collateral.to_account_info(),
+                    authority: self.market.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+        )?;
+
+        self.market.incentive_pool = 0;
+
+        msg!("Withdrew {} unspent maker incentives", amount);
+
+        emit!(IncentivesWithdrawn {
+            market_id,
+            authority: self.authority.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct WithdrawSeed<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        constraint = authority_collateral.mint == market.collateral_mint,
+        constraint = authority_collateral.owner == authority.key()
+    )]
+    pub authority_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
+        constraint = collateral_vault.key() == market.collateral_vault
+    )]
+    pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> WithdrawSeed<'info> {
+    /// Returns the collateral the market was seeded with to the authority, only once the
+    /// market is settled. Paid like any other claim, so a socialized loss or a rebasing
+    /// vault scales it too.
+    pub fn handler(&mut self, market_id: u32) -> Result<()> {
+        require!(
+            self.market.is_settled,
+            PredictionMarketError::MarketNotSettled
+        );
+
+        let seed = self.market.seed_collateral;
+        require!(seed > 0, PredictionMarketError::NothingToClaim);
+
+        let paid = self
+            .market
+            .socialized_payout(seed, self.collateral_vault.amount)?;
+        require!(
+            self.collateral_vault.amount >= paid,
+            PredictionMarketError::InsufficientEscrow
+        );
+
+        let market_id_bytes = self.market.market_id.to_le_bytes();
+        let bump = self.market.bump;
+        let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[bump]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                Transfer {
+                    from: self.collateral_vault.to_account_info(),
+                    to: self.authority_collateral.to_account_info(),
+                    authority: self.market.to_account_info(),
+                },
+                &[seeds],
+            ),
+            paid,
+        )?;
+
+        self.market.seed_collateral = 0;
+        self.market.total_collateral_locked = self
+            .market
+            .total_collateral_locked
+            .checked_sub(seed)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        msg!("Withdrew {} seed collateral", paid);
+
+        emit!(SeedWithdrawn {
+            market_id,
+            authority: self.authority.key(),
+            seed,
+            paid,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(market_id: u32)]
+pub struct WithdrawTreasury<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [MARKET_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.bump,
+        constraint = market.market_id == market_id,
+        constraint = market.authority == authority.key()
+    )]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        constraint = authority_collateral.mint == market.collateral_mint,
+        constraint = authority_collateral.owner == authority.key()
+    )]
+    pub authority_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, market.market_id.to_le_bytes().as_ref()],
+        bump = market.vault_bump,
+        constraint = collateral_vault.key() == market.collateral_vault
+    )]
+    pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> WithdrawTreasury<'info> {
+    /// Pays the trading fees and the rounding dust collected from scalar claims to the authority
+    ///
+    /// Both are in the vault but not in `total_collateral_locked`. `close_market` fails with
+    /// `TreasuryNotWithdrawn` until this has run, since the close closes the market account.
+    pub fn handler(&mut self, market_id: u32) -> Result<()> {
+        let amount = self.market.treasury_balance;
+        require!(amount > 0, PredictionMarketError::NothingToClaim);
+        require!(
+            self.collateral_vault.amount >= amount,
+            PredictionMarketError::InsufficientEscrow
+        );
+
+        let market_id_bytes = self.market.market_id.to_le_bytes();
+        let bump = self.market.bump;
+        let seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[bump]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                Transfer {
+                    from: self.collateral_vault.to_account_info(),
+                    to: self.authority_collateral.to_account_info(),
+                    authority: self.market.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+        )?;
+
+        self.market.treasury_balance = 0;
+
+        msg!("Withdrew {} collateral from the treasury", amount);
+
+        emit!(FeesWithdrawn {
+            market_id,
+            authority: self.authority.key(),
+            destination: self.authority_collateral.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -4,7 +4,10 @@ pub mod error;
 pub mod events;
 pub mod instructions;
 pub mod state;
+pub mod reentrancy_guard;
+pub mod transfer_lock;
 pub use crate::instructions::*;
+pub use crate::reentrancy_guard::*;
 pub use crate::state::*;
 
 declare_id!("AA9xwyVDCqHJTSPtigKyvLhaMpgjmU7CCT99SXWt43DP");
@@ -13,24 +16,52 @@ declare_id!("AA9xwyVDCqHJTSPtigKyvLhaMpgjmU7CCT99SXWt43DP");
 pub mod prediction_market_turbin3 {
     use super::*;
 
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize_market(
         ctx: Context<InitializeMarket>,
         market_id: u32,
         settlement_deadline: i64,
         meta_data_url: String,
+        category: u16,
+        restricted_transfer: bool,
+        outcome_a_label: String,
+        outcome_b_label: String,
+        market_kind: MarketKind,
+        min_price: Option<u64>,
+        max_price: Option<u64>,
+        slug: String,
+        rebasing: bool,
+        seed_collateral: u64,
     ) -> Result<()> {
-        ctx.accounts
-            .initialise(market_id, settlement_deadline, &ctx.bumps, meta_data_url)
+        ctx.accounts.initialise(
+            market_id,
+            settlement_deadline,
+            &ctx.bumps,
+            meta_data_url,
+            category,
+            restricted_transfer,
+            outcome_a_label,
+            outcome_b_label,
+            market_kind,
+            min_price,
+            max_price,
+            slug,
+            rebasing,
+            seed_collateral,
+        )
     }
 
     pub fn split_tokens(ctx: Context<SplitToken>, market_id: u32, amount: u64) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
         ctx.accounts.split_token(market_id, amount, &ctx.bumps)
     }
 
     pub fn merge_tokens(ctx: Context<MergeTokens>, market_id: u32, amount: u64) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
         ctx.accounts.merge_tokens(market_id, amount)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn place_order<'info>(
         ctx: Context<'_, '_, '_, 'info, PlaceOrder<'info>>,
         market_id: u32,
@@ -39,7 +70,9 @@ pub mod prediction_market_turbin3 {
         quantity: u64,
         price: u64,
         max_iteration: u64,
-    ) -> Result<()> {
+        client_nonce: Option<u64>,
+    ) -> Result<PlaceOrderResult> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
         let remaining_accounts = ctx.remaining_accounts;
         let program_id = ctx.program_id;
         ctx.accounts.handler(
@@ -49,12 +82,42 @@ pub mod prediction_market_turbin3 {
             quantity,
             price,
             max_iteration,
+            client_nonce,
+            false,
+            &ctx.bumps,
+            remaining_accounts,
+            program_id,
+        )
+    }
+
+    pub fn place_quote<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceOrder<'info>>,
+        market_id: u32,
+        side: OrderSide,
+        token_type: TokenType,
+        quantity: u64,
+        price: u64,
+        client_nonce: Option<u64>,
+    ) -> Result<PlaceOrderResult> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        let remaining_accounts = ctx.remaining_accounts;
+        let program_id = ctx.program_id;
+        ctx.accounts.handler(
+            market_id,
+            side,
+            token_type,
+            quantity,
+            price,
+            constants::MAX_ITERATION_CAP,
+            client_nonce,
+            true,
             &ctx.bumps,
             remaining_accounts,
             program_id,
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn market_order<'info>(
         ctx: Context<'_, '_, '_, 'info, MarketOrder<'info>>,
         market_id: u32,
@@ -62,7 +125,10 @@ pub mod prediction_market_turbin3 {
         token_type: TokenType,
         order_amount: u64,
         max_iteration: u64,
+        auto_mint: bool,
+        rest_remainder_at: Option<u64>,
     ) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
         let remaining_accounts = ctx.remaining_accounts;
         let program_id = ctx.program_id;
         ctx.accounts.handler(
@@ -71,25 +137,88 @@ pub mod prediction_market_turbin3 {
             token_type,
             order_amount,
             max_iteration,
+            auto_mint,
+            rest_remainder_at,
             &ctx.bumps,
             remaining_accounts,
             program_id,
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub fn replace_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReplaceOrder<'info>>,
+        market_id: u32,
+        cancel_order_id: u64,
+        new_side: OrderSide,
+        new_token_type: TokenType,
+        new_quantity: u64,
+        new_price: u64,
+        max_iteration: u64,
+        client_nonce: Option<u64>,
+    ) -> Result<PlaceOrderResult> {
+        let remaining_accounts = ctx.remaining_accounts;
+        let program_id = ctx.program_id;
+        ctx.accounts.handler(
+            market_id,
+            cancel_order_id,
+            new_side,
+            new_token_type,
+            new_quantity,
+            new_price,
+            max_iteration,
+            client_nonce,
+            &ctx.bumps.place,
+            remaining_accounts,
+            program_id,
+        )
+    }
+
     pub fn cancel_order(ctx: Context<CancelOrder>, market_id: u32, order_id: u64) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
         ctx.accounts.handler(market_id, order_id)
     }
 
-    pub fn close_market(ctx: Context<CloseMarket>, market_id: u32) -> Result<()> {
-        ctx.accounts.handler(market_id)
+    pub fn cancel_and_claim(
+        ctx: Context<CancelAndClaim>,
+        market_id: u32,
+        order_id: u64,
+    ) -> Result<()> {
+        let program_id = ctx.program_id;
+        ctx.accounts.handler(market_id, order_id, program_id)
+    }
+
+    pub fn close_market(
+        ctx: Context<CloseMarket>,
+        market_id: u32,
+        cancel_own_orders: bool,
+    ) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts.handler(market_id, cancel_own_orders)
     }
 
     pub fn claim_funds(ctx: Context<ClaimFunds>, market_id: u32) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts.handler(market_id)
+    }
+
+    pub fn claim_collateral(ctx: Context<ClaimCollateral>, market_id: u32) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
         ctx.accounts.handler(market_id)
     }
 
-    pub fn claim_rewards(ctx: Context<ClaimRewards>, market_id: u32) -> Result<()> {
+    pub fn claim_rewards(
+        ctx: Context<ClaimRewards>,
+        market_id: u32,
+        max_amount: Option<u64>,
+        close_empty_ata: bool,
+    ) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts.handler(market_id, max_amount, close_empty_ata)
+    }
+
+    pub fn redeem_all(ctx: Context<RedeemAll>, market_id: u32) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
         ctx.accounts.handler(market_id)
     }
 
@@ -97,15 +226,441 @@ pub mod prediction_market_turbin3 {
         ctx: Context<SetWinner>,
         market_id: u32,
         winning_outcome: WinningOutcome,
+        settlement_value: Option<i64>,
     ) -> Result<()> {
-        ctx.accounts.handler(market_id, winning_outcome)
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts
+            .handler(market_id, winning_outcome, settlement_value, &ctx.bumps)
     }
 
     pub fn update_metadata(
         ctx: Context<UpdateMetadata>,
         market_id: u32,
         new_metadata_url: String,
+        new_outcome_a_label: Option<String>,
+        new_outcome_b_label: Option<String>,
+    ) -> Result<()> {
+        ctx.accounts.handler(
+            market_id,
+            new_metadata_url,
+            new_outcome_a_label,
+            new_outcome_b_label,
+        )
+    }
+
+    pub fn migrate_user_stats(ctx: Context<MigrateUserStats>, market_id: u32) -> Result<()> {
+        ctx.accounts.handler(market_id)
+    }
+
+    pub fn migrate_market(ctx: Context<MigrateMarket>, market_id: u32) -> Result<()> {
+        ctx.accounts.handler(market_id)
+    }
+
+    pub fn set_collateral_cap(
+        ctx: Context<SetCollateralCap>,
+        market_id: u32,
+        max_collateral: u64,
+    ) -> Result<()> {
+        ctx.accounts.handler(market_id, max_collateral)
+    }
+
+    pub fn set_wash_trade_guard(
+        ctx: Context<SetWashTradeGuard>,
+        market_id: u32,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.handler(market_id, enabled)
+    }
+
+    pub fn fund_incentives(ctx: Context<FundIncentives>, market_id: u32, amount: u64) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts.handler(market_id, amount)
+    }
+
+    pub fn set_reward_rate(
+        ctx: Context<SetRewardRate>,
+        market_id: u32,
+        reward_rate: u64,
+    ) -> Result<()> {
+        ctx.accounts.handler(market_id, reward_rate)
+    }
+
+    pub fn withdraw_incentives(ctx: Context<WithdrawIncentives>, market_id: u32) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts.handler(market_id)
+    }
+
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, market_id: u32) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts.handler(market_id)
+    }
+
+    pub fn setup_user(ctx: Context<SetupUser>, market_id: u32) -> Result<()> {
+        ctx.accounts.handler(market_id, &ctx.bumps)
+    }
+
+    pub fn user_position(ctx: Context<GetUserPosition>, _market_id: u32) -> Result<UserPosition> {
+        ctx.accounts.handler()
+    }
+
+    pub fn market_status(ctx: Context<GetMarketStatus>, _market_id: u32) -> Result<MarketStatus> {
+        ctx.accounts.handler()
+    }
+
+    pub fn implied_probability(
+        ctx: Context<GetImpliedProbability>,
+        _market_id: u32,
+    ) -> Result<ImpliedProbability> {
+        ctx.accounts.handler()
+    }
+
+    pub fn open_interest(ctx: Context<GetOpenInterest>, _market_id: u32) -> Result<OpenInterest> {
+        ctx.accounts.handler()
+    }
+
+    pub fn get_market_config(
+        ctx: Context<GetMarketConfig>,
+        _market_id: u32,
+    ) -> Result<MarketConfig> {
+        ctx.accounts.handler()
+    }
+
+    pub fn reconcile_user(ctx: Context<ReconcileUser>, _market_id: u32) -> Result<ReconcileReport> {
+        ctx.accounts.handler()
+    }
+
+    pub fn set_rounding_mode(
+        ctx: Context<SetRoundingMode>,
+        market_id: u32,
+        rounding_mode: RoundingMode,
+    ) -> Result<()> {
+        ctx.accounts.handler(market_id, rounding_mode)
+    }
+
+    pub fn cancel_in_range(
+        ctx: Context<CancelInRange>,
+        market_id: u32,
+        token_type: TokenType,
+        side: OrderSide,
+        min_price: u64,
+        max_price: u64,
+    ) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts
+            .handler(market_id, token_type, side, min_price, max_price)
+    }
+
+    pub fn relist_claimable(
+        ctx: Context<RelistClaimable>,
+        market_id: u32,
+        token_type: TokenType,
+        quantity: u64,
+        price: u64,
+    ) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts.handler(market_id, token_type, quantity, price)
+    }
+
+    pub fn settle_orderbook<'info>(
+        ctx: Context<'_, '_, '_, 'info, SettleOrderBook<'info>>,
+        market_id: u32,
+    ) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        let remaining_accounts = ctx.remaining_accounts;
+        let program_id = ctx.program_id;
+        ctx.accounts
+            .handler(market_id, remaining_accounts, program_id)
+    }
+
+    pub fn reset_orderbook<'info>(
+        ctx: Context<'_, '_, '_, 'info, ResetOrderBook<'info>>,
+        market_id: u32,
+    ) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        let remaining_accounts = ctx.remaining_accounts;
+        let program_id = ctx.program_id;
+        ctx.accounts
+            .handler(market_id, remaining_accounts, program_id)
+    }
+
+    pub fn reserve_orderbook_capacity(
+        ctx: Context<ReserveOrderBookCapacity>,
+        market_id: u32,
+        orders_per_side: u32,
+    ) -> Result<()> {
+        ctx.accounts.handler(market_id, orders_per_side)
+    }
+
+    pub fn set_claim_delegate(
+        ctx: Context<SetClaimDelegate>,
+        market_id: u32,
+        delegate: Option<Pubkey>,
+    ) -> Result<()> {
+        ctx.accounts.handler(market_id, delegate)
+    }
+
+    pub fn initialize_market_from_template(
+        ctx: Context<InitializeMarketFromTemplate>,
+        market_id: u32,
+        _template_market_id: u32,
+        settlement_deadline: i64,
+        slug: String,
+        seed_collateral: u64,
+    ) -> Result<()> {
+        ctx.accounts.handler(
+            market_id,
+            settlement_deadline,
+            slug,
+            seed_collateral,
+            &ctx.bumps.init,
+        )
+    }
+
+    pub fn verify_supply_invariant(
+        ctx: Context<VerifySupplyInvariant>,
+        _market_id: u32,
+    ) -> Result<SupplyReport> {
+        ctx.accounts.handler()
+    }
+
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        ctx.accounts.handler(&ctx.bumps)
+    }
+
+    pub fn add_market_creator(ctx: Context<UpdateCreatorAllowlist>, creator: Pubkey) -> Result<()> {
+        ctx.accounts.add_creator(creator)
+    }
+
+    pub fn remove_market_creator(
+        ctx: Context<UpdateCreatorAllowlist>,
+        creator: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.remove_creator(creator)
+    }
+
+    pub fn set_min_seed_collateral(
+        ctx: Context<SetMinSeedCollateral>,
+        min_seed_collateral: u64,
+    ) -> Result<()> {
+        ctx.accounts.handler(min_seed_collateral)
+    }
+
+    pub fn withdraw_seed(ctx: Context<WithdrawSeed>, market_id: u32) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts.handler(market_id)
+    }
+
+    pub fn match_preview(
+        ctx: Context<MatchPreview>,
+        market_id: u32,
+        side: OrderSide,
+        token_type: TokenType,
+        quantity: u64,
+        price: u64,
+        max_iteration: u64,
+    ) -> Result<Vec<Pubkey>> {
+        ctx.accounts.handler(
+            market_id,
+            side,
+            token_type,
+            quantity,
+            price,
+            max_iteration,
+            ctx.program_id,
+        )
+    }
+
+    pub fn stake(ctx: Context<Stake>, market_id: u32, amount: u64) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts.handler(market_id, amount, &ctx.bumps)
+    }
+
+    pub fn unstake(ctx: Context<Unstake>, market_id: u32, amount: u64) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts.handler(market_id, amount)
+    }
+
+    pub fn deposit_collateral(
+        ctx: Context<DepositCollateral>,
+        market_id: u32,
+        amount: u64,
+    ) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts.handler(market_id, amount)
+    }
+
+    pub fn withdraw_collateral(
+        ctx: Context<WithdrawCollateral>,
+        market_id: u32,
+        amount: u64,
     ) -> Result<()> {
-        ctx.accounts.handler(market_id, new_metadata_url)
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts.handler(market_id, amount)
+    }
+
+    pub fn deposit_tokens(
+        ctx: Context<DepositTokens>,
+        market_id: u32,
+        token_type: TokenType,
+        amount: u64,
+    ) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts.handler(market_id, token_type, amount)
+    }
+
+    pub fn withdraw_tokens(
+        ctx: Context<WithdrawTokens>,
+        market_id: u32,
+        token_type: TokenType,
+        amount: u64,
+    ) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        ctx.accounts.handler(market_id, token_type, amount)
+    }
+
+    pub fn set_trading_cutoff(
+        ctx: Context<SetTradingCutoff>,
+        market_id: u32,
+        trading_cutoff_buffer: i64,
+    ) -> Result<()> {
+        ctx.accounts.handler(market_id, trading_cutoff_buffer)
+    }
+
+    pub fn resolve_slug(ctx: Context<ResolveSlug>, _slug: String) -> Result<u32> {
+        ctx.accounts.handler()
+    }
+
+    pub fn force_settle_and_close<'info>(
+        ctx: Context<'_, '_, '_, 'info, ForceSettleAndClose<'info>>,
+        market_id: u32,
+    ) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.close.market)?;
+        let remaining_accounts = ctx.remaining_accounts;
+        let program_id = ctx.program_id;
+        ctx.accounts
+            .handler(market_id, remaining_accounts, program_id)
+    }
+
+    pub fn get_closed_order(
+        ctx: Context<GetClosedOrder>,
+        _market_id: u32,
+        order_id: u64,
+    ) -> Result<ClosedOrder> {
+        ctx.accounts.handler(order_id)
+    }
+
+    pub fn set_stp_policy(
+        ctx: Context<SetStpPolicy>,
+        market_id: u32,
+        stp_policy: StpPolicy,
+    ) -> Result<()> {
+        ctx.accounts.handler(market_id, stp_policy)
+    }
+
+    pub fn split_and_quote<'info>(
+        ctx: Context<'_, '_, '_, 'info, SplitAndQuote<'info>>,
+        market_id: u32,
+        amount: u64,
+        yes_price: u64,
+        no_price: u64,
+    ) -> Result<SplitAndQuoteResult> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.place.market)?;
+        let remaining_accounts = ctx.remaining_accounts;
+        let program_id = ctx.program_id;
+        ctx.accounts.handler(
+            market_id,
+            amount,
+            yes_price,
+            no_price,
+            &ctx.bumps.place,
+            remaining_accounts,
+            program_id,
+        )
+    }
+
+    pub fn prune_expired_orders<'info>(
+        ctx: Context<'_, '_, '_, 'info, PruneExpiredOrders<'info>>,
+        market_id: u32,
+        max_iteration: u64,
+    ) -> Result<()> {
+        let _guard = ReentrancyGuard::enter(&ctx.accounts.market)?;
+        let remaining_accounts = ctx.remaining_accounts;
+        let program_id = ctx.program_id;
+        ctx.accounts
+            .handler(market_id, max_iteration, remaining_accounts, program_id)
+    }
+
+    pub fn set_lot_size(ctx: Context<SetLotSize>, market_id: u32, lot_size: u64) -> Result<()> {
+        ctx.accounts.handler(market_id, lot_size)
+    }
+
+    pub fn set_max_order_quantity(
+        ctx: Context<SetMaxOrderQuantity>,
+        market_id: u32,
+        max_order_quantity: u64,
+    ) -> Result<()> {
+        ctx.accounts.handler(market_id, max_order_quantity)
+    }
+
+    pub fn set_book_mode(
+        ctx: Context<SetBookMode>,
+        market_id: u32,
+        book_mode: BookMode,
+    ) -> Result<()> {
+        ctx.accounts.handler(market_id, book_mode)
+    }
+
+    pub fn resync_collateral(ctx: Context<ResyncCollateral>, market_id: u32) -> Result<()> {
+        ctx.accounts.handler(market_id)
+    }
+
+    pub fn transfer_order(
+        ctx: Context<TransferOrder>,
+        market_id: u32,
+        order_id: u64,
+        new_owner: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.handler(market_id, order_id, new_owner, &ctx.bumps)
+    }
+
+    pub fn set_socialized_loss(
+        ctx: Context<SetSocializedLoss>,
+        market_id: u32,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.handler(market_id, enabled)
+    }
+
+    pub fn set_side_paused(
+        ctx: Context<SetSidePaused>,
+        market_id: u32,
+        token_type: TokenType,
+        paused: bool,
+    ) -> Result<()> {
+        ctx.accounts.handler(market_id, token_type, paused)
+    }
+
+    pub fn set_fee_exempt(ctx: Context<SetFeeExempt>, market_id: u32, exempt: bool) -> Result<()> {
+        ctx.accounts.handler(market_id, exempt)
+    }
+
+    pub fn set_fee_schedule(
+        ctx: Context<SetFeeSchedule>,
+        market_id: u32,
+        fee_schedule: Option<FeeSchedule>,
+    ) -> Result<()> {
+        ctx.accounts.handler(market_id, fee_schedule)
+    }
+
+    pub fn effective_fee(ctx: Context<GetEffectiveFee>, _market_id: u32) -> Result<u16> {
+        ctx.accounts.handler()
+    }
+
+    pub fn orderbook_rent_for(
+        ctx: Context<GetOrderBookRent>,
+        _market_id: u32,
+        orders_per_side: u32,
+    ) -> Result<u64> {
+        ctx.accounts.handler(orders_per_side)
     }
 }
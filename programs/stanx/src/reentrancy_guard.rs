@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::error::*;
+use crate::state::*;
+
+// Defense in depth against reentrancy through token program CPIs (e.g. Token-2022 transfer
+// hooks) until the CPI ordering is fully audited. The runtime already rejects most reentrant
+// calls. Instructions that move funds hold a guard for their whole run. Entering writes
+// `Market.in_progress` straight into the account data, so a nested call, which deserializes
+// the market afresh, sees the flag. The guard clears the byte when dropped, on every exit path.
+// A failed transaction is rolled back anyway, so the flag can't be left set.
+//
+// Cost: one byte of Market rent and two single-byte writes per guarded instruction.
+
+pub struct ReentrancyGuard<'info> {
+    market: AccountInfo<'info>,
+}
+
+impl<'info> ReentrancyGuard<'info> {
+    /// Fails with `ReentrancyDetected` if an instruction on this market is already running
+    pub fn enter(market: &Account<'info, Market>) -> Result<Self> {
+        require!(
+            !market.in_progress,
+            PredictionMarketError::ReentrancyDetected
+        );
+        let market = market.to_account_info();
+        market.try_borrow_mut_data()?[MARKET_IN_PROGRESS_OFFSET] = 1;
+        Ok(ReentrancyGuard { market })
+    }
+}
+
+impl Drop for ReentrancyGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut data) = self.market.try_borrow_mut_data() {
+            data[MARKET_IN_PROGRESS_OFFSET] = 0;
+        }
+    }
+}
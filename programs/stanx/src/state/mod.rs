@@ -1,10 +1,22 @@
 use anchor_lang::prelude::*;
+use solana_sha256_hasher::{hash, hashv};
+
+use crate::constants::{
+    CLOSED_ORDERS_CAPACITY, MARKETS_PER_REGISTRY_PAGE, MAX_MARKET_CREATORS, MAX_SLUG_LEN,
+    OUTCOME_TOKEN_DECIMALS, STAKE_TIER_THRESHOLDS, TOKEN_DECIMALS_SCALE, TRADING_FEE_BPS,
+};
+use crate::error::PredictionMarketError;
 
 #[account]
 #[derive(InitSpace)]
 pub struct Market {
     pub authority: Pubkey,
     pub market_id: u32,
+    // Kept ahead of any variable-length field so it sits at MARKET_CATEGORY_OFFSET
+    pub category: u16,
+    // Set while a guarded instruction runs, also ahead of any variable-length field so it sits
+    // at MARKET_IN_PROGRESS_OFFSET
+    pub in_progress: bool,
     pub settlement_deadline: i64,
     pub collateral_mint: Pubkey,
     pub collateral_vault: Pubkey,
@@ -18,6 +30,719 @@ pub struct Market {
     pub outcome_no_mint: Pubkey,
     pub yes_escrow: Pubkey,
     pub no_escrow: Pubkey,
+    // 0 = unlimited
+    pub max_collateral: u64,
+    pub wash_trade_guard: bool,
+    // Liquidity mining, inactive while reward_rate == 0.
+    // reward_rate is micro collateral per display token per second a maker's quantity rests on the book.
+    pub reward_rate: u64,
+    pub incentive_vault: Pubkey,
+    // Unspent balance of incentive_vault
+    pub incentive_pool: u64,
+    // Outcome token accounts stay frozen outside program instructions until settlement
+    pub restricted_transfer: bool,
+    // Who gets the sub-unit when a fill's collateral value isn't a whole base unit
+    pub rounding_mode: RoundingMode,
+    // Display names for the YES/NO outcomes, empty = "Yes"/"No"
+    #[max_len(32)]
+    pub outcome_a_label: String,
+    #[max_len(32)]
+    pub outcome_b_label: String,
+    // Stored so trading instructions can re-derive the vault/escrow PDAs cheaply
+    pub vault_bump: u8,
+    pub yes_escrow_bump: u8,
+    pub no_escrow_bump: u8,
+    // TWAP accumulator of the YES mid price (Uniswap V2 style): sum of mid × seconds, wrapping.
+    // TWAP over [t1, t2] = (cumulative(t2) - cumulative(t1)) / (t2 - t1)
+    pub price_cumulative: u128,
+    pub last_price_update: i64,
+    pub market_kind: MarketKind,
+    // Scalar markets only: value recorded by set_winner, clamped to [low, high]
+    pub settlement_value: i64,
+    // Scalar markets only: outcome tokens redeemed so far, keeps payout rounding exact
+    pub yes_redeemed: u64,
+    pub no_redeemed: u64,
+    // Orders must satisfy min_price <= price < max_price (collateral base units per whole token)
+    pub min_price: u64,
+    pub max_price: u64,
+    // When meta_data_url or the labels last changed, set at creation
+    pub last_metadata_update: i64,
+    // Collateral held in the stake vault, must be unstaked before the market can close
+    pub total_staked: u64,
+    // Trading closes this many seconds before settlement_deadline, 0 = trade up to the deadline
+    pub trading_cutoff_buffer: i64,
+    // URL slug, unique across markets through the MarketSlug PDA
+    #[max_len(MAX_SLUG_LEN)]
+    pub slug: String,
+    // What happens when an incoming order would match a resting order of the same user
+    pub stp_policy: StpPolicy,
+    // Order quantities must be a multiple of this many outcome base units, 0 or 1 = any quantity
+    pub lot_size: u64,
+    // Outcome tokens in circulation: raised on every mint, lowered on every burn
+    pub yes_minted: u64,
+    pub no_minted: u64,
+    // Largest quantity a single order may have, 0 = unlimited
+    pub max_order_quantity: u64,
+    // Whether taker limit orders may rest or only maker quotes do
+    pub book_mode: BookMode,
+    // After settlement, claims are paid pro rata from the vault when it holds less than
+    // total_collateral_locked instead of first come first served
+    pub socialized_loss: bool,
+    // Halt new orders on one outcome while the other keeps trading
+    pub yes_trading_paused: bool,
+    pub no_trading_paused: bool,
+    // Fills matched on this market so far, drives the fee_schedule decay
+    pub total_trades: u64,
+    // Trading fee that decays as the market matures, None = flat TRADING_FEE_BPS
+    pub fee_schedule: Option<FeeSchedule>,
+    // Collateral whose balance changes without transfers (yield-bearing, rebasing): claims,
+    // merges and rewards pay a pro-rata share of the vault instead of the nominal amount
+    pub rebasing: bool,
+    // Trading fees and scalar claim rounding dust owed to the protocol, held in the vault
+    // outside total_collateral_locked until withdraw_treasury
+    pub treasury_balance: u64,
+    // Collateral the creator locked in the vault at creation, part of total_collateral_locked
+    // until withdraw_seed returns it after settlement
+    pub seed_collateral: u64,
+    // Execution (book) price of the latest fill per token type, 0 before the first trade
+    pub last_yes_price: u64,
+    pub last_yes_price_at: i64,
+    pub last_no_price: u64,
+    pub last_no_price_at: i64,
+}
+
+impl Market {
+    /// Orders are accepted until `trading_cutoff_buffer` seconds before the deadline
+    pub fn check_trading_open(&self, now: i64) -> Result<()> {
+        require!(
+            now < self.settlement_deadline,
+            PredictionMarketError::MarketExpired
+        );
+        require!(
+            now < self
+                .settlement_deadline
+                .saturating_sub(self.trading_cutoff_buffer),
+            PredictionMarketError::TradingWindowClosed
+        );
+        Ok(())
+    }
+
+    /// Rejects new orders on a `token_type` whose trading the authority paused
+    pub fn check_side_open(&self, token_type: TokenType) -> Result<()> {
+        let paused = match token_type {
+            TokenType::Yes => self.yes_trading_paused,
+            TokenType::No => self.no_trading_paused,
+        };
+        require!(!paused, PredictionMarketError::SideTradingPaused);
+        Ok(())
+    }
+
+    /// Rejects order prices outside `[min_price, max_price)`
+    pub fn check_price(&self, price: u64) -> Result<()> {
+        require!(
+            price >= self.min_price && price < self.max_price,
+            PredictionMarketError::PriceOutOfBounds
+        );
+        Ok(())
+    }
+
+    /// Rejects order quantities that aren't a multiple of `lot_size`
+    pub fn check_lot_size(&self, quantity: u64) -> Result<()> {
+        require!(
+            self.lot_size <= 1 || quantity % self.lot_size == 0,
+            PredictionMarketError::InvalidLotSize
+        );
+        Ok(())
+    }
+
+    /// Rejects orders larger than `max_order_quantity`
+    pub fn check_max_order_quantity(&self, quantity: u64) -> Result<()> {
+        require!(
+            self.max_order_quantity == 0 || quantity <= self.max_order_quantity,
+            PredictionMarketError::OrderTooLarge
+        );
+        Ok(())
+    }
+
+    /// `quantity` capped at `max_order_quantity`
+    pub fn cap_order_quantity(&self, quantity: u64) -> u64 {
+        if self.max_order_quantity == 0 {
+            return quantity;
+        }
+        quantity.min(self.max_order_quantity)
+    }
+
+    /// `quantity` rounded down to a multiple of `lot_size`
+    pub fn round_to_lot(&self, quantity: u64) -> u64 {
+        if self.lot_size <= 1 {
+            return quantity;
+        }
+        quantity - quantity % self.lot_size
+    }
+
+    /// Trading fee for the next fill: `max(min_fee_bps, base_fee_bps - total_trades / decay_step)`
+    /// under a `fee_schedule`, `TRADING_FEE_BPS` otherwise
+    pub fn effective_fee_bps(&self) -> u16 {
+        let Some(schedule) = self.fee_schedule else {
+            return TRADING_FEE_BPS;
+        };
+        let decay = self.total_trades / schedule.decay_step.max(1);
+        let fee = (schedule.base_fee_bps as u64).saturating_sub(decay);
+        fee.max(schedule.min_fee_bps as u64) as u16
+    }
+
+    /// Counts one matched fill and records `price`, the resting order's price it executed
+    /// at, as the last traded price of `token_type`
+    pub fn record_trade(&mut self, token_type: TokenType, price: u64, now: i64) -> Result<()> {
+        self.total_trades = self
+            .total_trades
+            .checked_add(1)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        match token_type {
+            TokenType::Yes => {
+                self.last_yes_price = price;
+                self.last_yes_price_at = now;
+            }
+            TokenType::No => {
+                self.last_no_price = price;
+                self.last_no_price_at = now;
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts `amount` freshly minted YES+NO pairs
+    pub fn record_mint(&mut self, amount: u64) -> Result<()> {
+        self.yes_minted = self
+            .yes_minted
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        self.no_minted = self
+            .no_minted
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Takes burned tokens off the issuance counters, errors if more is burned than was minted
+    pub fn record_burn(&mut self, yes_amount: u64, no_amount: u64) -> Result<()> {
+        self.yes_minted = self
+            .yes_minted
+            .checked_sub(yes_amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        self.no_minted = self
+            .no_minted
+            .checked_sub(no_amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Defense in depth against a corrupted `Market`: the vault and both escrows come from
+    /// distinct `init` accounts, so the stored keys must never coincide
+    pub fn check_distinct_holdings(&self) -> Result<()> {
+        require_keys_neq!(
+            self.yes_escrow,
+            self.no_escrow,
+            PredictionMarketError::HoldingAccountsCollide
+        );
+        require_keys_neq!(
+            self.yes_escrow,
+            self.collateral_vault,
+            PredictionMarketError::HoldingAccountsCollide
+        );
+        require_keys_neq!(
+            self.no_escrow,
+            self.collateral_vault,
+            PredictionMarketError::HoldingAccountsCollide
+        );
+        Ok(())
+    }
+
+    /// Rejects a split of `amount` that would push either outcome mint's supply past u64::MAX,
+    /// which the token program would only report as an opaque overflow
+    pub fn check_mint_supply(yes_supply: u64, no_supply: u64, amount: u64) -> Result<()> {
+        require!(
+            yes_supply.checked_add(amount).is_some() && no_supply.checked_add(amount).is_some(),
+            PredictionMarketError::SupplyOverflow
+        );
+        Ok(())
+    }
+
+    /// Rejects a split once `set_winner` has revoked the market PDA's mint authority over
+    /// either outcome mint, which the token program would only report as an owner mismatch
+    pub fn check_mint_authority(
+        market: Pubkey,
+        yes_authority: Option<Pubkey>,
+        no_authority: Option<Pubkey>,
+    ) -> Result<()> {
+        require!(
+            yes_authority == Some(market) && no_authority == Some(market),
+            PredictionMarketError::MintAuthorityRevoked
+        );
+        Ok(())
+    }
+
+    /// Collateral paid out for merging `amount` YES+NO pairs, `amount` less a `fee_bps` merge fee.
+    /// The fee rounds up under `FavorProtocol` and down otherwise, like trading fees.
+    pub fn merge_collateral_return(
+        amount: u64,
+        fee_bps: u16,
+        rounding: RoundingMode,
+    ) -> Result<u64> {
+        let fee_exact = (amount as u128)
+            .checked_mul(fee_bps as u128)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        let fee = if rounding == RoundingMode::FavorProtocol {
+            fee_exact.div_ceil(10_000)
+        } else {
+            fee_exact / 10_000
+        };
+        let fee = u64::try_from(fee).map_err(|_| PredictionMarketError::MathOverflow)?;
+        Ok(amount.saturating_sub(fee))
+    }
+
+    /// Outcome tokens are only minted and burned in YES+NO pairs until settlement, so both
+    /// supplies must match while the market is live
+    pub fn check_supply_balanced(&self, yes_supply: u64, no_supply: u64) -> Result<()> {
+        require!(
+            self.is_settled || yes_supply == no_supply,
+            PredictionMarketError::SupplyInvariantViolated
+        );
+        Ok(())
+    }
+
+    /// Part of a vault holding `vault_balance` that backs `total_collateral_locked`.
+    /// `treasury_balance` sits in the same vault but belongs to the protocol, not to users.
+    pub fn backing_balance(&self, vault_balance: u64) -> u64 {
+        vault_balance.saturating_sub(self.treasury_balance)
+    }
+
+    /// What a claim of `nominal` collateral actually pays out of a vault holding `vault_balance`
+    ///
+    /// Rebasing markets always pay their pro-rata share, see `rebased_payout`. Otherwise, with
+    /// `socialized_loss` on and a settled market whose `backing_balance` (the vault less the
+    /// treasury) is less than `total_collateral_locked` (the sum of every outstanding claim),
+    /// each claim is scaled by `backing / total_collateral_locked`, rounded down, so treasury
+    /// fees are never spent on claims. Callers still deduct the full `nominal` from
+    /// `total_collateral_locked`, which keeps the ratio the same for everyone claiming after them.
+    pub fn socialized_payout(&self, nominal: u64, vault_balance: u64) -> Result<u64> {
+        if self.rebasing {
+            return self.rebased_payout(nominal, vault_balance);
+        }
+        let backing = self.backing_balance(vault_balance);
+        if !self.socialized_loss || !self.is_settled || backing >= self.total_collateral_locked {
+            return Ok(nominal);
+        }
+        let paid = (nominal as u128 * backing as u128) / self.total_collateral_locked as u128;
+        u64::try_from(paid).map_err(|_| PredictionMarketError::MathOverflow.into())
+    }
+
+    /// `nominal` collateral as a share of a rebasing vault holding `vault_balance`
+    ///
+    /// The vault's balance drifts from `total_collateral_locked` as the collateral accrues yield
+    /// (or rebases down), so `nominal` is scaled by `backing_balance / total_collateral_locked`,
+    /// rounded down, in either direction and before or after settlement. The treasury is left
+    /// out of the backing, so claims never pay out what `withdraw_treasury` owes. Like
+    /// `socialized_payout`, callers deduct the full `nominal`, so every later claim gets the
+    /// same ratio. Non-rebasing markets pay `nominal`.
+    pub fn rebased_payout(&self, nominal: u64, vault_balance: u64) -> Result<u64> {
+        if !self.rebasing || self.total_collateral_locked == 0 {
+            return Ok(nominal);
+        }
+        let backing = self.backing_balance(vault_balance);
+        let paid = (nominal as u128 * backing as u128) / self.total_collateral_locked as u128;
+        u64::try_from(paid).map_err(|_| PredictionMarketError::MathOverflow.into())
+    }
+
+    /// Rejects `incoming` collateral that would push the vault past `max_collateral`
+    pub fn check_collateral_cap(&self, incoming: u64) -> Result<()> {
+        if self.max_collateral == 0 {
+            return Ok(());
+        }
+        let new_total = self
+            .total_collateral_locked
+            .checked_add(incoming)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        require!(
+            new_total <= self.max_collateral,
+            PredictionMarketError::CollateralCapExceeded
+        );
+        Ok(())
+    }
+
+    /// Adds `mid_price × seconds since the last update` to `price_cumulative`
+    ///
+    /// Called on every fill with the mid from before the fill. Fills later in the same
+    /// transaction add nothing since no time has passed. Without a mid (empty YES book)
+    /// the interval is carried over to the next update.
+    pub fn update_twap(&mut self, mid_price: Option<u64>, now: i64) {
+        let Some(mid_price) = mid_price else {
+            return;
+        };
+        if self.last_price_update != 0 {
+            let elapsed = now.saturating_sub(self.last_price_update).max(0) as u128;
+            self.price_cumulative = self
+                .price_cumulative
+                .wrapping_add((mid_price as u128).wrapping_mul(elapsed));
+        }
+        self.last_price_update = now;
+    }
+
+    /// Collateral for redeeming `yes_amount` YES and `no_amount` NO of a settled scalar market
+    ///
+    /// A YES token is worth (value - low) / (high - low) collateral and a NO token the complement.
+    /// `retired` is computed on the running totals in `yes_redeemed`/`no_redeemed`, rounded down,
+    /// so once every token is redeemed it adds up to exactly the collateral backing them,
+    /// whatever order users claim in. `payout` depends on `rounding_mode`:
+    /// - `FavorProtocol`: this claim's value rounded down. The sub-unit left over is the
+    ///   difference to `retired`, which `retire_payout` moves to `treasury_balance`
+    /// - `FavorMaker` / `FavorTaker`: the YES running total rounds down and the NO total up,
+    ///   so `payout == retired` and no dust is collected
+    pub fn scalar_payout(&mut self, yes_amount: u64, no_amount: u64) -> Result<ScalarPayout> {
+        let MarketKind::Scalar { low, high } = self.market_kind else {
+            return Ok(ScalarPayout {
+                payout: 0,
+                retired: 0,
+            });
+        };
+        // set_winner already clamps, clamped again so an out-of-range value can never pay one
+        // side more than the pair's collateral or underflow the other
+        let value = self.settlement_value.clamp(low, high);
+        // Bounds may be negative (spreads), widen before subtracting so the span can't overflow
+        let range = (high as i128 - low as i128) as u128;
+        let yes_weight = (value as i128 - low as i128) as u128;
+        let no_weight = range - yes_weight;
+
+        let yes_after = self
+            .yes_redeemed
+            .checked_add(yes_amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        let no_after = self
+            .no_redeemed
+            .checked_add(no_amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        let (payout, retired) = if self.rounding_mode == RoundingMode::FavorProtocol {
+            // floor(a + b) - floor(a) >= floor(b), so the dust is never negative
+            let total =
+                |yes: u64, no: u64| (yes as u128 * yes_weight + no as u128 * no_weight) / range;
+            (
+                total(yes_amount, no_amount),
+                total(yes_after, no_after) - total(self.yes_redeemed, self.no_redeemed),
+            )
+        } else {
+            let yes_total = |redeemed: u64| (redeemed as u128 * yes_weight) / range;
+            let no_total = |redeemed: u64| (redeemed as u128 * no_weight).div_ceil(range);
+            let payout = (yes_total(yes_after) - yes_total(self.yes_redeemed))
+                + (no_total(no_after) - no_total(self.no_redeemed));
+            (payout, payout)
+        };
+
+        self.yes_redeemed = yes_after;
+        self.no_redeemed = no_after;
+        Ok(ScalarPayout {
+            payout: u64::try_from(payout).map_err(|_| PredictionMarketError::MathOverflow)?,
+            retired: u64::try_from(retired).map_err(|_| PredictionMarketError::MathOverflow)?,
+        })
+    }
+
+    /// Takes a redemption's `retired` collateral off `total_collateral_locked` and adds
+    /// the rounding dust it didn't pay out to `treasury_balance`. Returns the dust.
+    pub fn retire_payout(&mut self, redemption: ScalarPayout) -> Result<u64> {
+        self.total_collateral_locked = self
+            .total_collateral_locked
+            .checked_sub(redemption.retired)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        let dust = redemption
+            .retired
+            .checked_sub(redemption.payout)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        self.treasury_balance = self
+            .treasury_balance
+            .checked_add(dust)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        Ok(dust)
+    }
+
+    /// Moves a fill's trading `fee` out of `total_collateral_locked` into `treasury_balance`.
+    /// It stays in the vault, but no user can claim it, so it mustn't block `close_market`.
+    pub fn collect_fee(&mut self, fee: u64) -> Result<()> {
+        self.total_collateral_locked = self
+            .total_collateral_locked
+            .checked_sub(fee)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        self.treasury_balance = self
+            .treasury_balance
+            .checked_add(fee)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Collateral owed if every outstanding outcome token is redeemed after settling on
+    /// `outcome` (scalar markets use `settlement_value`, which must already be set)
+    ///
+    /// Binary winners pay one collateral base unit per token, a `Neither` result only pays out
+    /// YES+NO pairs. Scalar payouts are rounded up so the bound is never short.
+    pub fn max_settlement_payout(
+        &self,
+        outcome: WinningOutcome,
+        yes_supply: u64,
+        no_supply: u64,
+    ) -> Result<u64> {
+        if let MarketKind::Scalar { low, high } = self.market_kind {
+            let value = self.settlement_value.clamp(low, high);
+            let range = (high as i128 - low as i128) as u128;
+            let yes_weight = (value as i128 - low as i128) as u128;
+            let total = (yes_supply as u128 * yes_weight
+                + no_supply as u128 * (range - yes_weight))
+                .div_ceil(range);
+            return u64::try_from(total).map_err(|_| PredictionMarketError::MathOverflow.into());
+        }
+        Ok(match outcome {
+            WinningOutcome::OutcomeA => yes_supply,
+            WinningOutcome::OutcomeB => no_supply,
+            WinningOutcome::Neither => yes_supply.min(no_supply),
+        })
+    }
+
+    /// Maker reward for `quantity` base units that rested on the book from `rested_since` until `now`,
+    /// drawn from `incentive_pool` (capped at what's left).
+    ///
+    /// Accrued once per unit when it leaves the book (filled or cancelled), measured from the
+    /// order's `timestamp`, so partial fills never double count and `Order` needs no extra state.
+    pub fn accrue_maker_reward(
+        &mut self,
+        quantity: u64,
+        rested_since: i64,
+        now: i64,
+    ) -> Result<u64> {
+        if self.reward_rate == 0 || self.incentive_pool == 0 {
+            return Ok(0);
+        }
+        // Orders stop earning once trading closes, even if cancelled after settlement
+        let now = now.min(self.settlement_deadline);
+        let seconds_rested = now.saturating_sub(rested_since).max(0) as u128;
+        let reward = (quantity as u128)
+            .checked_mul(seconds_rested)
+            .and_then(|v| v.checked_mul(self.reward_rate as u128))
+            .ok_or(PredictionMarketError::MathOverflow)?
+            / TOKEN_DECIMALS_SCALE as u128;
+        let reward = reward.min(self.incentive_pool as u128) as u64;
+        self.incentive_pool -= reward;
+        Ok(reward)
+    }
+
+    /// Current layout of a market created before `category` was added, which shifted every
+    /// later field. Everything added since starts at the value `initialize_market` gives a
+    /// market created without the newer options.
+    pub fn from_legacy(
+        legacy: LegacyMarket,
+        vault_bump: u8,
+        yes_escrow_bump: u8,
+        no_escrow_bump: u8,
+        max_price: u64,
+        now: i64,
+    ) -> Self {
+        Market {
+            authority: legacy.authority,
+            market_id: legacy.market_id,
+            category: 0,
+            in_progress: false,
+            settlement_deadline: legacy.settlement_deadline,
+            collateral_mint: legacy.collateral_mint,
+            collateral_vault: legacy.collateral_vault,
+            is_settled: legacy.is_settled,
+            winning_outcome: legacy.winning_outcome,
+            total_collateral_locked: legacy.total_collateral_locked,
+            bump: legacy.bump,
+            meta_data_url: legacy.meta_data_url,
+            outcome_yes_mint: legacy.outcome_yes_mint,
+            outcome_no_mint: legacy.outcome_no_mint,
+            yes_escrow: legacy.yes_escrow,
+            no_escrow: legacy.no_escrow,
+            max_collateral: 0,
+            wash_trade_guard: false,
+            reward_rate: 0,
+            incentive_vault: Pubkey::default(),
+            incentive_pool: 0,
+            restricted_transfer: false,
+            rounding_mode: RoundingMode::FavorProtocol,
+            outcome_a_label: String::new(),
+            outcome_b_label: String::new(),
+            vault_bump,
+            yes_escrow_bump,
+            no_escrow_bump,
+            price_cumulative: 0,
+            last_price_update: 0,
+            market_kind: MarketKind::Binary,
+            settlement_value: 0,
+            yes_redeemed: 0,
+            no_redeemed: 0,
+            min_price: 1,
+            max_price,
+            last_metadata_update: now,
+            total_staked: 0,
+            trading_cutoff_buffer: 0,
+            slug: String::new(),
+            stp_policy: StpPolicy::Skip,
+            lot_size: 0,
+            yes_minted: 0,
+            no_minted: 0,
+            max_order_quantity: 0,
+            book_mode: BookMode::Clob,
+            socialized_loss: false,
+            yes_trading_paused: false,
+            no_trading_paused: false,
+            total_trades: 0,
+            fee_schedule: None,
+            rebasing: false,
+            treasury_balance: 0,
+            seed_collateral: 0,
+            last_yes_price: 0,
+            last_yes_price_at: 0,
+            last_no_price: 0,
+            last_no_price_at: 0,
+        }
+    }
+}
+
+/// `Market` as it was laid out before `category`, read by `migrate_market`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, InitSpace)]
+pub struct LegacyMarket {
+    pub authority: Pubkey,
+    pub market_id: u32,
+    pub settlement_deadline: i64,
+    pub collateral_mint: Pubkey,
+    pub collateral_vault: Pubkey,
+    pub is_settled: bool,
+    pub winning_outcome: Option<WinningOutcome>,
+    pub total_collateral_locked: u64,
+    pub bump: u8,
+    #[max_len(200)]
+    pub meta_data_url: String,
+    pub outcome_yes_mint: Pubkey,
+    pub outcome_no_mint: Pubkey,
+    pub yes_escrow: Pubkey,
+    pub no_escrow: Pubkey,
+}
+
+/// One page of the market index, listing every market whose id falls in
+/// `[page * MARKETS_PER_REGISTRY_PAGE, (page + 1) * MARKETS_PER_REGISTRY_PAGE)`
+///
+/// Front-ends can list markets with a single `getMultipleAccounts` over the pages instead
+/// of a `getProgramAccounts` scan. Pages are sized for the whole id range up front, so
+/// they never need to grow.
+#[account]
+#[derive(InitSpace)]
+pub struct MarketRegistry {
+    pub page: u32,
+    pub bump: u8,
+    #[max_len(MARKETS_PER_REGISTRY_PAGE)]
+    pub entries: Vec<MarketRegistryEntry>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct MarketRegistryEntry {
+    pub market_id: u32,
+    // Set by close_market, cleared again if the id is reused for a new market
+    pub closed: bool,
+}
+
+impl MarketRegistry {
+    pub fn page_for(market_id: u32) -> u32 {
+        market_id / MARKETS_PER_REGISTRY_PAGE
+    }
+
+    pub fn list(&mut self, market_id: u32) {
+        match self.entries.iter_mut().find(|e| e.market_id == market_id) {
+            Some(entry) => entry.closed = false,
+            None => self.entries.push(MarketRegistryEntry {
+                market_id,
+                closed: false,
+            }),
+        }
+    }
+
+    pub fn mark_closed(&mut self, market_id: u32) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.market_id == market_id) {
+            entry.closed = true;
+        }
+    }
+}
+
+/// Slug index at `[MARKET_SLUG_SEED, slug]`. Created together with the market, so a taken
+/// slug makes `initialize_market` fail, and closed with it so the slug can be reused.
+#[account]
+#[derive(InitSpace)]
+pub struct MarketSlug {
+    pub market_id: u32,
+    pub bump: u8,
+}
+
+impl MarketSlug {
+    /// 1..=MAX_SLUG_LEN bytes of lowercase ASCII letters, digits and '-'
+    pub fn is_valid(slug: &str) -> bool {
+        !slug.is_empty()
+            && slug.len() <= MAX_SLUG_LEN
+            && slug
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+    }
+}
+
+/// Program-wide settings, a single PDA at `[CONFIG_SEED]`
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub admin: Pubkey,
+    pub bump: u8,
+    // Signers allowed to create markets, anyone may while it's empty
+    #[max_len(MAX_MARKET_CREATORS)]
+    pub creators: Vec<Pubkey>,
+    // Collateral every new market must be seeded with, 0 = seeding is optional
+    pub min_seed_collateral: u64,
+}
+
+impl Config {
+    pub fn allows_creator(&self, creator: &Pubkey) -> bool {
+        self.creators.is_empty() || self.creators.contains(creator)
+    }
+}
+
+/// Collateral a user has staked on one market, at `[STAKE_SEED, market_id, user]`.
+/// The tokens sit in the market's stake vault until `unstake`.
+#[account]
+#[derive(InitSpace)]
+pub struct StakeAccount {
+    pub market_id: u32,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl StakeAccount {
+    /// Priority tier for `amount`: how many of `STAKE_TIER_THRESHOLDS` it reaches (0..=3)
+    pub fn tier(&self) -> u8 {
+        STAKE_TIER_THRESHOLDS
+            .iter()
+            .filter(|threshold| self.amount >= **threshold)
+            .count() as u8
+    }
+}
+
+/// Write-once audit record of how a market was resolved, kept on-chain so the
+/// resolution provenance survives event pruning
+#[account]
+#[derive(InitSpace)]
+pub struct SettlementRecord {
+    pub market_id: u32,
+    pub resolver: Pubkey,
+    pub outcome: WinningOutcome,
+    pub settled_at: i64,
+    // SETTLEMENT_SOURCE_* constant identifying who resolved the market
+    pub source: u8,
+    pub settlement_value: i64,
+    pub bump: u8,
 }
 
 #[account]
@@ -33,6 +758,399 @@ pub struct UserStats {
     pub locked_collateral: u64,
     pub reward_claimed: bool,
     pub bump: u8,
+    // Last resting limit order, used by the wash-trade cooldown (slot 0 = none yet)
+    pub last_order_slot: u64,
+    pub last_order_side: OrderSide,
+    pub last_order_token_type: TokenType,
+    // Lifetime maker rewards credited to claimable_collateral
+    pub rewards_earned: u64,
+    // Net trading fees for this user: rebates add, fees paid as the seller of a fill subtract.
+    // Liquidity rewards are tracked in rewards_earned, not here
+    pub fees_earned: i64,
+    // Optional third party allowed to sign claim_funds/claim_rewards for this user,
+    // funds still only go to the user's own token accounts
+    pub claim_delegate: Option<Pubkey>,
+    // Collateral deposited with deposit_collateral and not yet locked by an order,
+    // held in the vault and counted in Market::total_collateral_locked
+    pub available_collateral: u64,
+    // Outcome tokens deposited with deposit_tokens and not yet locked by an order, held in escrow
+    pub available_yes: u64,
+    pub available_no: u64,
+    // Set by the market authority for designated market makers, pays no trading fee
+    pub fee_exempt: bool,
+}
+
+impl UserStats {
+    pub fn credit_maker_reward(&mut self, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        self.claimable_collateral = self
+            .claimable_collateral
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        self.rewards_earned = self
+            .rewards_earned
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Moves `amount` locked by an order on `side` to claimable: collateral for a buy,
+    /// `token_type` tokens for a sell
+    pub fn unlock_to_claimable(
+        &mut self,
+        side: OrderSide,
+        token_type: TokenType,
+        amount: u64,
+    ) -> Result<()> {
+        let (locked, claimable) = match (side, token_type) {
+            (OrderSide::Buy, _) => (&mut self.locked_collateral, &mut self.claimable_collateral),
+            (OrderSide::Sell, TokenType::Yes) => (&mut self.locked_yes, &mut self.claimable_yes),
+            (OrderSide::Sell, TokenType::No) => (&mut self.locked_no, &mut self.claimable_no),
+        };
+        *locked = locked
+            .checked_sub(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        *claimable = claimable
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Positive `delta` is a rebate earned, negative is a fee paid
+    pub fn record_fee(&mut self, delta: i64) -> Result<()> {
+        self.fees_earned = self
+            .fees_earned
+            .checked_add(delta)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Records a trading `fee` this user paid as the seller of a fill
+    pub fn pay_fee(&mut self, fee: u64) -> Result<()> {
+        if fee == 0 {
+            return Ok(());
+        }
+        let fee = i64::try_from(fee).map_err(|_| PredictionMarketError::MathOverflow)?;
+        self.record_fee(-fee)
+    }
+
+    pub fn position(&self) -> UserPosition {
+        UserPosition {
+            user: self.user,
+            market_id: self.market_id,
+            claimable_yes: self.claimable_yes,
+            locked_yes: self.locked_yes,
+            claimable_no: self.claimable_no,
+            locked_no: self.locked_no,
+            claimable_collateral: self.claimable_collateral,
+            locked_collateral: self.locked_collateral,
+            rewards_earned: self.rewards_earned,
+            fees_earned: self.fees_earned,
+            reward_claimed: self.reward_claimed,
+        }
+    }
+}
+
+// Returned by the `user_position` view
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserPosition {
+    pub user: Pubkey,
+    pub market_id: u32,
+    pub claimable_yes: u64,
+    pub locked_yes: u64,
+    pub claimable_no: u64,
+    pub locked_no: u64,
+    pub claimable_collateral: u64,
+    pub locked_collateral: u64,
+    pub rewards_earned: u64,
+    pub fees_earned: i64,
+    pub reward_claimed: bool,
+}
+
+// Returned by the `market_status` view
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarketStatus {
+    pub is_settled: bool,
+    pub total_collateral_locked: u64,
+    pub open_orders_total: u32,
+    pub yes_minted: u64,
+    pub no_minted: u64,
+    // Same preconditions `close_market` enforces: settled, no collateral left, empty book
+    pub closeable: bool,
+    // Last execution price per token type and when it traded, 0 before the first fill
+    pub last_yes_price: u64,
+    pub last_yes_price_at: i64,
+    pub last_no_price: u64,
+    pub last_no_price_at: i64,
+}
+
+impl MarketStatus {
+    pub fn new(market: &Market, orderbook: &OrderBook) -> Self {
+        let open_orders_total = orderbook.total_orders() as u32;
+        MarketStatus {
+            is_settled: market.is_settled,
+            total_collateral_locked: market.total_collateral_locked,
+            open_orders_total,
+            yes_minted: market.yes_minted,
+            no_minted: market.no_minted,
+            closeable: market.is_settled
+                && market.total_collateral_locked == 0
+                && open_orders_total == 0,
+            last_yes_price: market.last_yes_price,
+            last_yes_price_at: market.last_yes_price_at,
+            last_no_price: market.last_no_price,
+            last_no_price_at: market.last_no_price_at,
+        }
+    }
+}
+
+// Returned by the `get_market_config` view: the market's trading parameters, so clients don't
+// depend on the `Market` account layout. Fields are only ever appended.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MarketConfig {
+    pub market_id: u32,
+    pub authority: Pubkey,
+    pub category: u16,
+    pub collateral_mint: Pubkey,
+    pub settlement_deadline: i64,
+    pub trading_cutoff_buffer: i64,
+    pub market_kind: MarketKind,
+    pub min_price: u64,
+    pub max_price: u64,
+    pub lot_size: u64,
+    pub max_order_quantity: u64,
+    pub max_collateral: u64,
+    pub book_mode: BookMode,
+    pub stp_policy: StpPolicy,
+    pub rounding_mode: RoundingMode,
+    pub wash_trade_guard: bool,
+    pub restricted_transfer: bool,
+    pub socialized_loss: bool,
+    pub yes_trading_paused: bool,
+    pub no_trading_paused: bool,
+    pub reward_rate: u64,
+    pub fee_schedule: Option<FeeSchedule>,
+    pub effective_fee_bps: u16,
+    pub outcome_a_label: String,
+    pub outcome_b_label: String,
+    pub rebasing: bool,
+}
+
+impl MarketConfig {
+    pub fn new(market: &Market) -> Self {
+        MarketConfig {
+            market_id: market.market_id,
+            authority: market.authority,
+            category: market.category,
+            collateral_mint: market.collateral_mint,
+            settlement_deadline: market.settlement_deadline,
+            trading_cutoff_buffer: market.trading_cutoff_buffer,
+            market_kind: market.market_kind,
+            min_price: market.min_price,
+            max_price: market.max_price,
+            lot_size: market.lot_size,
+            max_order_quantity: market.max_order_quantity,
+            max_collateral: market.max_collateral,
+            book_mode: market.book_mode,
+            stp_policy: market.stp_policy,
+            rounding_mode: market.rounding_mode,
+            wash_trade_guard: market.wash_trade_guard,
+            restricted_transfer: market.restricted_transfer,
+            socialized_loss: market.socialized_loss,
+            yes_trading_paused: market.yes_trading_paused,
+            no_trading_paused: market.no_trading_paused,
+            reward_rate: market.reward_rate,
+            fee_schedule: market.fee_schedule,
+            effective_fee_bps: market.effective_fee_bps(),
+            outcome_a_label: market.outcome_a_label.clone(),
+            outcome_b_label: market.outcome_b_label.clone(),
+            rebasing: market.rebasing,
+        }
+    }
+}
+
+// Returned by the `implied_probability` view. A price of 1.0 collateral per token is 10_000 bps.
+// An outcome with an empty book reports 0 with its `_quoted` flag false.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImpliedProbability {
+    pub yes_prob_bps: u16,
+    pub no_prob_bps: u16,
+    pub yes_quoted: bool,
+    pub no_quoted: bool,
+}
+
+impl ImpliedProbability {
+    /// Each outcome's mid price (see `OrderBook::mid_price`) in bps of one whole collateral
+    /// token, `collateral_decimals` being the collateral mint's decimals. Capped at 10_000.
+    pub fn new(orderbook: &OrderBook, collateral_decimals: u8) -> Result<Self> {
+        let one = 10u128
+            .checked_pow(collateral_decimals as u32)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        let to_bps = |mid: Option<u64>| {
+            mid.map_or(0, |price| {
+                ((price as u128 * 10_000) / one).min(10_000) as u16
+            })
+        };
+        let yes_mid = orderbook.mid_price(TokenType::Yes);
+        let no_mid = orderbook.mid_price(TokenType::No);
+        Ok(ImpliedProbability {
+            yes_prob_bps: to_bps(yes_mid),
+            no_prob_bps: to_bps(no_mid),
+            yes_quoted: yes_mid.is_some(),
+            no_quoted: no_mid.is_some(),
+        })
+    }
+}
+
+// Returned by the `open_interest` view: what the resting orders have locked, nothing else.
+// Unlike `total_collateral_locked` it leaves out split collateral, deposits and claimables.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpenInterest {
+    // Sum of `quantity - filledquantity` over resting YES sells, held in the YES escrow
+    pub yes_locked_in_book: u64,
+    // Same over resting NO sells, held in the NO escrow
+    pub no_locked_in_book: u64,
+    // Sum of `Order::collateral_for(quantity - filledquantity, price)` over resting YES and NO
+    // buys, the exact lock a cancel would refund
+    pub collateral_locked_in_book: u64,
+}
+
+impl OpenInterest {
+    pub fn new(orderbook: &OrderBook) -> Result<Self> {
+        let unfilled = |order: &Order| order.quantity.saturating_sub(order.filledquantity);
+        let tokens = |orders: &[Order]| {
+            orders.iter().try_fold(0u64, |total, order| {
+                total
+                    .checked_add(unfilled(order))
+                    .ok_or(PredictionMarketError::MathOverflow)
+            })
+        };
+
+        let mut collateral_locked_in_book: u64 = 0;
+        for order in orderbook
+            .yes_buy_orders
+            .iter()
+            .chain(orderbook.no_buy_orders.iter())
+        {
+            collateral_locked_in_book = collateral_locked_in_book
+                .checked_add(Order::collateral_for(unfilled(order), order.price)?)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+        }
+
+        Ok(OpenInterest {
+            yes_locked_in_book: tokens(&orderbook.yes_sell_orders)?,
+            no_locked_in_book: tokens(&orderbook.no_sell_orders)?,
+            collateral_locked_in_book,
+        })
+    }
+}
+
+// Returned by the `verify_supply_invariant` view
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SupplyReport {
+    pub yes_supply: u64,
+    pub no_supply: u64,
+    pub is_settled: bool,
+}
+
+// Returned by the `reconcile_user` view
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReconcileReport {
+    pub user: Pubkey,
+    // Locked balances recomputed from the user's resting orders
+    pub expected_locked_collateral: u64,
+    pub expected_locked_yes: u64,
+    pub expected_locked_no: u64,
+    pub locked_matches_orders: bool,
+    // locked + claimable + available of each asset is covered by the vault/escrow holding it
+    pub collateral_covered: bool,
+    pub yes_covered: bool,
+    pub no_covered: bool,
+    pub consistent: bool,
+}
+
+impl ReconcileReport {
+    /// Cross-checks one user's UserStats against the orderbook and the market's token accounts
+    ///
+    /// A user's share can only be checked against the whole vault/escrow balance, so the
+    /// coverage flags catch impossible states rather than proving global solvency
+    pub fn new(
+        stats: &UserStats,
+        orderbook: &OrderBook,
+        vault_amount: u64,
+        yes_escrow_amount: u64,
+        no_escrow_amount: u64,
+    ) -> Result<Self> {
+        let mut expected_locked_collateral: u64 = 0;
+        let mut expected_locked_yes: u64 = 0;
+        let mut expected_locked_no: u64 = 0;
+
+        for order in orderbook
+            .yes_buy_orders
+            .iter()
+            .chain(orderbook.yes_sell_orders.iter())
+            .chain(orderbook.no_buy_orders.iter())
+            .chain(orderbook.no_sell_orders.iter())
+            .filter(|o| o.user_key == stats.user)
+        {
+            let remaining = order
+                .quantity
+                .checked_sub(order.filledquantity)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+            let (total, amount) = match (order.side, order.token_type) {
+                (OrderSide::Buy, _) => (
+                    &mut expected_locked_collateral,
+                    Order::collateral_for(remaining, order.price)?,
+                ),
+                (OrderSide::Sell, TokenType::Yes) => (&mut expected_locked_yes, remaining),
+                (OrderSide::Sell, TokenType::No) => (&mut expected_locked_no, remaining),
+            };
+            *total = total
+                .checked_add(amount)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+        }
+
+        let covered = |locked: u64, claimable: u64, held: u64| {
+            locked
+                .checked_add(claimable)
+                .is_some_and(|owed| owed <= held)
+        };
+
+        let locked_matches_orders = stats.locked_collateral == expected_locked_collateral
+            && stats.locked_yes == expected_locked_yes
+            && stats.locked_no == expected_locked_no;
+        let collateral_covered = covered(
+            stats.locked_collateral,
+            stats
+                .claimable_collateral
+                .saturating_add(stats.available_collateral),
+            vault_amount,
+        );
+        let yes_covered = covered(
+            stats.locked_yes,
+            stats.claimable_yes.saturating_add(stats.available_yes),
+            yes_escrow_amount,
+        );
+        let no_covered = covered(
+            stats.locked_no,
+            stats.claimable_no.saturating_add(stats.available_no),
+            no_escrow_amount,
+        );
+
+        Ok(ReconcileReport {
+            user: stats.user,
+            expected_locked_collateral,
+            expected_locked_yes,
+            expected_locked_no,
+            locked_matches_orders,
+            collateral_covered,
+            yes_covered,
+            no_covered,
+            consistent: locked_matches_orders && collateral_covered && yes_covered && no_covered,
+        })
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
@@ -46,6 +1164,139 @@ pub struct Order {
     pub quantity: u64,
     pub filledquantity: u64,
     pub timestamp: i64,
+    // Owner's stake tier when the order was placed, breaks ties at equal price
+    pub priority: u8,
+}
+
+impl Order {
+    /// Collateral (base units) for `quantity` outcome base units at `price` (collateral base units
+    /// per whole outcome token): floor(quantity × price / TOKEN_DECIMALS_SCALE), computed in u128.
+    ///
+    /// Rounding is always down, against whoever receives collateral:
+    /// - a buy locks `collateral_for(quantity, price)` up front
+    /// - a resting buy's lock is always `collateral_for(remaining, price)`, so each fill releases
+    ///   `lock_release` (the before/after difference) and a cancel refunds `collateral_for(remaining)`.
+    ///   These telescope to exactly the original lock, no sub-unit is created or left stranded
+    /// - the seller of a fill receives `collateral_for(fill_qty, fill_price)`, never more than the
+    ///   buyer released; the buyer gets the difference back as claimable collateral
+    pub fn collateral_for(quantity: u64, price: u64) -> Result<u64> {
+        let value = (quantity as u128)
+            .checked_mul(price as u128)
+            .ok_or(PredictionMarketError::MathOverflow)?
+            / TOKEN_DECIMALS_SCALE as u128;
+        u64::try_from(value).map_err(|_| PredictionMarketError::MathOverflow.into())
+    }
+
+    /// Average price (collateral base units per whole token) of `collateral` exchanged for
+    /// `quantity` outcome base units, rounded down. 0 when nothing was filled.
+    pub fn average_price(collateral: u64, quantity: u64) -> Result<u64> {
+        if quantity == 0 {
+            return Ok(0);
+        }
+        let value = (collateral as u128)
+            .checked_mul(TOKEN_DECIMALS_SCALE as u128)
+            .ok_or(PredictionMarketError::MathOverflow)?
+            / quantity as u128;
+        u64::try_from(value).map_err(|_| PredictionMarketError::MathOverflow.into())
+    }
+
+    /// Collateral value of a fill, paid by the buyer and received by the seller (minus `fee`)
+    ///
+    /// `quantity × price / 10^decimals` is rounded per `rounding`:
+    /// - `FavorProtocol`: value rounded down, fee rounded up
+    /// - `FavorMaker` / `FavorTaker`: value rounded up when the favored side is the seller,
+    ///   down otherwise, fee rounded down
+    ///
+    /// Callers must still cap what the seller receives at what the buyer's lock released,
+    /// so rounding up can never pay out collateral that was not deposited
+    pub fn compute_fill_value(
+        quantity: u64,
+        price: u64,
+        decimals: u8,
+        fee_bps: u16,
+        rounding: RoundingMode,
+        seller_is_maker: bool,
+    ) -> Result<FillValue> {
+        let scale = 10u128
+            .checked_pow(decimals as u32)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        let exact = (quantity as u128)
+            .checked_mul(price as u128)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+
+        let favor_seller = match rounding {
+            RoundingMode::FavorProtocol => false,
+            RoundingMode::FavorMaker => seller_is_maker,
+            RoundingMode::FavorTaker => !seller_is_maker,
+        };
+        let gross = if favor_seller {
+            exact.div_ceil(scale)
+        } else {
+            exact / scale
+        };
+
+        let fee_exact = gross
+            .checked_mul(fee_bps as u128)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        let fee = if rounding == RoundingMode::FavorProtocol {
+            fee_exact.div_ceil(10_000)
+        } else {
+            fee_exact / 10_000
+        };
+
+        Ok(FillValue {
+            gross: u64::try_from(gross).map_err(|_| PredictionMarketError::MathOverflow)?,
+            fee: u64::try_from(fee).map_err(|_| PredictionMarketError::MathOverflow)?,
+        })
+    }
+
+    /// Collateral released from a buy order's lock when `fill_qty` of its `remaining` quantity fills
+    pub fn lock_release(remaining: u64, fill_qty: u64, price: u64) -> Result<u64> {
+        let remaining_after = remaining
+            .checked_sub(fill_qty)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        Self::collateral_for(remaining, price)?
+            .checked_sub(Self::collateral_for(remaining_after, price)?)
+            .ok_or(PredictionMarketError::MathOverflow.into())
+    }
+
+    /// Splits the collateral one fill releases from the buyer's lock, whichever side rests
+    ///
+    /// The buyer's lock gives up `lock_release(buyer_remaining, fill_qty, buyer_price)`. Of
+    /// that the buyer pays the fill value at `book_price` (capped at the release), the seller
+    /// gets it minus the fee, and the rest (price improvement, rounding dust) goes back to the
+    /// buyer. All of it stays in the vault, so `total_collateral_locked` doesn't change:
+    /// `released == seller_proceeds + fee + buyer_refund`.
+    pub fn settle_buy_fill(
+        buyer_remaining: u64,
+        fill_qty: u64,
+        buyer_price: u64,
+        book_price: u64,
+        fee_bps: u16,
+        rounding: RoundingMode,
+        taker_is_buyer: bool,
+    ) -> Result<BuyFill> {
+        let released = Self::lock_release(buyer_remaining, fill_qty, buyer_price)?;
+        let value = Self::compute_fill_value(
+            fill_qty,
+            book_price,
+            OUTCOME_TOKEN_DECIMALS,
+            fee_bps,
+            rounding,
+            taker_is_buyer,
+        )?;
+
+        Ok(BuyFill::split(released, value))
+    }
+
+    /// Deterministic id for clients that supply their own nonce:
+    /// first 8 bytes (little-endian) of sha256(user_key || client_nonce.to_le_bytes())
+    pub fn client_order_id(user_key: &Pubkey, client_nonce: u64) -> u64 {
+        let hash = hashv(&[user_key.as_ref(), &client_nonce.to_le_bytes()]);
+        let mut id_bytes = [0u8; 8];
+        id_bytes.copy_from_slice(&hash.to_bytes()[..8]);
+        u64::from_le_bytes(id_bytes)
+    }
 }
 
 #[account]
@@ -57,17 +1308,42 @@ pub struct OrderBook {
     pub no_buy_orders: Vec<Order>,
     pub no_sell_orders: Vec<Order>,
     pub bump: u8,
+    pub book_hash: [u8; 32],
+    // Ring buffer of the last CLOSED_ORDERS_CAPACITY orders that left the book,
+    // closed_orders_head is the slot the next one is written to
+    pub closed_orders: Vec<ClosedOrder>,
+    pub closed_orders_head: u8,
 }
 
 impl OrderBook {
-    pub const BASE_SIZE: usize = 8 + 4 + 8 + 1 + 16;
+    pub const BASE_SIZE: usize = 8 + 4 + 8 + 1 + 16 + 32 + Self::CLOSED_ORDERS_SIZE;
 
-    pub const ORDER_SIZE: usize = 78;
+    // Reserved up front so recording a closed order never needs a realloc
+    pub const CLOSED_ORDERS_SIZE: usize = 4 + CLOSED_ORDERS_CAPACITY * ClosedOrder::INIT_SPACE + 1;
+
+    pub const ORDER_SIZE: usize = 79;
 
     pub fn space(orders_per_side: usize) -> usize {
         Self::BASE_SIZE + (orders_per_side * Self::ORDER_SIZE * 4) // 4 vectors
     }
 
+    /// Queue order for one side of the book: best price first (highest buy, lowest sell),
+    /// then higher `priority`, then earlier placement.
+    ///
+    /// Priority only reorders orders at the same price, a staker never jumps a better-priced
+    /// order. It does mean an earlier order can lose its place in the queue to a later one
+    /// from a bigger staker, so time priority only holds within a tier.
+    pub fn sort_side(orders: &mut [Order], side: OrderSide) {
+        // sort_by is stable, orders that compare equal keep their (time) order
+        orders.sort_by(|a, b| {
+            let by_price = match side {
+                OrderSide::Buy => b.price.cmp(&a.price),
+                OrderSide::Sell => a.price.cmp(&b.price),
+            };
+            by_price.then(b.priority.cmp(&a.priority))
+        });
+    }
+
     pub fn total_orders(&self) -> usize {
         self.yes_buy_orders.len()
             + self.yes_sell_orders.len()
@@ -75,6 +1351,184 @@ impl OrderBook {
             + self.no_sell_orders.len()
     }
 
+    /// Id for a new order
+    ///
+    /// - `client_nonce = Some(n)` => `Order::client_order_id(user, n)`, rejected with
+    ///   `OrderIdCollision` if that id is already resting
+    /// - `client_nonce = None` => next free value of the `next_order_id` counter
+    pub fn assign_order_id(&mut self, user_key: &Pubkey, client_nonce: Option<u64>) -> Result<u64> {
+        match client_nonce {
+            Some(nonce) => {
+                let id = Order::client_order_id(user_key, nonce);
+                // id 0 is reserved for market orders in OrderMatched
+                require!(
+                    id != 0 && !self.contains_order(id),
+                    PredictionMarketError::OrderIdCollision
+                );
+                Ok(id)
+            }
+            None => {
+                // Skip counter values already taken by a client-chosen id still resting on the book
+                let mut id = self.next_order_id;
+                while self.contains_order(id) {
+                    id = id
+                        .checked_add(1)
+                        .ok_or(PredictionMarketError::MathOverflow)?;
+                }
+                self.next_order_id = id
+                    .checked_add(1)
+                    .ok_or(PredictionMarketError::MathOverflow)?;
+                Ok(id)
+            }
+        }
+    }
+
+    /// Makers an incoming limit order would fill against, in the order `place_order` walks the
+    /// book: orders that don't cross or belong to `taker` are skipped, and the walk stops after
+    /// `max_iteration` fills or once `quantity` is filled. Each maker is listed once.
+    ///
+    /// Fills that round to zero collateral are skipped by `place_order` but still listed here,
+    /// so the result can only over-approximate the counterparties needed.
+    pub fn preview_makers(
+        &self,
+        taker: &Pubkey,
+        side: OrderSide,
+        token_type: TokenType,
+        quantity: u64,
+        price: u64,
+        max_iteration: u64,
+    ) -> Vec<Pubkey> {
+        let opposing = match (token_type, side) {
+            (TokenType::Yes, OrderSide::Buy) => &self.yes_sell_orders,
+            (TokenType::Yes, OrderSide::Sell) => &self.yes_buy_orders,
+            (TokenType::No, OrderSide::Buy) => &self.no_sell_orders,
+            (TokenType::No, OrderSide::Sell) => &self.no_buy_orders,
+        };
+
+        let mut makers: Vec<Pubkey> = Vec::new();
+        let mut remaining = quantity;
+        let mut iteration = 0;
+        for order in opposing.iter() {
+            if remaining == 0 || iteration >= max_iteration {
+                break;
+            }
+            let crosses = match side {
+                OrderSide::Buy => price >= order.price,
+                OrderSide::Sell => price <= order.price,
+            };
+            let book_left = order.quantity.saturating_sub(order.filledquantity);
+            if !crosses || order.user_key == *taker || book_left == 0 {
+                continue;
+            }
+
+            remaining -= remaining.min(book_left);
+            iteration += 1;
+            if !makers.contains(&order.user_key) {
+                makers.push(order.user_key);
+            }
+        }
+        makers
+    }
+
+    /// Highest resting buy price of `token_type`
+    pub fn best_buy(&self, token_type: TokenType) -> Option<u64> {
+        match token_type {
+            TokenType::Yes => self.yes_buy_orders.first(),
+            TokenType::No => self.no_buy_orders.first(),
+        }
+        .map(|o| o.price)
+    }
+
+    /// Lowest resting sell price of `token_type`
+    pub fn best_sell(&self, token_type: TokenType) -> Option<u64> {
+        match token_type {
+            TokenType::Yes => self.yes_sell_orders.first(),
+            TokenType::No => self.no_sell_orders.first(),
+        }
+        .map(|o| o.price)
+    }
+
+    /// Mid of the best bid and ask of `token_type`, or the one side that has orders
+    pub fn mid_price(&self, token_type: TokenType) -> Option<u64> {
+        match (self.best_buy(token_type), self.best_sell(token_type)) {
+            (Some(bid), Some(ask)) => Some(bid / 2 + ask / 2 + (bid % 2 + ask % 2) / 2),
+            (Some(price), None) | (None, Some(price)) => Some(price),
+            (None, None) => None,
+        }
+    }
+
+    /// Mid of the best YES bid and ask, or the one side that has orders
+    pub fn yes_mid_price(&self) -> Option<u64> {
+        self.mid_price(TokenType::Yes)
+    }
+
+    pub fn contains_order(&self, order_id: u64) -> bool {
+        self.yes_buy_orders
+            .iter()
+            .chain(self.yes_sell_orders.iter())
+            .chain(self.no_buy_orders.iter())
+            .chain(self.no_sell_orders.iter())
+            .any(|o| o.id == order_id)
+    }
+
+    /// sha256 snapshot of the resting book, so an off-chain mirror can detect divergence
+    /// without fetching every order.
+    ///
+    /// Preimage, all integers little-endian:
+    /// - vectors in this order: yes_buy_orders, yes_sell_orders, no_buy_orders, no_sell_orders
+    /// - per vector: its length as u32, then each order in stored (sorted) order as
+    ///   id u64 || price u64 || quantity u64 || filledquantity u64
+    pub fn compute_book_hash(&self) -> [u8; 32] {
+        let sides = [
+            &self.yes_buy_orders,
+            &self.yes_sell_orders,
+            &self.no_buy_orders,
+            &self.no_sell_orders,
+        ];
+        let mut preimage = Vec::with_capacity(4 * 4 + self.total_orders() * 32);
+        for orders in sides {
+            preimage.extend_from_slice(&(orders.len() as u32).to_le_bytes());
+            for order in orders.iter() {
+                preimage.extend_from_slice(&order.id.to_le_bytes());
+                preimage.extend_from_slice(&order.price.to_le_bytes());
+                preimage.extend_from_slice(&order.quantity.to_le_bytes());
+                preimage.extend_from_slice(&order.filledquantity.to_le_bytes());
+            }
+        }
+        hash(&preimage).to_bytes()
+    }
+
+    /// Keeps an order that left the book in `closed_orders`. Once `CLOSED_ORDERS_CAPACITY`
+    /// entries are held, each new one overwrites the oldest.
+    pub fn record_closed(&mut self, order: Order, reason: ClosedOrderReason, closed_at: i64) {
+        let entry = ClosedOrder {
+            order,
+            reason,
+            closed_at,
+        };
+        let head = self.closed_orders_head as usize;
+        if head < self.closed_orders.len() {
+            self.closed_orders[head] = entry;
+        } else {
+            self.closed_orders.push(entry);
+        }
+        self.closed_orders_head = ((head + 1) % CLOSED_ORDERS_CAPACITY) as u8;
+    }
+
+    /// Most recent retained entry for `order_id`, newest first since a client-chosen id
+    /// can be reused once its order has left the book
+    pub fn find_closed_order(&self, order_id: u64) -> Option<&ClosedOrder> {
+        let len = self.closed_orders.len();
+        let head = self.closed_orders_head as usize;
+        (1..=len)
+            .map(|back| &self.closed_orders[(head + len - back) % len])
+            .find(|entry| entry.order.id == order_id)
+    }
+
+    pub fn refresh_book_hash(&mut self) {
+        self.book_hash = self.compute_book_hash();
+    }
+
     pub fn current_space_needed(&self) -> usize {
         let max_per_side = self
             .yes_buy_orders
@@ -98,6 +1552,27 @@ impl OrderBook {
     }
 }
 
+// Why an order left the book, see `OrderBook::record_closed`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum ClosedOrderReason {
+    // Fully filled by incoming orders
+    Filled,
+    // Cancelled by its owner (cancel_order, cancel_in_range, replace_order)
+    Cancelled,
+    // Removed by settle_orderbook after settlement, remainder moved to claimable
+    Settled,
+    // Removed by prune_expired_orders after the settlement deadline, remainder moved to claimable
+    Expired,
+}
+
+// `order` keeps its final `filledquantity`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct ClosedOrder {
+    pub order: Order,
+    pub reason: ClosedOrderReason,
+    pub closed_at: i64,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
 pub enum WinningOutcome {
     OutcomeA,
@@ -105,6 +1580,15 @@ pub enum WinningOutcome {
     Neither,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum MarketKind {
+    // YES or NO redeems 1:1 for the winning side
+    Binary,
+    // Both sides redeem pro rata to where the settlement value falls in [low, high],
+    // signed so spread/index markets can settle below zero
+    Scalar { low: i64, high: i64 },
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
 pub enum TokenType {
     Yes,
@@ -116,3 +1600,407 @@ pub enum OrderSide {
     Buy,
     Sell,
 }
+
+// Outcome of a place_order call, see `PlaceOrderResult`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaceOrderStatus {
+    // Whole quantity matched
+    FullyFilled,
+    // Some quantity matched, the rest rests on the book
+    PartiallyFilledResting,
+    // Some quantity matched, the book side was full so the rest went back to claimable
+    PartiallyFilledClaimable,
+    // Nothing matched, the whole order rests on the book
+    Rested,
+    // Nothing matched and the book side was full, the whole order went back to claimable
+    Claimable,
+}
+
+// Returned by place_order
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlaceOrderResult {
+    pub status: PlaceOrderStatus,
+    pub filled_quantity: u64,
+    pub resting_quantity: u64,
+}
+
+impl PlaceOrderResult {
+    pub fn new(quantity: u64, filled_quantity: u64, resting_quantity: u64) -> Self {
+        let status = if filled_quantity >= quantity {
+            PlaceOrderStatus::FullyFilled
+        } else if filled_quantity > 0 && resting_quantity > 0 {
+            PlaceOrderStatus::PartiallyFilledResting
+        } else if filled_quantity > 0 {
+            PlaceOrderStatus::PartiallyFilledClaimable
+        } else if resting_quantity > 0 {
+            PlaceOrderStatus::Rested
+        } else {
+            PlaceOrderStatus::Claimable
+        };
+        PlaceOrderResult {
+            status,
+            filled_quantity,
+            resting_quantity,
+        }
+    }
+}
+
+// Returned by split_and_quote, one result per leg
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SplitAndQuoteResult {
+    pub yes: PlaceOrderResult,
+    pub no: PlaceOrderResult,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum RoundingMode {
+    // Round fill values down, the sub-unit stays with the buyer, fees round up
+    FavorProtocol,
+    // Round fill values toward the resting order
+    FavorMaker,
+    // Round fill values toward the incoming order
+    FavorTaker,
+}
+
+// How orders may rest on a market's book
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum BookMode {
+    // Continuous book, any limit order rests what it doesn't fill
+    Clob,
+    // Only maker quotes (post-only, from place_quote/relist_claimable) rest. A place_order has
+    // to fill completely and a market_order can't rest its remainder.
+    MakerOnly,
+}
+
+// Self-trade prevention, applied in place_order and market_order when an incoming order
+// reaches a resting order of the same user. Nothing trades in any case.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum StpPolicy {
+    // Skip the resting order and keep matching deeper in the book
+    Skip,
+    // Stop matching, the incoming order's unmatched remainder is cancelled instead of resting
+    CancelNewest,
+    // Cancel the resting order and keep matching
+    CancelOldest,
+    // Shrink both orders by the smaller of their remaining quantities and keep matching
+    DecrementBoth,
+}
+
+// Trading fee that starts at `base_fee_bps` and drops by 1 bps every `decay_step` fills on the
+// market, never below `min_fee_bps`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct FeeSchedule {
+    pub base_fee_bps: u16,
+    pub min_fee_bps: u16,
+    pub decay_step: u64,
+}
+
+// Who a `FeeCollected` was charged to
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum FeeKind {
+    // Resting order on the selling side of the fill
+    Maker,
+    // Incoming order on the selling side of the fill
+    Taker,
+}
+
+// See `Order::compute_fill_value`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FillValue {
+    pub gross: u64,
+    pub fee: u64,
+}
+
+impl FillValue {
+    /// What the seller receives
+    pub fn net(&self) -> u64 {
+        self.gross - self.fee
+    }
+}
+
+// See `Order::settle_buy_fill`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuyFill {
+    // Taken off the buyer's locked_collateral
+    pub released: u64,
+    // Added to the seller's claimable_collateral
+    pub seller_proceeds: u64,
+    // Kept in the vault
+    pub fee: u64,
+    // Added to the buyer's claimable_collateral
+    pub buyer_refund: u64,
+}
+
+impl BuyFill {
+    /// Splits `released` collateral between the fill `value` and the buyer's refund. The buyer
+    /// never pays more than was released, and the fee never exceeds what the buyer paid.
+    pub fn split(released: u64, value: FillValue) -> Self {
+        let paid = value.gross.min(released);
+        let seller_proceeds = value.net().min(paid);
+        BuyFill {
+            released,
+            seller_proceeds,
+            fee: paid - seller_proceeds,
+            buyer_refund: released - paid,
+        }
+    }
+
+    /// What the buyer paid for the fill, fee included
+    pub fn paid(&self) -> u64 {
+        self.seller_proceeds + self.fee
+    }
+}
+
+// See `Market::scalar_payout`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScalarPayout {
+    // Owed to the claimant, before any socialized loss
+    pub payout: u64,
+    // Taken off total_collateral_locked, payout plus the rounding dust
+    pub retired: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{MARKET_CATEGORY_OFFSET, MARKET_IN_PROGRESS_OFFSET};
+
+    fn legacy_market() -> LegacyMarket {
+        LegacyMarket {
+            authority: Pubkey::new_unique(),
+            market_id: 42,
+            settlement_deadline: 1_700_000_000,
+            collateral_mint: Pubkey::new_unique(),
+            collateral_vault: Pubkey::new_unique(),
+            is_settled: true,
+            winning_outcome: Some(WinningOutcome::OutcomeB),
+            total_collateral_locked: 1_000,
+            bump: 254,
+            meta_data_url: "https://example.com/market.json".to_string(),
+            outcome_yes_mint: Pubkey::new_unique(),
+            outcome_no_mint: Pubkey::new_unique(),
+            yes_escrow: Pubkey::new_unique(),
+            no_escrow: Pubkey::new_unique(),
+        }
+    }
+
+    fn market() -> Market {
+        Market::from_legacy(legacy_market(), 253, 252, 251, 1_000_000, 0)
+    }
+
+    fn settle(
+        buyer_remaining: u64,
+        fill_qty: u64,
+        buyer_price: u64,
+        book_price: u64,
+        fee_bps: u16,
+        rounding: RoundingMode,
+        taker_is_buyer: bool,
+    ) -> BuyFill {
+        let fill = Order::settle_buy_fill(
+            buyer_remaining,
+            fill_qty,
+            buyer_price,
+            book_price,
+            fee_bps,
+            rounding,
+            taker_is_buyer,
+        )
+        .unwrap();
+        assert_eq!(
+            fill.released,
+            fill.seller_proceeds + fill.fee + fill.buyer_refund
+        );
+        fill
+    }
+
+    #[test]
+    fn settle_buy_fill_at_the_buyer_price() {
+        let fill = settle(
+            1_000_000,
+            1_000_000,
+            500_000,
+            500_000,
+            0,
+            RoundingMode::FavorProtocol,
+            false,
+        );
+        assert_eq!(fill.released, 500_000);
+        assert_eq!(fill.seller_proceeds, 500_000);
+        assert_eq!(fill.fee, 0);
+        assert_eq!(fill.buyer_refund, 0);
+    }
+
+    #[test]
+    fn settle_buy_fill_refunds_price_improvement() {
+        let fill = settle(
+            2_000_000,
+            1_000_000,
+            600_000,
+            500_000,
+            0,
+            RoundingMode::FavorProtocol,
+            false,
+        );
+        assert_eq!(fill.released, 600_000);
+        assert_eq!(fill.seller_proceeds, 500_000);
+        assert_eq!(fill.fee, 0);
+        assert_eq!(fill.buyer_refund, 100_000);
+    }
+
+    #[test]
+    fn settle_buy_fill_rounds_per_mode() {
+        // 3 base units at 0.333333 are worth 0.999999 of a collateral unit, the lock releases 1
+        let cases = [
+            (RoundingMode::FavorProtocol, true, 0),
+            (RoundingMode::FavorProtocol, false, 0),
+            (RoundingMode::FavorMaker, true, 1),
+            (RoundingMode::FavorMaker, false, 0),
+            (RoundingMode::FavorTaker, true, 0),
+            (RoundingMode::FavorTaker, false, 1),
+        ];
+        for (rounding, taker_is_buyer, proceeds) in cases {
+            let fill = settle(4, 3, 333_333, 333_333, 0, rounding, taker_is_buyer);
+            assert_eq!(fill.released, 1);
+            assert_eq!(fill.seller_proceeds, proceeds);
+            assert_eq!(fill.fee, 0);
+            assert_eq!(fill.buyer_refund, 1 - proceeds);
+        }
+    }
+
+    #[test]
+    fn settle_buy_fill_charges_the_fee_to_the_seller() {
+        let fill = settle(
+            1_000_000,
+            1_000_000,
+            500_000,
+            500_000,
+            100,
+            RoundingMode::FavorProtocol,
+            false,
+        );
+        assert_eq!(fill.seller_proceeds, 495_000);
+        assert_eq!(fill.fee, 5_000);
+        assert_eq!(fill.buyer_refund, 0);
+
+        // 1% of 333 is 3.33: rounded up for the protocol, down otherwise
+        let fill = settle(
+            1_000,
+            1_000,
+            333_000,
+            333_000,
+            100,
+            RoundingMode::FavorProtocol,
+            false,
+        );
+        assert_eq!((fill.seller_proceeds, fill.fee), (329, 4));
+        let fill = settle(
+            1_000,
+            1_000,
+            333_000,
+            333_000,
+            100,
+            RoundingMode::FavorMaker,
+            true,
+        );
+        assert_eq!((fill.seller_proceeds, fill.fee), (330, 3));
+
+        // The fee comes out of the fill value, the improvement still goes back to the buyer
+        let fill = settle(
+            1_000_000,
+            1_000_000,
+            600_000,
+            500_000,
+            50,
+            RoundingMode::FavorTaker,
+            true,
+        );
+        assert_eq!(fill.released, 600_000);
+        assert_eq!(fill.seller_proceeds, 497_500);
+        assert_eq!(fill.fee, 2_500);
+        assert_eq!(fill.buyer_refund, 100_000);
+    }
+
+    #[test]
+    fn from_legacy_keeps_the_legacy_fields() {
+        let legacy = legacy_market();
+        let market = Market::from_legacy(legacy.clone(), 253, 252, 251, 1_000_000, 5);
+
+        let mut data = Vec::new();
+        market.try_serialize(&mut data).unwrap();
+        let market = Market::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(market.authority, legacy.authority);
+        assert_eq!(market.market_id, legacy.market_id);
+        assert_eq!(market.settlement_deadline, legacy.settlement_deadline);
+        assert_eq!(market.collateral_vault, legacy.collateral_vault);
+        assert_eq!(market.winning_outcome, legacy.winning_outcome);
+        assert_eq!(
+            market.total_collateral_locked,
+            legacy.total_collateral_locked
+        );
+        assert_eq!(market.bump, legacy.bump);
+        assert_eq!(market.meta_data_url, legacy.meta_data_url);
+        assert_eq!(market.no_escrow, legacy.no_escrow);
+        assert_eq!(
+            (
+                market.vault_bump,
+                market.yes_escrow_bump,
+                market.no_escrow_bump
+            ),
+            (253, 252, 251)
+        );
+        assert_eq!((market.min_price, market.max_price), (1, 1_000_000));
+        assert_eq!(market.last_metadata_update, 5);
+    }
+
+    #[test]
+    fn category_and_in_progress_sit_at_their_offsets() {
+        let mut market = market();
+        market.category = 0x0102;
+        market.in_progress = true;
+
+        let mut data = Vec::new();
+        market.try_serialize(&mut data).unwrap();
+        assert_eq!(
+            data[MARKET_CATEGORY_OFFSET..MARKET_CATEGORY_OFFSET + 2],
+            0x0102u16.to_le_bytes()
+        );
+        assert_eq!(data[MARKET_IN_PROGRESS_OFFSET], 1);
+        assert!(data.len() <= Market::DISCRIMINATOR.len() + Market::INIT_SPACE);
+    }
+
+    #[test]
+    fn socialized_payout_leaves_the_treasury_out_of_the_backing() {
+        let mut market = market();
+        market.socialized_loss = true;
+        market.total_collateral_locked = 1_000;
+
+        // Fully backed once the treasury is set aside, every claim is paid in full
+        market.treasury_balance = 100;
+        assert_eq!(market.socialized_payout(100, 1_100).unwrap(), 100);
+
+        // The same vault with a 200 treasury only backs 900 of the 1000 owed
+        market.treasury_balance = 200;
+        assert_eq!(market.socialized_payout(100, 1_100).unwrap(), 90);
+        assert_eq!(market.socialized_payout(1_000, 1_100).unwrap(), 900);
+
+        // A vault holding no more than the treasury backs nothing
+        assert_eq!(market.socialized_payout(100, 150).unwrap(), 0);
+    }
+
+    #[test]
+    fn rebased_payout_leaves_the_treasury_in_the_vault() {
+        let mut market = market();
+        market.rebasing = true;
+        market.total_collateral_locked = 1_000;
+        market.treasury_balance = 100;
+
+        // 10% yield on the 1000 owed to users, the 100 treasury isn't part of the share
+        assert_eq!(market.rebased_payout(500, 1_200).unwrap(), 550);
+
+        // Every claim paid, exactly the treasury is left in the vault to withdraw
+        let paid = market.socialized_payout(1_000, 1_200).unwrap();
+        assert_eq!(paid, 1_100);
+        assert_eq!(1_200 - paid, market.treasury_balance);
+    }
+}
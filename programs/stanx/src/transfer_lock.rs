@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{self, FreezeAccount, ThawAccount},
+    token_interface::{TokenAccount, TokenInterface},
+};
+
+use crate::constants::*;
+use crate::error::*;
+use crate::state::*;
+
+// Restricted-transfer markets keep users' outcome token accounts frozen while the market is live,
+// so YES/NO can only move through program instructions. The market PDA is the mints' freeze
+// authority; instructions thaw the account before touching it and freeze it again when done.
+// After settlement accounts are no longer re-frozen.
+
+/// Thaws `account` if the market restricts transfers and it is currently frozen
+pub fn thaw_if_restricted<'info>(
+    market: &Account<'info, Market>,
+    token_program: &Interface<'info, TokenInterface>,
+    account: &InterfaceAccount<'info, TokenAccount>,
+    mint: Option<AccountInfo<'info>>,
+) -> Result<()> {
+    if !market.restricted_transfer || !account.is_frozen() {
+        return Ok(());
+    }
+    let mint = mint.ok_or(PredictionMarketError::OutcomeMintRequired)?;
+
+    let market_id_bytes = market.market_id.to_le_bytes();
+    let signer_seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[market.bump]];
+
+    token::thaw_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        ThawAccount {
+            account: account.to_account_info(),
+            mint,
+            authority: market.to_account_info(),
+        },
+        &[signer_seeds],
+    ))
+}
+
+/// Freezes `account` again if the market restricts transfers and is not settled yet,
+/// must be paired with `thaw_if_restricted` on the same account
+pub fn freeze_if_restricted<'info>(
+    market: &Account<'info, Market>,
+    token_program: &Interface<'info, TokenInterface>,
+    account: &InterfaceAccount<'info, TokenAccount>,
+    mint: Option<AccountInfo<'info>>,
+) -> Result<()> {
+    if !market.restricted_transfer || market.is_settled {
+        return Ok(());
+    }
+    let mint = mint.ok_or(PredictionMarketError::OutcomeMintRequired)?;
+
+    let market_id_bytes = market.market_id.to_le_bytes();
+    let signer_seeds = &[MARKET_SEED, market_id_bytes.as_ref(), &[market.bump]];
+
+    token::freeze_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        FreezeAccount {
+            account: account.to_account_info(),
+            mint,
+            authority: market.to_account_info(),
+        },
+        &[signer_seeds],
+    ))
+}
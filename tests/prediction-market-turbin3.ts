@@ -15,6 +15,8 @@ import {
   TOKEN_PROGRAM_ID,
   ASSOCIATED_TOKEN_PROGRAM_ID,
   getAccount,
+  getMint,
+  transfer,
 } from "@solana/spl-token";
 import { BN } from "bn.js";
 import { assert, expect } from "chai";
@@ -27,6 +29,39 @@ describe("prediction_market", () => {
   const program = anchor.workspace
     .predictionMarketTurbin3 as Program<PredictionMarketTurbin3>;
 
+  // Registry page listing a market: ["market_registry", floor(market_id / 256)]
+  const registryPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("market_registry"),
+        new BN(Math.floor(id / 256)).toArrayLike(Buffer, "le", 4),
+      ],
+      program.programId,
+    )[0];
+
+  // Slug index of a market: ["market_slug", slug]
+  const slugOf = (id: number) => `market-${id}`;
+  const slugPda = (slug: string) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("market_slug"), Buffer.from(slug)],
+      program.programId,
+    )[0];
+
+  // close_market's optional accounts, only read with cancel_own_orders when the authority
+  // still has resting orders
+  const noRefundAccounts = {
+    authorityStats: null,
+    collateralVault: null,
+    authorityCollateral: null,
+    yesEscrow: null,
+    noEscrow: null,
+    authorityOutcomeYes: null,
+    authorityOutcomeNo: null,
+    outcomeYesMint: null,
+    outcomeNoMint: null,
+    tokenProgram: null,
+  };
+
   let authority = provider.wallet;
   let user: Keypair;
 
@@ -125,7 +160,7 @@ describe("prediction_market", () => {
 
       // Now you can see all the accounts needed for initializeMarket!
       await program.methods
-        .initializeMarket(marketId, settlementDeadline, "")
+        .initializeMarket(marketId, settlementDeadline, "", 0, false, "", "", { binary: {} }, null, null, slugOf(marketId), false, new BN(0))
         .accounts({
           market: marketPda,
           authority: authority.publicKey,
@@ -136,6 +171,8 @@ describe("prediction_market", () => {
           yesEscrowPda,
           noEscrowPda,
           orderbookPda,
+          marketRegistry: registryPda(marketId),
+          marketSlug: slugPda(slugOf(marketId)),
           systemProgram: SystemProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
         })
@@ -143,6 +180,342 @@ describe("prediction_market", () => {
 
       console.log("Market initialized successfully!");
       console.log("Market PDA:", marketPda.toBase58());
+
+      const registry = await program.account.marketRegistry.fetch(
+        registryPda(marketId),
+      );
+      assert.deepInclude(registry.entries, { marketId, closed: false });
+    });
+
+    it("Rejects initializing a market id that already exists", async () => {
+      try {
+        await program.methods
+          .initializeMarket(marketId, settlementDeadline, "", 0, false, "", "", { binary: {} }, null, null, slugOf(marketId), false, new BN(0))
+          .accounts({
+            market: marketPda,
+            authority: authority.publicKey,
+            collateralMint: collateralMint,
+            collateralVault,
+            outcomeYesMint,
+            outcomeNoMint,
+            yesEscrowPda,
+            noEscrowPda,
+            orderbookPda,
+            marketRegistry: registryPda(marketId),
+            marketSlug: slugPda(slugOf(marketId)),
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .rpc();
+        assert.fail("Should not allow initializing an existing market");
+      } catch (err) {
+        expect(err.toString()).to.include("MarketAlreadyExists");
+      }
+    });
+
+    // Fresh market PDAs for `initialize_market_from_template`
+    const templateInitAccounts = (id: number, payer: PublicKey) => {
+      const idLE = new BN(id).toArrayLike(Buffer, "le", 4);
+      const pda = (...seeds: Buffer[]) =>
+        PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+      const yesMint = pda(Buffer.from("outcome_a"), idLE);
+      const noMint = pda(Buffer.from("outcome_b"), idLE);
+      return {
+        market: pda(Buffer.from("market"), idLE),
+        authority: payer,
+        collateralMint,
+        collateralVault: pda(Buffer.from("vault"), idLE),
+        outcomeYesMint: yesMint,
+        outcomeNoMint: noMint,
+        yesEscrow: pda(Buffer.from("escrow"), idLE, yesMint.toBuffer()),
+        noEscrow: pda(Buffer.from("escrow"), idLE, noMint.toBuffer()),
+        orderbook: pda(Buffer.from("orderbook"), idLE),
+        marketRegistry: registryPda(id),
+        marketSlug: slugPda(slugOf(id)),
+        systemProgram: SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      };
+    };
+
+    it("Initializes a market from a template", async () => {
+      const templatedMarketId = 4;
+      const deadline = new BN(Math.floor(Date.now() / 1000) + 2 * 86400);
+      const init = templateInitAccounts(templatedMarketId, authority.publicKey);
+
+      await program.methods
+        .initializeMarketFromTemplate(
+          templatedMarketId,
+          marketId,
+          deadline,
+          slugOf(templatedMarketId),
+          new BN(0),
+        )
+        .accounts({ template: marketPda, init })
+        .rpc();
+
+      const template = await program.account.market.fetch(marketPda);
+      const market = await program.account.market.fetch(init.market);
+      assert.equal(market.marketId, templatedMarketId);
+      assert.ok(market.settlementDeadline.eq(deadline));
+      assert.ok(market.outcomeYesMint.equals(init.outcomeYesMint));
+      assert.ok(market.collateralMint.equals(template.collateralMint));
+      assert.ok(market.minPrice.eq(template.minPrice));
+      assert.ok(market.maxPrice.eq(template.maxPrice));
+      assert.equal(market.category, template.category);
+      assert.deepEqual(market.marketKind, template.marketKind);
+      assert.deepEqual(market.roundingMode, template.roundingMode);
+    });
+
+    it("Rejects a template owned by another authority", async () => {
+      try {
+        await program.methods
+          .initializeMarketFromTemplate(
+            5,
+            marketId,
+            new BN(Math.floor(Date.now() / 1000) + 86400),
+            slugOf(5),
+            new BN(0),
+          )
+          .accounts({
+            template: marketPda,
+            init: templateInitAccounts(5, user.publicKey),
+          })
+          .signers([user])
+          .rpc();
+        assert.fail("Should not clone another authority's market");
+      } catch (err) {
+        expect(err.toString()).to.include("InvalidTemplate");
+      }
+    });
+
+    it("Rejects a metadata URL longer than 200 bytes", async () => {
+      try {
+        await program.methods
+          .initializeMarket(
+            6,
+            new BN(Math.floor(Date.now() / 1000) + 86400),
+            "x".repeat(201),
+            0,
+            false,
+            "",
+            "",
+            { binary: {} },
+            null,
+            null,
+            slugOf(6),
+            false,
+            new BN(0),
+          )
+          .accounts(templateInitAccounts(6, authority.publicKey))
+          .rpc();
+        assert.fail("Should reject an oversized metadata URL");
+      } catch (err) {
+        expect(err.toString()).to.include("InvalidMetadata");
+      }
+    });
+
+    it("Restricts market creation to allowlisted creators", async () => {
+      const [configPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("config")],
+        program.programId,
+      );
+
+      await program.methods
+        .initializeConfig()
+        .accounts({ admin: authority.publicKey })
+        .rpc();
+      await program.methods
+        .addMarketCreator(authority.publicKey)
+        .accounts({ admin: authority.publicKey })
+        .rpc();
+
+      const config = await program.account.config.fetch(configPda);
+      assert.ok(config.admin.equals(authority.publicKey));
+      assert.equal(config.creators.length, 1);
+
+      try {
+        await program.methods
+          .initializeMarket(
+            7,
+            new BN(Math.floor(Date.now() / 1000) + 86400),
+            "",
+            0,
+            false,
+            "",
+            "",
+            { binary: {} },
+            null,
+            null,
+            slugOf(7),
+            false,
+            new BN(0),
+          )
+          .accounts(templateInitAccounts(7, user.publicKey))
+          .signers([user])
+          .rpc();
+        assert.fail("Non-allowlisted signer should not create markets");
+      } catch (err) {
+        expect(err.toString()).to.include("NotAuthorized");
+      }
+
+      try {
+        await program.methods
+          .addMarketCreator(user.publicKey)
+          .accounts({ admin: user.publicKey })
+          .signers([user])
+          .rpc();
+        assert.fail("Only the admin can edit the allowlist");
+      } catch (err) {
+        expect(err.toString()).to.include("NotAuthorized");
+      }
+
+      // An empty allowlist reopens market creation for the rest of the suite
+      await program.methods
+        .removeMarketCreator(authority.publicKey)
+        .accounts({ admin: authority.publicKey })
+        .rpc();
+      const reopened = await program.account.config.fetch(configPda);
+      assert.equal(reopened.creators.length, 0);
+    });
+
+    it("Locks seed collateral at creation and returns it after settlement", async () => {
+      const seedMarketId = 9;
+      const init = templateInitAccounts(seedMarketId, authority.publicKey);
+      const authorityCollateral = (
+        await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          authority.payer,
+          collateralMint,
+          authority.publicKey,
+        )
+      ).address;
+      await mintTo(
+        provider.connection,
+        authority.payer,
+        collateralMint,
+        authorityCollateral,
+        authority.publicKey,
+        1_000,
+      );
+
+      await program.methods
+        .setMinSeedCollateral(new BN(1_000))
+        .accounts({ admin: authority.publicKey })
+        .rpc();
+
+      const deadline = new BN(Math.floor(Date.now() / 1000) + 2);
+      const create = (seed: number) =>
+        program.methods
+          .initializeMarket(
+            seedMarketId,
+            deadline,
+            "",
+            0,
+            false,
+            "",
+            "",
+            { binary: {} },
+            null,
+            null,
+            slugOf(seedMarketId),
+            false,
+            new BN(seed),
+          )
+          .accounts({ ...init, authorityCollateral })
+          .rpc();
+
+      try {
+        await create(999);
+        assert.fail("Should reject a seed below the configured minimum");
+      } catch (err) {
+        expect(err.toString()).to.include("SeedCollateralTooLow");
+      }
+
+      await create(1_000);
+      let market = await program.account.market.fetch(init.market);
+      assert.equal(Number(market.seedCollateral), 1_000);
+      assert.equal(Number(market.totalCollateralLocked), 1_000);
+      let vault = await getAccount(provider.connection, init.collateralVault);
+      assert.equal(Number(vault.amount), 1_000);
+
+      // Seeding is optional again for the rest of the suite
+      await program.methods
+        .setMinSeedCollateral(new BN(0))
+        .accounts({ admin: authority.publicKey })
+        .rpc();
+
+      const withdrawSeed = () =>
+        program.methods
+          .withdrawSeed(seedMarketId)
+          .accounts({
+            authority: authority.publicKey,
+            market: init.market,
+            authorityCollateral,
+            collateralVault: init.collateralVault,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .rpc();
+
+      try {
+        await withdrawSeed();
+        assert.fail("Seed stays locked until settlement");
+      } catch (err) {
+        expect(err.toString()).to.include("MarketNotSettled");
+      }
+
+      await new Promise((s) => setTimeout(s, 3000));
+      await program.methods
+        .setWinner(seedMarketId, { outcomeA: {} }, null)
+        .accounts({
+          market: init.market,
+          authority: authority.publicKey,
+          outcomeYesMint: init.outcomeYesMint,
+          outcomeNoMint: init.outcomeNoMint,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const before = await getAccount(provider.connection, authorityCollateral);
+      await withdrawSeed();
+      const after = await getAccount(provider.connection, authorityCollateral);
+      assert.equal(Number(after.amount) - Number(before.amount), 1_000);
+      vault = await getAccount(provider.connection, init.collateralVault);
+      assert.equal(Number(vault.amount), 0);
+      market = await program.account.market.fetch(init.market);
+      assert.equal(Number(market.seedCollateral), 0);
+      assert.equal(Number(market.totalCollateralLocked), 0);
+    });
+
+    it("Resolves a slug to its market and rejects taken or malformed slugs", async () => {
+      const resolved = await program.methods
+        .resolveSlug(slugOf(marketId))
+        .accounts({ marketSlug: slugPda(slugOf(marketId)) })
+        .view();
+      assert.equal(resolved, marketId);
+
+      const deadline = new BN(Math.floor(Date.now() / 1000) + 86400);
+      for (const [slug, error] of [
+        [slugOf(marketId), "SlugTaken"],
+        ["Not A Slug", "InvalidSlug"],
+      ]) {
+        try {
+          await program.methods
+            .initializeMarketFromTemplate(8, marketId, deadline, slug, new BN(0))
+            .accounts({
+              template: marketPda,
+              init: {
+                ...templateInitAccounts(8, authority.publicKey),
+                marketSlug: slugPda(slug),
+              },
+            })
+            .rpc();
+          assert.fail(`Should reject slug "${slug}"`);
+        } catch (err) {
+          expect(err.toString()).to.include(error);
+        }
+      }
     });
   });
 
@@ -246,6 +619,50 @@ describe("prediction_market", () => {
       assert.equal(Number(market.totalCollateralLocked), splitAmount);
     });
 
+    it("Reports balanced YES/NO supplies after a split", async () => {
+      const report = await program.methods
+        .verifySupplyInvariant(marketId)
+        .accounts({
+          market: marketPda,
+          outcomeYesMint,
+          outcomeNoMint,
+        })
+        .view();
+
+      assert.ok(report.yesSupply.eq(report.noSupply));
+      assert.ok(report.yesSupply.gtn(0));
+      assert.isFalse(report.isSettled);
+
+      // Only splits have minted so far, so the counters match the mint supplies
+      const status = await program.methods
+        .marketStatus(marketId)
+        .accounts({ market: marketPda, orderbook: orderbookPda })
+        .view();
+      assert.ok(status.yesMinted.eq(report.yesSupply));
+      assert.ok(status.noMinted.eq(report.noSupply));
+    });
+
+    it("Reads the market configuration in one call", async () => {
+      const market = await program.account.market.fetch(marketPda);
+      const config = await program.methods
+        .getMarketConfig(marketId)
+        .accounts({ market: marketPda })
+        .view();
+
+      assert.equal(config.marketId, marketId);
+      assert.isTrue(config.authority.equals(market.authority));
+      assert.isTrue(config.collateralMint.equals(market.collateralMint));
+      assert.ok(config.settlementDeadline.eq(market.settlementDeadline));
+      assert.ok(config.minPrice.eq(market.minPrice));
+      assert.ok(config.maxPrice.eq(market.maxPrice));
+      assert.deepEqual(config.bookMode, market.bookMode);
+      assert.deepEqual(config.roundingMode, market.roundingMode);
+      assert.equal(config.outcomeALabel, market.outcomeALabel);
+      assert.isNull(config.feeSchedule);
+      assert.equal(config.effectiveFeeBps, 0);
+      assert.isFalse(config.rebasing);
+    });
+
     it("What If we give zero amount, then we will observe the State", async () => {
       try {
         const splitAmount = 0;
@@ -425,6 +842,7 @@ describe("prediction_market", () => {
           new BN(orderqty),
           new BN(price),
           new BN(max_iteration),
+          null,
         )
         .accounts({
           market: marketPda,
@@ -539,6 +957,7 @@ describe("prediction_market", () => {
           new BN(sell_orderqty),
           new BN(sell_price),
           new BN(max_iteration),
+          null,
         )
         .accounts({
           market: marketPda,
@@ -750,7 +1169,7 @@ describe("prediction_market", () => {
 
       let orderqty = 2;
       let priceStart = 0.6 * USDC_UNIT;
-      let priceIncrement = 0.1 * USDC_UNIT;
+      let priceIncrement = 0.03 * USDC_UNIT; // stays below the 1.0 max_price
 
       {
         console.log("\n" + "-".repeat(80));
@@ -807,6 +1226,7 @@ describe("prediction_market", () => {
             new BN(orderqty),
             new BN(priceStart + i * priceIncrement),
             new BN(max_iteration),
+            null,
           )
           .accounts({
             market: marketPda,
@@ -893,6 +1313,7 @@ describe("prediction_market", () => {
           new BN(other_user_qty_sell),
           new BN(other_user_price_sell),
           new BN(max_iteration),
+          null,
         )
         .accounts({
           market: marketPda,
@@ -1088,6 +1509,7 @@ describe("prediction_market", () => {
             new BN(other_user_qty),
             new BN(other_user_price),
             new BN(max_iteration),
+            null,
           )
           .accounts({
             market: marketPda,
@@ -1138,6 +1560,8 @@ describe("prediction_market", () => {
             { yes: {} },
             new BN(user_order_amount),
             new BN(max_iteration),
+            false,
+            null,
           )
           .accounts({
             market: marketPda,
@@ -1203,118 +1627,359 @@ describe("prediction_market", () => {
           "Error in Market Order, Locked Token in other User account are not equal to the received token in the main user account",
         );
       });
-    });
 
-    describe("Checking for Order Cancellation", async () => {
-      it("Cancelling a Buy Order - should return locked collateral", async () => {
-        // User places a buy order for YES tokens
-        let orderqty = 5;
-        let price = 0.6 * USDC_UNIT;
-        let orderAmount = orderqty * price;
+      const marketOrderAccounts = () => ({
+        market: marketPda,
+        orderbook: orderbookPda,
+        user: user.publicKey,
+        userOutcomeYes: userOutcomeYesAccount,
+        userOutcomeNo: userOutcomeNoAccount,
+        collateralVault,
+        userCollateral: userCollateralAccount,
+        userStatsAccount,
+        outcomeYesMint,
+        outcomeNoMint,
+        yesEscrow: yesEscrowPda,
+        noEscrow: noEscrowPda,
+        systemProgram: SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      });
 
-        // Get initial state
-        let userCollateralAccountBefore = await getAccount(
+      it("Auto-mints the unfilled part of a market buy", async () => {
+        const orderAmount = 2 * USDC_UNIT;
+        const collateralBefore = await getAccount(
           provider.connection,
           userCollateralAccount,
         );
-        let userStatsAccountBefore = await program.account.userStats.fetch(
-          userStatsAccount,
+        const yesBefore = await getAccount(
+          provider.connection,
+          userOutcomeYesAccount,
         );
-        let orderbookBefore = await program.account.orderBook.fetch(
-          orderbookPda,
+        const noBefore = await getAccount(
+          provider.connection,
+          userOutcomeNoAccount,
         );
-        let initialBuyOrdersLength = orderbookBefore.yesBuyOrders.length;
 
-        // Place the buy order
         await program.methods
-          .placeOrder(
+          .marketOrder(
             marketId,
             { buy: {} },
             { yes: {} },
-            new BN(orderqty),
-            new BN(price),
+            new BN(orderAmount),
             new BN(max_iteration),
+            true,
+            null,
           )
-          .accounts({
-            market: marketPda,
-            orderbook: orderbookPda,
-            user: user.publicKey,
-            userOutcomeYes: userOutcomeYesAccount,
-            userOutcomeNo: userOutcomeNoAccount,
-            collateralVault,
-            userCollateral: userCollateralAccount,
-            userStatsAccount,
-            yesEscrow: yesEscrowPda,
-            noEscrow: noEscrowPda,
-            systemProgram: SystemProgram.programId,
-            tokenProgram: TOKEN_PROGRAM_ID,
-          })
+          .accounts(marketOrderAccounts())
+          .remainingAccounts([
+            {
+              pubkey: other_userStatsAccount,
+              isSigner: false,
+              isWritable: true,
+            },
+          ])
           .signers([user])
           .rpc();
 
-        // Verify order was placed
-        let orderbookAfterPlace = await program.account.orderBook.fetch(
-          orderbookPda,
+        const collateralAfter = await getAccount(
+          provider.connection,
+          userCollateralAccount,
         );
-        assert.equal(
-          orderbookAfterPlace.yesBuyOrders.length,
-          initialBuyOrdersLength + 1,
-          "Order should be added to orderbook",
+        const yesAfter = await getAccount(
+          provider.connection,
+          userOutcomeYesAccount,
         );
-
-        let userStatsAfterPlace = await program.account.userStats.fetch(
-          userStatsAccount,
+        const noAfter = await getAccount(
+          provider.connection,
+          userOutcomeNoAccount,
         );
+        const minted = Number(noAfter.amount) - Number(noBefore.amount);
+
+        // Nothing is refunded, the unfilled collateral became YES+NO pairs
         assert.equal(
-          Number(userStatsAfterPlace.lockedCollateral) -
-            Number(userStatsAccountBefore.lockedCollateral),
+          Number(collateralBefore.amount) - Number(collateralAfter.amount),
           orderAmount,
-          "Collateral should be locked after placing order",
         );
+        assert.isAtLeast(
+          Number(yesAfter.amount) - Number(yesBefore.amount),
+          minted,
+        );
+      });
 
-        // Get the order ID (last order in the list)
-        let orderId =
-          orderbookAfterPlace.yesBuyOrders[
-            orderbookAfterPlace.yesBuyOrders.length - 1
-          ].id;
+      it("Rests the unfilled part of a market buy at the fallback price", async () => {
+        const orderAmount = 1 * USDC_UNIT;
+        const restPrice = 0.37 * USDC_UNIT;
+        const statsBefore = await program.account.userStats.fetch(
+          userStatsAccount,
+        );
 
-        // Cancel the order
         await program.methods
-          .cancelOrder(marketId, new BN(orderId))
-          .accounts({
-            market: marketPda,
-            orderbook: orderbookPda,
-            user: user.publicKey,
-            userOutcomeYes: userOutcomeYesAccount,
-            userOutcomeNo: userOutcomeNoAccount,
-            collateralVault,
-            userCollateral: userCollateralAccount,
-            userStatsAccount,
-            yesEscrow: yesEscrowPda,
-            noEscrow: noEscrowPda,
-            systemProgram: SystemProgram.programId,
-            tokenProgram: TOKEN_PROGRAM_ID,
-          })
+          .marketOrder(
+            marketId,
+            { buy: {} },
+            { no: {} },
+            new BN(orderAmount),
+            new BN(max_iteration),
+            false,
+            new BN(restPrice),
+          )
+          .accounts(marketOrderAccounts())
           .signers([user])
           .rpc();
 
-        // Verify order was cancelled
-        let orderbookAfterCancel = await program.account.orderBook.fetch(
-          orderbookPda,
+        const book = await program.account.orderBook.fetch(orderbookPda);
+        const rested = book.noBuyOrders.find(
+          (o) =>
+            o.userKey.equals(user.publicKey) && o.price.toNumber() === restPrice,
         );
-        let userStatsAfterCancel = await program.account.userStats.fetch(
+        assert.ok(rested, "unfilled remainder should rest on the NO bids");
+
+        // Only what the resting order needs stays locked
+        const statsAfter = await program.account.userStats.fetch(
           userStatsAccount,
         );
-        let userCollateralAccountAfter = await getAccount(
-          provider.connection,
-          userCollateralAccount,
-        );
-
-        // Order should be removed from orderbook
         assert.equal(
-          orderbookAfterCancel.yesBuyOrders.length,
-          initialBuyOrdersLength,
-          "Order should be removed from orderbook after cancellation",
+          statsAfter.lockedCollateral.toNumber() -
+            statsBefore.lockedCollateral.toNumber(),
+          Math.floor((rested.quantity.toNumber() * restPrice) / USDC_UNIT),
+        );
+      });
+
+      it("Rejects auto_mint on a market sell", async () => {
+        try {
+          await program.methods
+            .marketOrder(
+              marketId,
+              { sell: {} },
+              { yes: {} },
+              new BN(1_000_000),
+              new BN(max_iteration),
+              true,
+              null,
+            )
+            .accounts(marketOrderAccounts())
+            .signers([user])
+            .rpc();
+          assert.fail("auto_mint should be rejected for sells");
+        } catch (err) {
+          expect(err.toString()).to.include("AutoMintBuyOnly");
+        }
+      });
+
+      it("Fails a crossing limit order before locking funds when counterparty stats are missing", async () => {
+        const askPrice = 0.98 * USDC_UNIT;
+        const otherUserAccounts = {
+          market: marketPda,
+          orderbook: orderbookPda,
+          user: other_user.publicKey,
+          userOutcomeYes: other_userOutcomeYesAccount,
+          userOutcomeNo: other_userOutcomeNoAccount,
+          collateralVault,
+          userCollateral: other_userCollateralAccount,
+          userStatsAccount: other_userStatsAccount,
+          yesEscrow: yesEscrowPda,
+          noEscrow: noEscrowPda,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        };
+
+        await program.methods
+          .placeOrder(
+            marketId,
+            { sell: {} },
+            { yes: {} },
+            new BN(1_000_000),
+            new BN(askPrice),
+            new BN(max_iteration),
+            null,
+          )
+          .accounts(otherUserAccounts)
+          .signers([other_user])
+          .rpc();
+
+        // match_preview lists the UserStats the buy below has to pass
+        const preview: PublicKey[] = await program.methods
+          .matchPreview(
+            marketId,
+            { buy: {} },
+            { yes: {} },
+            new BN(1_000_000),
+            new BN(askPrice),
+            new BN(max_iteration),
+          )
+          .accounts({ user: user.publicKey, orderbook: orderbookPda })
+          .view();
+        assert.ok(preview.some((k) => k.equals(other_userStatsAccount)));
+        assert.notOk(preview.some((k) => k.equals(userStatsAccount)));
+
+        const collateralBefore = await getAccount(
+          provider.connection,
+          userCollateralAccount,
+        );
+        try {
+          await program.methods
+            .placeOrder(
+              marketId,
+              { buy: {} },
+              { yes: {} },
+              new BN(1_000_000),
+              new BN(askPrice),
+              new BN(max_iteration),
+              null,
+            )
+            .accounts({
+              market: marketPda,
+              orderbook: orderbookPda,
+              user: user.publicKey,
+              userOutcomeYes: userOutcomeYesAccount,
+              userOutcomeNo: userOutcomeNoAccount,
+              collateralVault,
+              userCollateral: userCollateralAccount,
+              userStatsAccount,
+              yesEscrow: yesEscrowPda,
+              noEscrow: noEscrowPda,
+              systemProgram: SystemProgram.programId,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .signers([user])
+            .rpc();
+          assert.fail("Should require the seller's UserStats up front");
+        } catch (err) {
+          expect(err.toString()).to.include("SellerStatsAccountNotProvided");
+        }
+        const collateralAfter = await getAccount(
+          provider.connection,
+          userCollateralAccount,
+        );
+        assert.equal(collateralAfter.amount, collateralBefore.amount);
+
+        // Take the ask off the book again
+        const book = await program.account.orderBook.fetch(orderbookPda);
+        const ask = book.yesSellOrders.find(
+          (o) =>
+            o.userKey.equals(other_user.publicKey) &&
+            o.price.toNumber() === askPrice,
+        );
+        await program.methods
+          .cancelOrder(marketId, ask.id)
+          .accounts(otherUserAccounts)
+          .signers([other_user])
+          .rpc();
+      });
+    });
+
+    describe("Checking for Order Cancellation", async () => {
+      it("Cancelling a Buy Order - should return locked collateral", async () => {
+        // User places a buy order for YES tokens
+        let orderqty = 5;
+        let price = 0.6 * USDC_UNIT;
+        let orderAmount = orderqty * price;
+
+        // Get initial state
+        let userCollateralAccountBefore = await getAccount(
+          provider.connection,
+          userCollateralAccount,
+        );
+        let userStatsAccountBefore = await program.account.userStats.fetch(
+          userStatsAccount,
+        );
+        let orderbookBefore = await program.account.orderBook.fetch(
+          orderbookPda,
+        );
+        let initialBuyOrdersLength = orderbookBefore.yesBuyOrders.length;
+
+        // Place the buy order
+        await program.methods
+          .placeOrder(
+            marketId,
+            { buy: {} },
+            { yes: {} },
+            new BN(orderqty),
+            new BN(price),
+            new BN(max_iteration),
+            null,
+          )
+          .accounts({
+            market: marketPda,
+            orderbook: orderbookPda,
+            user: user.publicKey,
+            userOutcomeYes: userOutcomeYesAccount,
+            userOutcomeNo: userOutcomeNoAccount,
+            collateralVault,
+            userCollateral: userCollateralAccount,
+            userStatsAccount,
+            yesEscrow: yesEscrowPda,
+            noEscrow: noEscrowPda,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([user])
+          .rpc();
+
+        // Verify order was placed
+        let orderbookAfterPlace = await program.account.orderBook.fetch(
+          orderbookPda,
+        );
+        assert.equal(
+          orderbookAfterPlace.yesBuyOrders.length,
+          initialBuyOrdersLength + 1,
+          "Order should be added to orderbook",
+        );
+
+        let userStatsAfterPlace = await program.account.userStats.fetch(
+          userStatsAccount,
+        );
+        assert.equal(
+          Number(userStatsAfterPlace.lockedCollateral) -
+            Number(userStatsAccountBefore.lockedCollateral),
+          orderAmount,
+          "Collateral should be locked after placing order",
+        );
+
+        // Get the order ID (last order in the list)
+        let orderId =
+          orderbookAfterPlace.yesBuyOrders[
+            orderbookAfterPlace.yesBuyOrders.length - 1
+          ].id;
+
+        // Cancel the order
+        await program.methods
+          .cancelOrder(marketId, new BN(orderId))
+          .accounts({
+            market: marketPda,
+            orderbook: orderbookPda,
+            user: user.publicKey,
+            userOutcomeYes: userOutcomeYesAccount,
+            userOutcomeNo: userOutcomeNoAccount,
+            collateralVault,
+            userCollateral: userCollateralAccount,
+            userStatsAccount,
+            yesEscrow: yesEscrowPda,
+            noEscrow: noEscrowPda,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([user])
+          .rpc();
+
+        // Verify order was cancelled
+        let orderbookAfterCancel = await program.account.orderBook.fetch(
+          orderbookPda,
+        );
+        let userStatsAfterCancel = await program.account.userStats.fetch(
+          userStatsAccount,
+        );
+        let userCollateralAccountAfter = await getAccount(
+          provider.connection,
+          userCollateralAccount,
+        );
+
+        // Order should be removed from orderbook
+        assert.equal(
+          orderbookAfterCancel.yesBuyOrders.length,
+          initialBuyOrdersLength,
+          "Order should be removed from orderbook after cancellation",
         );
 
         // Locked collateral should be returned
@@ -1331,6 +1996,15 @@ describe("prediction_market", () => {
           "User's collateral balance should be restored",
         );
 
+        // The cancelled order stays queryable from the closed orders ring buffer
+        const closed = await program.methods
+          .getClosedOrder(marketId, new BN(orderId))
+          .accounts({ orderbook: orderbookPda })
+          .view();
+        assert.equal(closed.order.id.toString(), orderId.toString());
+        assert.equal(closed.order.filledquantity.toNumber(), 0);
+        assert.deepEqual(closed.reason, { cancelled: {} });
+
         console.log("Buy order cancelled successfully");
       });
 
@@ -1361,6 +2035,7 @@ describe("prediction_market", () => {
             new BN(orderqty),
             new BN(price),
             new BN(max_iteration),
+            null,
           )
           .accounts({
             market: marketPda,
@@ -1488,6 +2163,7 @@ describe("prediction_market", () => {
             new BN(orderqty),
             new BN(price),
             new BN(max_iteration),
+            null,
           )
           .accounts({
             market: marketPda,
@@ -1599,6 +2275,7 @@ describe("prediction_market", () => {
             new BN(buyOrderQty),
             new BN(buyPrice),
             new BN(max_iteration),
+            null,
           )
           .accounts({
             market: marketPda,
@@ -1634,6 +2311,7 @@ describe("prediction_market", () => {
             new BN(10), // Only 10 tokens, partially filling the 15 token buy order
             new BN(0.5 * USDC_UNIT), // Price so it matches
             new BN(max_iteration),
+            null,
           )
           .accounts({
             market: marketPda,
@@ -1717,6 +2395,7 @@ describe("prediction_market", () => {
             new BN(orderqty),
             new BN(price),
             new BN(max_iteration),
+            null,
           )
           .accounts({
             market: marketPda,
@@ -1791,36 +2470,164 @@ describe("prediction_market", () => {
           .rpc();
       });
 
-      it("Should fail - Cancelling non-existent order (invalid order_id)", async () => {
-        // Use a very large order ID that doesn't exist
-        let invalidOrderId = 999999;
+      it("Transfers a resting order to another wallet with its lock", async () => {
+        const orderqty = 5;
+        const price = 0.6 * USDC_UNIT;
+        const lock = (orderqty * price) / 1_000_000;
+
+        await program.methods
+          .placeOrder(
+            marketId,
+            { buy: {} },
+            { yes: {} },
+            new BN(orderqty),
+            new BN(price),
+            new BN(max_iteration),
+            null,
+          )
+          .accounts({
+            market: marketPda,
+            orderbook: orderbookPda,
+            user: user.publicKey,
+            userOutcomeYes: userOutcomeYesAccount,
+            userOutcomeNo: userOutcomeNoAccount,
+            collateralVault,
+            userCollateral: userCollateralAccount,
+            userStatsAccount,
+            yesEscrow: yesEscrowPda,
+            noEscrow: noEscrowPda,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([user])
+          .rpc();
+
+        let book = await program.account.orderBook.fetch(orderbookPda);
+        // The order just placed: the user's one with the highest id
+        const order = book.yesBuyOrders
+          .filter((o) => o.userKey.equals(user.publicKey))
+          .reduce((a, b) => (a.id.gt(b.id) ? a : b));
+        const position = book.yesBuyOrders.indexOf(order);
+        const userLockBefore = Number(
+          (await program.account.userStats.fetch(userStatsAccount))
+            .lockedCollateral,
+        );
+        const otherLockBefore = Number(
+          (await program.account.userStats.fetch(other_userStatsAccount))
+            .lockedCollateral,
+        );
+
+        await program.methods
+          .transferOrder(marketId, order.id, other_user.publicKey)
+          .accounts({
+            user: user.publicKey,
+            market: marketPda,
+            orderbook: orderbookPda,
+            userStatsAccount,
+            newOwnerStats: other_userStatsAccount,
+          })
+          .signers([user])
+          .rpc();
+
+        // Same order, same queue position, new owner
+        book = await program.account.orderBook.fetch(orderbookPda);
+        assert.ok(book.yesBuyOrders[position].id.eq(order.id));
+        assert.isTrue(
+          book.yesBuyOrders[position].userKey.equals(other_user.publicKey),
+        );
+        const userStats = await program.account.userStats.fetch(
+          userStatsAccount,
+        );
+        const otherStats = await program.account.userStats.fetch(
+          other_userStatsAccount,
+        );
+        assert.equal(
+          userLockBefore - Number(userStats.lockedCollateral),
+          lock,
+        );
+        assert.equal(
+          Number(otherStats.lockedCollateral) - otherLockBefore,
+          lock,
+        );
 
+        // The previous owner can't move it any more
+        const stranger = Keypair.generate().publicKey;
+        const [strangerStats] = PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("user_stats"),
+            new BN(marketId).toArrayLike(Buffer, "le", 4),
+            stranger.toBuffer(),
+          ],
+          program.programId,
+        );
         try {
           await program.methods
-            .cancelOrder(marketId, new BN(invalidOrderId))
+            .transferOrder(marketId, order.id, stranger)
             .accounts({
+              user: user.publicKey,
               market: marketPda,
               orderbook: orderbookPda,
-              user: user.publicKey,
-              userOutcomeYes: userOutcomeYesAccount,
-              userOutcomeNo: userOutcomeNoAccount,
-              collateralVault,
-              userCollateral: userCollateralAccount,
               userStatsAccount,
-              yesEscrow: yesEscrowPda,
-              noEscrow: noEscrowPda,
-              systemProgram: SystemProgram.programId,
-              tokenProgram: TOKEN_PROGRAM_ID,
+              newOwnerStats: strangerStats,
             })
             .signers([user])
             .rpc();
-
-          assert.fail("Should not allow cancelling non-existent order");
+          assert.fail("Only the current owner can transfer an order");
         } catch (err) {
-          expect(err.toString()).to.include("OrdernotFound");
-          console.log("Correctly prevented cancellation of non-existent order");
+          expect(err.toString()).to.include("NotAuthorized");
         }
-      });
+
+        // The new owner cancels it and gets the collateral
+        await program.methods
+          .cancelOrder(marketId, order.id)
+          .accounts({
+            market: marketPda,
+            orderbook: orderbookPda,
+            user: other_user.publicKey,
+            userOutcomeYes: other_userOutcomeYesAccount,
+            userOutcomeNo: other_userOutcomeNoAccount,
+            collateralVault,
+            userCollateral: other_userCollateralAccount,
+            userStatsAccount: other_userStatsAccount,
+            yesEscrow: yesEscrowPda,
+            noEscrow: noEscrowPda,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([other_user])
+          .rpc();
+      });
+
+      it("Should fail - Cancelling non-existent order (invalid order_id)", async () => {
+        // Use a very large order ID that doesn't exist
+        let invalidOrderId = 999999;
+
+        try {
+          await program.methods
+            .cancelOrder(marketId, new BN(invalidOrderId))
+            .accounts({
+              market: marketPda,
+              orderbook: orderbookPda,
+              user: user.publicKey,
+              userOutcomeYes: userOutcomeYesAccount,
+              userOutcomeNo: userOutcomeNoAccount,
+              collateralVault,
+              userCollateral: userCollateralAccount,
+              userStatsAccount,
+              yesEscrow: yesEscrowPda,
+              noEscrow: noEscrowPda,
+              systemProgram: SystemProgram.programId,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .signers([user])
+            .rpc();
+
+          assert.fail("Should not allow cancelling non-existent order");
+        } catch (err) {
+          expect(err.toString()).to.include("OrdernotFound");
+          console.log("Correctly prevented cancellation of non-existent order");
+        }
+      });
     });
 
     describe("Merge Tokens", () => {
@@ -1909,6 +2716,19 @@ describe("prediction_market", () => {
           "Market totalCollateralLocked should decrease by mergeAmount",
         );
 
+        // The reentrancy guard is released once the instruction ends
+        assert.isFalse(marketAfter.inProgress);
+
+        // Burned pairs come off the issuance counters
+        assert.equal(
+          Number(marketBefore.yesMinted) - Number(marketAfter.yesMinted),
+          mergeAmount,
+        );
+        assert.equal(
+          Number(marketBefore.noMinted) - Number(marketAfter.noMinted),
+          mergeAmount,
+        );
+
         console.log("Merge successful, collateral returned to user");
       });
 
@@ -1999,6 +2819,7 @@ describe("prediction_market", () => {
           .accounts({
             market: marketPda,
             user: user.publicKey,
+            claimer: user.publicKey,
             userStats: userStatsAccount,
             collateralMint,
             outcomeYesMint,
@@ -2093,6 +2914,7 @@ describe("prediction_market", () => {
           .accounts({
             market: marketPda,
             user: other_user.publicKey,
+            claimer: other_user.publicKey,
             userStats: other_userStatsAccount,
             collateralMint,
             outcomeYesMint,
@@ -2166,6 +2988,7 @@ describe("prediction_market", () => {
             .accounts({
               market: marketPda,
               user: user.publicKey,
+              claimer: user.publicKey,
               userStats: userStatsAccount,
               collateralMint,
               outcomeYesMint,
@@ -2186,451 +3009,2876 @@ describe("prediction_market", () => {
           expect(err.toString()).to.include("NothingToClaim");
         }
       });
-    });
-  });
 
-  describe("Settlement & Lifecycle", () => {
-    let marketId2 = 2;
-    let marketPda2: PublicKey;
-    let collateralVault2: PublicKey;
-    let outcomeYesMint2: PublicKey;
-    let outcomeNoMint2: PublicKey;
-    let orderbookPda2: PublicKey;
-    let yesEscrowPda2: PublicKey;
-    let noEscrowPda2: PublicKey;
-    let userStatsAccount2: PublicKey;
+      it("Rejects a claimer who is not the user's claim delegate", async () => {
+        try {
+          await program.methods
+            .claimFunds(marketId)
+            .accounts({
+              market: marketPda,
+              user: user.publicKey,
+              claimer: other_user.publicKey,
+              userStats: userStatsAccount,
+              collateralMint,
+              outcomeYesMint,
+              outcomeNoMint,
+              userCollateral: userCollateralAccount,
+              collateralVault,
+              userOutcomeYes: userOutcomeYesAccount,
+              userOutcomeNo: userOutcomeNoAccount,
+              yesEscrow: yesEscrowPda,
+              noEscrow: noEscrowPda,
+              associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+              tokenProgram: TOKEN_PROGRAM_ID,
+              systemProgram: SystemProgram.programId,
+            })
+            .signers([other_user])
+            .rpc();
+          assert.fail("Should not allow claiming for another user");
+        } catch (err) {
+          expect(err.toString()).to.include("UnauthorizedClaimer");
+        }
+      });
 
-    let userOutcomeYesAccount2: PublicKey;
-    let userOutcomeNoAccount2: PublicKey;
+      it("Lets the claim delegate sign claim_funds for the user", async () => {
+        await program.methods
+          .setClaimDelegate(marketId, other_user.publicKey)
+          .accounts({ user: user.publicKey, userStats: userStatsAccount })
+          .signers([user])
+          .rpc();
 
-    before(async () => {
-      const marketId2BN = new BN(2);
-      const marketId2LE = marketId2BN.toArrayLike(Buffer, "le", 4);
+        // Past the delegate check, the user simply has nothing left to claim
+        try {
+          await program.methods
+            .claimFunds(marketId)
+            .accounts({
+              market: marketPda,
+              user: user.publicKey,
+              claimer: other_user.publicKey,
+              userStats: userStatsAccount,
+              collateralMint,
+              outcomeYesMint,
+              outcomeNoMint,
+              userCollateral: userCollateralAccount,
+              collateralVault,
+              userOutcomeYes: userOutcomeYesAccount,
+              userOutcomeNo: userOutcomeNoAccount,
+              yesEscrow: yesEscrowPda,
+              noEscrow: noEscrowPda,
+              associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+              tokenProgram: TOKEN_PROGRAM_ID,
+              systemProgram: SystemProgram.programId,
+            })
+            .signers([other_user])
+            .rpc();
+          assert.fail("Should fail with NothingToClaim");
+        } catch (err) {
+          expect(err.toString()).to.include("NothingToClaim");
+        }
 
-      [marketPda2] = PublicKey.findProgramAddressSync(
-        [Buffer.from("market"), marketId2LE],
-        program.programId,
-      );
-      [collateralVault2] = PublicKey.findProgramAddressSync(
-        [Buffer.from("vault"), marketId2LE],
-        program.programId,
-      );
-      [outcomeYesMint2] = PublicKey.findProgramAddressSync(
-        [Buffer.from("outcome_a"), marketId2LE],
-        program.programId,
-      );
-      [outcomeNoMint2] = PublicKey.findProgramAddressSync(
-        [Buffer.from("outcome_b"), marketId2LE],
-        program.programId,
-      );
-      [orderbookPda2] = PublicKey.findProgramAddressSync(
-        [Buffer.from("orderbook"), marketId2LE],
-        program.programId,
-      );
-      [yesEscrowPda2] = PublicKey.findProgramAddressSync(
-        [Buffer.from("escrow"), marketId2LE, outcomeYesMint2.toBuffer()],
-        program.programId,
-      );
-      [noEscrowPda2] = PublicKey.findProgramAddressSync(
-        [Buffer.from("escrow"), marketId2LE, outcomeNoMint2.toBuffer()],
-        program.programId,
-      );
-      [userStatsAccount2] = PublicKey.findProgramAddressSync(
-        [Buffer.from("user_stats"), marketId2LE, user.publicKey.toBuffer()],
-        program.programId,
+        await program.methods
+          .setClaimDelegate(marketId, null)
+          .accounts({ user: user.publicKey, userStats: userStatsAccount })
+          .signers([user])
+          .rpc();
+      });
+    });
+  });
+
+  describe("Collateral Rounding", () => {
+    const roundingMarketId = 3;
+    const roundingMarketIdLE = new BN(roundingMarketId).toArrayLike(
+      Buffer,
+      "le",
+      4,
+    );
+    const pda = (seeds: Buffer[]) =>
+      PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+
+    const market3 = pda([Buffer.from("market"), roundingMarketIdLE]);
+    const vault3 = pda([Buffer.from("vault"), roundingMarketIdLE]);
+    const yesMint3 = pda([Buffer.from("outcome_a"), roundingMarketIdLE]);
+    const noMint3 = pda([Buffer.from("outcome_b"), roundingMarketIdLE]);
+    const orderbook3 = pda([Buffer.from("orderbook"), roundingMarketIdLE]);
+    const yesEscrow3 = pda([
+      Buffer.from("escrow"),
+      roundingMarketIdLE,
+      yesMint3.toBuffer(),
+    ]);
+    const noEscrow3 = pda([
+      Buffer.from("escrow"),
+      roundingMarketIdLE,
+      noMint3.toBuffer(),
+    ]);
+    const statsOf = (owner: PublicKey) =>
+      pda([Buffer.from("user_stats"), roundingMarketIdLE, owner.toBuffer()]);
+
+    type Trader = {
+      kp: Keypair;
+      collateral: PublicKey;
+      yes: PublicKey;
+      no: PublicKey;
+      stats: PublicKey;
+    };
+    let buyer: Trader;
+    let seller: Trader;
+
+    const newTrader = async (collateralAmount: number): Promise<Trader> => {
+      const kp = Keypair.generate();
+      const sig = await provider.connection.requestAirdrop(
+        kp.publicKey,
+        LAMPORTS_PER_SOL * 2,
       );
+      await provider.connection.confirmTransaction(sig);
 
-      // Deadline of 2 seconds
-      const shortDeadline = new BN(Math.floor(Date.now() / 1000) + 2);
+      const collateral = (
+        await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          authority.payer,
+          collateralMint,
+          kp.publicKey,
+        )
+      ).address;
+      await mintTo(
+        provider.connection,
+        authority.payer,
+        collateralMint,
+        collateral,
+        authority.publicKey,
+        collateralAmount,
+      );
+      const yes = (
+        await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          authority.payer,
+          yesMint3,
+          kp.publicKey,
+        )
+      ).address;
+      const no = (
+        await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          authority.payer,
+          noMint3,
+          kp.publicKey,
+        )
+      ).address;
+      return { kp, collateral, yes, no, stats: statsOf(kp.publicKey) };
+    };
+
+    const placeLimit = async (
+      trader: Trader,
+      side: object,
+      quantity: number,
+      price: number,
+      counterparties: Trader[] = [],
+      iterations: number = max_iteration,
+      stakeAccount: PublicKey | null = null,
+    ) => {
+      await program.methods
+        .placeOrder(
+          roundingMarketId,
+          side as any,
+          { yes: {} },
+          new BN(quantity),
+          new BN(price),
+          new BN(iterations),
+          null,
+        )
+        .accounts({
+          market: market3,
+          orderbook: orderbook3,
+          user: trader.kp.publicKey,
+          userOutcomeYes: trader.yes,
+          userOutcomeNo: trader.no,
+          collateralVault: vault3,
+          userCollateral: trader.collateral,
+          userStatsAccount: trader.stats,
+          yesEscrow: yesEscrow3,
+          noEscrow: noEscrow3,
+          incentiveVault: null,
+          stakeAccount,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(
+          counterparties.map((c) => ({
+            pubkey: c.stats,
+            isSigner: false,
+            isWritable: true,
+          })),
+        )
+        .signers([trader.kp])
+        .rpc();
+    };
 
+    before(async () => {
       await program.methods
-        .initializeMarket(marketId2, shortDeadline, "")
+        .initializeMarket(
+          roundingMarketId,
+          new BN(Math.floor(Date.now() / 1000) + 86400),
+          "",
+          0,
+          false,
+          "",
+          "",
+          { binary: {} },
+          null,
+          null,
+          slugOf(roundingMarketId),
+          false,
+          new BN(0),
+        )
         .accounts({
-          market: marketPda2,
+          market: market3,
           authority: authority.publicKey,
           collateralMint: collateralMint,
-          collateralVault: collateralVault2,
-          outcomeYesMint: outcomeYesMint2,
-          outcomeNoMint: outcomeNoMint2,
-          yesEscrow: yesEscrowPda2,
-          noEscrow: noEscrowPda2,
-          orderbook: orderbookPda2,
+          collateralVault: vault3,
+          outcomeYesMint: yesMint3,
+          outcomeNoMint: noMint3,
+          yesEscrow: yesEscrow3,
+          noEscrow: noEscrow3,
+          orderbook: orderbook3,
+          marketRegistry: registryPda(roundingMarketId),
+          marketSlug: slugPda(slugOf(roundingMarketId)),
           systemProgram: SystemProgram.programId,
           associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           tokenProgram: TOKEN_PROGRAM_ID,
         })
         .rpc();
 
-      let splitAmount2 = 50;
-
-      const userOutcomeYesInfo2 = await getOrCreateAssociatedTokenAccount(
-        provider.connection,
-        authority.payer,
-        outcomeYesMint2,
-        user.publicKey,
-      );
-      userOutcomeYesAccount2 = userOutcomeYesInfo2.address;
-
-      const userOutcomeNoInfo2 = await getOrCreateAssociatedTokenAccount(
-        provider.connection,
-        authority.payer,
-        outcomeNoMint2,
-        user.publicKey,
-      );
-      userOutcomeNoAccount2 = userOutcomeNoInfo2.address;
+      buyer = await newTrader(10 * USDC_UNIT);
+      seller = await newTrader(10 * USDC_UNIT);
 
       await program.methods
-        .splitTokens(marketId2, new BN(splitAmount2))
+        .splitTokens(roundingMarketId, new BN(20_000))
         .accounts({
-          market: marketPda2,
-          user: user.publicKey,
-          userCollateral: userCollateralAccount,
-          collateralVault: collateralVault2,
-          outcomeYesMint: outcomeYesMint2,
-          outcomeNoMint: outcomeNoMint2,
-          userOutcomeYes: userOutcomeYesAccount2,
-          userOutcomeNo: userOutcomeNoAccount2,
+          market: market3,
+          user: seller.kp.publicKey,
+          userCollateral: seller.collateral,
+          collateralVault: vault3,
+          outcomeYesMint: yesMint3,
+          outcomeNoMint: noMint3,
+          userOutcomeYes: seller.yes,
+          userOutcomeNo: seller.no,
           tokenProgram: TOKEN_PROGRAM_ID,
         })
-        .signers([user])
+        .signers([seller.kp])
         .rpc();
-
-      console.log(
-        "Market 2 initialised with short deadline & user funded with YES/NO tokens",
-      );
     });
 
-    describe("Update Metadata", () => {
-      it("Updating the market metadata URL", async () => {
-        const newUrl = "https://new_url.com";
+    it("Partial fills at a non-divisible price neither create nor strand collateral", async () => {
+      // 2000 base units @ 0.001999 locks floor(3.998) = 3
+      // each 1000-unit fill pays the seller floor(1.999) = 1
+      const price = 1_999;
+      await placeLimit(buyer, { buy: {} }, 2_000, price);
+
+      let buyerStats = await program.account.userStats.fetch(buyer.stats);
+      assert.equal(Number(buyerStats.lockedCollateral), 3);
+
+      await placeLimit(seller, { sell: {} }, 1_000, price, [buyer]);
+      await placeLimit(seller, { sell: {} }, 1_000, price, [buyer]);
+
+      buyerStats = await program.account.userStats.fetch(buyer.stats);
+      const sellerStats = await program.account.userStats.fetch(seller.stats);
+
+      // Whole lock released, the unit the seller couldn't be paid goes back to the buyer
+      assert.equal(Number(buyerStats.lockedCollateral), 0);
+      assert.equal(Number(buyerStats.claimableCollateral), 1);
+      assert.equal(Number(buyerStats.claimableYes), 2_000);
+      assert.equal(Number(sellerStats.claimableCollateral), 2);
+      assert.equal(Number(sellerStats.lockedYes), 0);
+
+      // Every unit in the vault is accounted for: split collateral + buyer's lock
+      const market = await program.account.market.fetch(market3);
+      const vault = await getAccount(provider.connection, vault3);
+      assert.equal(Number(market.totalCollateralLocked), 20_000 + 3);
+      assert.equal(Number(vault.amount), 20_000 + 3);
+    });
 
+    for (const mode of ["favorProtocol", "favorMaker", "favorTaker"]) {
+      it(`Conserves collateral across partial fills with rounding mode ${mode}`, async () => {
         await program.methods
-          .updateMetadata(marketId, newUrl)
-          .accounts({
-            market: marketPda,
-            authority: authority.publicKey,
-          })
+          .setRoundingMode(roundingMarketId, { [mode]: {} } as any)
+          .accounts({ authority: authority.publicKey, market: market3 })
           .rpc();
 
-        const market = await program.account.market.fetch(marketPda);
-        assert.equal(
-          market.metaDataUrl,
-          newUrl,
-          "Market metaDataUrl should be updated",
-        );
+        const price = 1_999;
+        await placeLimit(buyer, { buy: {} }, 2_000, price);
+        await placeLimit(seller, { sell: {} }, 1_000, price, [buyer]);
+        await placeLimit(seller, { sell: {} }, 1_000, price, [buyer]);
 
-        console.log("Metadata updated to:", newUrl);
-      });
-    });
+        // Buyer's lock is fully released whichever side got the rounding unit
+        const buyerStats = await program.account.userStats.fetch(buyer.stats);
+        assert.equal(Number(buyerStats.lockedCollateral), 0);
 
-    describe("Set Winner", () => {
-      it("What if we try to set winner before the deadline, should fail with SettlementDeadlineNotReached", async () => {
-        try {
-          await program.methods
-            .setWinner(marketId, { outcomeA: {} })
-            .accounts({
-              market: marketPda,
-              authority: authority.publicKey,
-              outcomeYesMint,
-              outcomeNoMint,
-              associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-              tokenProgram: TOKEN_PROGRAM_ID,
-            })
-            .rpc();
-        } catch (err) {
-          expect(err.toString()).to.include("SettlementDeadlineNotReached");
-        }
+        const market = await program.account.market.fetch(market3);
+        const vault = await getAccount(provider.connection, vault3);
+        assert.equal(Number(market.totalCollateralLocked), Number(vault.amount));
       });
+    }
 
-      it("Setting YES as the winner on market_id=2 after deadline expires", async () => {
-        // Waiting for 3 sec
-        await new Promise((s) => setTimeout(s, 3000));
+    it("An exact-price taker buy releases its whole lock with no refund", async () => {
+      // 1000 base units @ 0.5 is exactly 500, so there's no price improvement or rounding
+      const price = 500_000;
+      const value = 500;
+      await placeLimit(seller, { sell: {} }, 1_000, price);
 
-        await program.methods
-          .setWinner(marketId2, { outcomeA: {} })
-          .accounts({
-            market: marketPda2,
-            authority: authority.publicKey,
-            outcomeYesMint: outcomeYesMint2,
-            outcomeNoMint: outcomeNoMint2,
-            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-            tokenProgram: TOKEN_PROGRAM_ID,
-          })
-          .rpc();
+      const buyerBefore = await program.account.userStats.fetch(buyer.stats);
+      const sellerBefore = await program.account.userStats.fetch(seller.stats);
+      const marketBefore = await program.account.market.fetch(market3);
 
-        const market2 = await program.account.market.fetch(marketPda2);
+      await placeLimit(buyer, { buy: {} }, 1_000, price, [seller]);
 
-        assert.isTrue(market2.isSettled, "Market should be marked as settled");
-        assert.deepEqual(
-          market2.winningOutcome,
-          { outcomeA: {} },
-          "Winning outcome should be YES",
-        );
+      const buyerAfter = await program.account.userStats.fetch(buyer.stats);
+      const sellerAfter = await program.account.userStats.fetch(seller.stats);
+      const marketAfter = await program.account.market.fetch(market3);
+      const vault = await getAccount(provider.connection, vault3);
 
-        console.log("Winner set: YES");
+      // The lock taken for the buy is released in full, nothing is left over as a refund
+      assert.equal(Number(buyerAfter.lockedCollateral), 0);
+      assert.equal(
+        Number(buyerAfter.claimableCollateral),
+        Number(buyerBefore.claimableCollateral),
+      );
+      assert.equal(
+        Number(buyerAfter.claimableYes) - Number(buyerBefore.claimableYes),
+        1_000,
+      );
+      assert.equal(
+        Number(sellerAfter.claimableCollateral) -
+          Number(sellerBefore.claimableCollateral),
+        value,
+      );
 
-        // Verify collateral is still in the vault (50 from splitTokens)
-        // This is important — we'll use this to test CollateralNotFullyClaimed below
-        const lockedAfterSettle = Number(market2.totalCollateralLocked);
-        assert.isAbove(
-          lockedAfterSettle,
-          0,
-          "Vault should still have collateral after setWinner",
-        );
-      });
+      // Tracked collateral grows by exactly the fill value and still matches the vault
+      assert.equal(
+        Number(marketAfter.totalCollateralLocked) -
+          Number(marketBefore.totalCollateralLocked),
+        value,
+      );
+      assert.equal(
+        Number(marketAfter.totalCollateralLocked),
+        Number(vault.amount),
+      );
+    });
 
-      it("What if we try to set winner again on an already settled market, should fail", async () => {
-        // require!(!self.market.is_settled, MarketAlreadySettled)
+    it("Credits a maker filled at three price levels with one net update", async () => {
+      await program.methods
+        .splitTokens(roundingMarketId, new BN(3_000))
+        .accounts({
+          market: market3,
+          user: seller.kp.publicKey,
+          userCollateral: seller.collateral,
+          collateralVault: vault3,
+          outcomeYesMint: yesMint3,
+          outcomeNoMint: noMint3,
+          userOutcomeYes: seller.yes,
+          userOutcomeNo: seller.no,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([seller.kp])
+        .rpc();
 
-        try {
-          await program.methods
-            .setWinner(marketId2, { outcomeB: {} })
-            .accounts({
-              market: marketPda2,
-              authority: authority.publicKey,
-              outcomeYesMint: outcomeYesMint2,
-              outcomeNoMint: outcomeNoMint2,
-              associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-              tokenProgram: TOKEN_PROGRAM_ID,
-            })
-            .rpc();
-        } catch (err) {
-          expect(err.toString()).to.include("MarketAlreadySettled");
-        }
-      });
+      const prices = [500_000, 510_000, 520_000];
+      for (const price of prices) {
+        await placeLimit(seller, { sell: {} }, 1_000, price);
+      }
 
-      it("What if we try to close the settled market while collateral is still in the vault, should fail with CollateralNotFullyClaimed", async () => {
-        // Right after setWinner, the 50 collateral from splitTokens is still in the vault
-        // closeMarket requires total_collateral_locked == 0
-        // So closing here (before anyone calls claimRewards) should fail
-        //
-        // This is the exact ordering users must follow:
-        //   setWinner -> all users claimRewards -> authority closeMarket
+      const buyerBefore = await program.account.userStats.fetch(buyer.stats);
+      const sellerBefore = await program.account.userStats.fetch(seller.stats);
 
-        let market2 = await program.account.market.fetch(marketPda2);
-        console.log(
-          "totalCollateralLocked right after setWinner:",
-          Number(market2.totalCollateralLocked),
-        );
-        assert.isAbove(
-          Number(market2.totalCollateralLocked),
-          0,
-          "Precondition: vault must still have collateral right after setWinner",
-        );
+      // One taker buy sweeps all three asks, the seller's stats are passed once
+      await placeLimit(buyer, { buy: {} }, 3_000, 520_000, [seller]);
 
-        try {
-          await program.methods
-            .closeMarket(marketId2)
-            .accounts({
-              market: marketPda2,
-              authority: authority.publicKey,
-              orderbook: orderbookPda2,
-            })
-            .rpc();
-        } catch (err) {
-          expect(err.toString()).to.include("CollateralNotFullyClaimed");
-        }
-      });
+      const buyerAfter = await program.account.userStats.fetch(buyer.stats);
+      const sellerAfter = await program.account.userStats.fetch(seller.stats);
+
+      // The seller's totals are the sum of the three fills: 500 + 510 + 520
+      assert.equal(
+        Number(sellerAfter.claimableCollateral) -
+          Number(sellerBefore.claimableCollateral),
+        1_530,
+      );
+      assert.equal(
+        Number(sellerBefore.lockedYes) - Number(sellerAfter.lockedYes),
+        3_000,
+      );
+      assert.equal(
+        Number(buyerAfter.claimableYes) - Number(buyerBefore.claimableYes),
+        3_000,
+      );
+      assert.equal(Number(buyerAfter.lockedCollateral), 0);
+
+      const market = await program.account.market.fetch(market3);
+      const vault = await getAccount(provider.connection, vault3);
+      assert.equal(Number(market.totalCollateralLocked), Number(vault.amount));
     });
 
-    describe("Claim Rewards", () => {
-      it("What if we try to claimRewards before market is settled, should fail", async () => {
-        // require!(self.market.is_settled, MarketNotSettled)
-        // market_id=1 is not settled yet — good test target
+    it("Records the last YES price at the resting order's price", async () => {
+      const maker = await newTrader(USDC_UNIT);
+      const taker = await newTrader(USDC_UNIT);
+      await program.methods
+        .splitTokens(roundingMarketId, new BN(1_000))
+        .accounts({
+          market: market3,
+          user: maker.kp.publicKey,
+          userCollateral: maker.collateral,
+          collateralVault: vault3,
+          outcomeYesMint: yesMint3,
+          outcomeNoMint: noMint3,
+          userOutcomeYes: maker.yes,
+          userOutcomeNo: maker.no,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([maker.kp])
+        .rpc();
 
-        try {
-          await program.methods
-            .claimRewards(marketId)
-            .accounts({
-              market: marketPda,
-              user: user.publicKey,
-              userStats: userStatsAccount,
+      const before = await program.methods
+        .marketStatus(roundingMarketId)
+        .accounts({ market: market3, orderbook: orderbook3 })
+        .view();
+
+      await placeLimit(maker, { sell: {} }, 1_000, 480_000);
+      // The taker bids higher but trades at the ask
+      await placeLimit(taker, { buy: {} }, 1_000, 600_000, [maker]);
+
+      const status = await program.methods
+        .marketStatus(roundingMarketId)
+        .accounts({ market: market3, orderbook: orderbook3 })
+        .view();
+      assert.equal(Number(status.lastYesPrice), 480_000);
+      assert.isTrue(status.lastYesPriceAt.gte(before.lastYesPriceAt));
+      assert.isTrue(status.lastYesPriceAt.gtn(0));
+      // Only YES traded
+      assert.equal(Number(status.lastNoPrice), Number(before.lastNoPrice));
+    });
+
+    it("Reports implied probabilities from the best bid and ask", async () => {
+      const impliedProbability = () =>
+        program.methods
+          .impliedProbability(roundingMarketId)
+          .accounts({
+            market: market3,
+            orderbook: orderbook3,
+            collateralMint,
+          })
+          .view();
+
+      let probability = await impliedProbability();
+      assert.isFalse(probability.yesQuoted);
+      assert.equal(probability.yesProbBps, 0);
+
+      // A lone ask at 0.6 is the YES estimate, NO has no book
+      await placeLimit(seller, { sell: {} }, 1_000, 600_000);
+      probability = await impliedProbability();
+      assert.isTrue(probability.yesQuoted);
+      assert.equal(probability.yesProbBps, 6_000);
+      assert.isFalse(probability.noQuoted);
+      assert.equal(probability.noProbBps, 0);
+
+      // With a bid at 0.4 as well it's the mid
+      await placeLimit(buyer, { buy: {} }, 1_000, 400_000);
+      probability = await impliedProbability();
+      assert.equal(probability.yesProbBps, 5_000);
+
+      // Clear both orders for the tests after this one
+      await placeLimit(buyer, { buy: {} }, 1_000, 600_000, [seller]);
+      await placeLimit(seller, { sell: {} }, 1_000, 400_000, [buyer]);
+      probability = await impliedProbability();
+      assert.isFalse(probability.yesQuoted);
+    });
+
+    it("Reports open interest from the unfilled part of resting orders", async () => {
+      const openInterest = () =>
+        program.methods
+          .openInterest(roundingMarketId)
+          .accounts({ market: market3, orderbook: orderbook3 })
+          .view();
+
+      let interest = await openInterest();
+      assert.equal(Number(interest.yesLockedInBook), 0);
+      assert.equal(Number(interest.collateralLockedInBook), 0);
+
+      // 2000 @ 0.3 locks 600, an ask of 1500 is partly taken by a 500 buy
+      await placeLimit(buyer, { buy: {} }, 2_000, 300_000);
+      await placeLimit(seller, { sell: {} }, 1_500, 700_000);
+      await placeLimit(buyer, { buy: {} }, 500, 700_000, [seller]);
+
+      interest = await openInterest();
+      assert.equal(Number(interest.yesLockedInBook), 1_000);
+      assert.equal(Number(interest.noLockedInBook), 0);
+      assert.equal(Number(interest.collateralLockedInBook), 600);
+
+      // Clear both orders for the tests after this one
+      await placeLimit(buyer, { buy: {} }, 1_000, 700_000, [seller]);
+      await placeLimit(seller, { sell: {} }, 2_000, 300_000, [buyer]);
+      interest = await openInterest();
+      assert.equal(Number(interest.yesLockedInBook), 0);
+      assert.equal(Number(interest.collateralLockedInBook), 0);
+    });
+
+    it("Cancels an order and claims the fills' proceeds in one transaction", async () => {
+      const cancelAndClaim = async (orderId: BN) =>
+        program.methods
+          .cancelAndClaim(roundingMarketId, orderId)
+          .accounts({
+            cancel: {
+              user: seller.kp.publicKey,
+              market: market3,
+              orderbook: orderbook3,
+              collateralVault: vault3,
+              userCollateral: seller.collateral,
+              userStatsAccount: seller.stats,
+              userOutcomeYes: seller.yes,
+              userOutcomeNo: seller.no,
+              yesEscrow: yesEscrow3,
+              noEscrow: noEscrow3,
+              outcomeMint: null,
+              incentiveVault: null,
+              systemProgram: SystemProgram.programId,
+              associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            },
+            claim: {
+              claimer: seller.kp.publicKey,
+              user: seller.kp.publicKey,
+              market: market3,
+              userStats: seller.stats,
               collateralMint,
-              userCollateral: userCollateralAccount,
-              collateralVault,
-              outcomeYesMint,
-              outcomeNoMint,
-              userOutcomeYes: userOutcomeYesAccount,
-              userOutcomeNo: userOutcomeNoAccount,
+              outcomeYesMint: yesMint3,
+              outcomeNoMint: noMint3,
+              userCollateral: seller.collateral,
+              collateralVault: vault3,
+              userOutcomeYes: seller.yes,
+              userOutcomeNo: seller.no,
+              yesEscrow: yesEscrow3,
+              noEscrow: noEscrow3,
               associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
               tokenProgram: TOKEN_PROGRAM_ID,
               systemProgram: SystemProgram.programId,
-            })
-            .signers([user])
-            .rpc();
-        } catch (err) {
-          expect(err.toString()).to.include("MarketNotSettled");
-        }
-      });
+            },
+          } as any)
+          .signers([seller.kp])
+          .rpc();
+      const sellerAsk = async (price: number) =>
+        (await program.account.orderBook.fetch(orderbook3)).yesSellOrders.find(
+          (o) =>
+            o.userKey.equals(seller.kp.publicKey) &&
+            o.price.toNumber() === price,
+        );
 
-      it("Winner (YES holder) claiming collateral rewards by burning YES tokens", async () => {
-        // YES wins on market_id=2 — user holds YES tokens from splitTokens in before()
-        // claimRewards burns all YES tokens and returns 1:1 collateral
-        // totalCollateralLocked should decrease by the amount burned
+      // 1000 @ 0.45, 400 of it bought for 180
+      const price = 450_000;
+      await placeLimit(seller, { sell: {} }, 1_000, price);
+      await placeLimit(buyer, { buy: {} }, 400, price, [seller]);
 
-        let userYesAccountBefore = await getAccount(
-          provider.connection,
-          userOutcomeYesAccount2,
-        );
-        let userCollateralAccountBefore = await getAccount(
-          provider.connection,
-          userCollateralAccount,
-        );
-        let market2Before = await program.account.market.fetch(marketPda2);
+      const statsBefore = await program.account.userStats.fetch(seller.stats);
+      const collateralBefore = await getAccount(
+        provider.connection,
+        seller.collateral,
+      );
+      const yesBefore = await getAccount(provider.connection, seller.yes);
 
-        let yesToBurn = Number(userYesAccountBefore.amount);
-        console.log("YES tokens to burn for reward:", yesToBurn);
+      await cancelAndClaim((await sellerAsk(price)).id);
 
-        await program.methods
-          .claimRewards(marketId2)
+      const statsAfter = await program.account.userStats.fetch(seller.stats);
+      const collateralAfter = await getAccount(
+        provider.connection,
+        seller.collateral,
+      );
+      const yesAfter = await getAccount(provider.connection, seller.yes);
+
+      // The unfilled 600 come back from the escrow, every claimable balance is paid out
+      assert.isUndefined(await sellerAsk(price));
+      assert.equal(
+        Number(yesAfter.amount) - Number(yesBefore.amount),
+        600 + Number(statsBefore.claimableYes),
+      );
+      assert.equal(
+        Number(collateralAfter.amount) - Number(collateralBefore.amount),
+        Number(statsBefore.claimableCollateral),
+      );
+      assert.isAtLeast(Number(statsBefore.claimableCollateral), 180);
+      assert.equal(Number(statsAfter.claimableCollateral), 0);
+      assert.equal(Number(statsAfter.claimableYes), 0);
+      assert.equal(Number(statsAfter.claimableNo), 0);
+
+      // With nothing claimable the claim step is skipped instead of failing
+      await placeLimit(seller, { sell: {} }, 1_000, price);
+      await cancelAndClaim((await sellerAsk(price)).id);
+      assert.isUndefined(await sellerAsk(price));
+    });
+
+    it("Keeps market_id and next_order_id across an orderbook grow", async () => {
+      const bookBefore = await program.account.orderBook.fetch(orderbook3);
+      const spaceBefore = (
+        await provider.connection.getAccountInfo(orderbook3)
+      ).data.length;
+      const lamportsBefore = await provider.connection.getBalance(orderbook3);
+
+      const rentFor = (ordersPerSide: number) =>
+        program.methods
+          .orderbookRentFor(roundingMarketId, ordersPerSide)
+          .accounts({ orderbook: orderbook3 })
+          .view();
+      const rentNeeded = await rentFor(32);
+      assert.isTrue(rentNeeded.gtn(0));
+
+      await program.methods
+        .reserveOrderBookCapacity(roundingMarketId, 32)
+        .accounts({
+          authority: authority.publicKey,
+          market: market3,
+          orderbook: orderbook3,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const bookAfter = await program.account.orderBook.fetch(orderbook3);
+      const spaceAfter = (await provider.connection.getAccountInfo(orderbook3))
+        .data.length;
+      assert.isAbove(spaceAfter, spaceBefore);
+      // The view quoted exactly what the grow cost, and nothing more is needed now
+      const lamportsAfter = await provider.connection.getBalance(orderbook3);
+      assert.equal(lamportsAfter - lamportsBefore, rentNeeded.toNumber());
+      assert.equal((await rentFor(32)).toNumber(), 0);
+      assert.equal(bookAfter.marketId, roundingMarketId);
+      assert.equal(bookAfter.bump, bookBefore.bump);
+      assert.equal(
+        bookAfter.nextOrderId.toString(),
+        bookBefore.nextOrderId.toString(),
+      );
+
+      // The next order continues the id sequence instead of restarting it
+      await placeLimit(buyer, { buy: {} }, 1_000, 100_000);
+      const bookPlaced = await program.account.orderBook.fetch(orderbook3);
+      const bid = bookPlaced.yesBuyOrders.find((o) =>
+        o.userKey.equals(buyer.kp.publicKey),
+      );
+      assert.equal(bid.id.toString(), bookBefore.nextOrderId.toString());
+      assert.equal(
+        bookPlaced.nextOrderId.toString(),
+        bookBefore.nextOrderId.addn(1).toString(),
+      );
+
+      // Take the bid off the book again
+      await program.methods
+        .cancelOrder(roundingMarketId, bid.id)
+        .accounts({
+          market: market3,
+          orderbook: orderbook3,
+          user: buyer.kp.publicKey,
+          userOutcomeYes: buyer.yes,
+          userOutcomeNo: buyer.no,
+          collateralVault: vault3,
+          userCollateral: buyer.collateral,
+          userStatsAccount: buyer.stats,
+          yesEscrow: yesEscrow3,
+          noEscrow: noEscrow3,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer.kp])
+        .rpc();
+    });
+
+    it("Claims only collateral without touching outcome token accounts", async () => {
+      const claimCollateral = () =>
+        program.methods
+          .claimCollateral(roundingMarketId)
           .accounts({
-            market: marketPda2,
-            user: user.publicKey,
-            userStats: userStatsAccount2,
-            collateralMint,
-            userCollateral: userCollateralAccount,
-            collateralVault: collateralVault2,
-            outcomeYesMint: outcomeYesMint2,
-            outcomeNoMint: outcomeNoMint2,
-            userOutcomeYes: userOutcomeYesAccount2,
-            userOutcomeNo: userOutcomeNoAccount2,
-            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            claimer: seller.kp.publicKey,
+            user: seller.kp.publicKey,
+            market: market3,
+            userStats: seller.stats,
+            userCollateral: seller.collateral,
+            collateralVault: vault3,
             tokenProgram: TOKEN_PROGRAM_ID,
-            systemProgram: SystemProgram.programId,
           })
-          .signers([user])
+          .signers([seller.kp])
           .rpc();
 
-        let userYesAccountAfter = await getAccount(
-          provider.connection,
-          userOutcomeYesAccount2,
-        );
-        let userCollateralAccountAfter = await getAccount(
-          provider.connection,
-          userCollateralAccount,
-        );
-        let market2After = await program.account.market.fetch(marketPda2);
+      // 1000 @ 0.45 sold into a bid earns the seller 450
+      await placeLimit(buyer, { buy: {} }, 1_000, 450_000);
+      await placeLimit(seller, { sell: {} }, 1_000, 450_000, [buyer]);
 
-        // All YES tokens should be burned
-        assert.equal(
-          Number(userYesAccountAfter.amount),
-          0,
-          "All YES tokens should be burned after claiming rewards",
-        );
+      const statsBefore = await program.account.userStats.fetch(seller.stats);
+      const collateralBefore = await getAccount(
+        provider.connection,
+        seller.collateral,
+      );
+      assert.isAtLeast(Number(statsBefore.claimableCollateral), 450);
 
-        // Collateral returned 1:1 for the burned YES tokens
-        assert.equal(
-          Number(userCollateralAccountAfter.amount) -
-            Number(userCollateralAccountBefore.amount),
-          yesToBurn,
-          "User should receive collateral equal to burned YES tokens",
-        );
+      await claimCollateral();
 
-        // Market totalCollateralLocked should decrease by the burned amount
-        assert.equal(
-          Number(market2Before.totalCollateralLocked) -
-            Number(market2After.totalCollateralLocked),
-          yesToBurn,
-          "totalCollateralLocked should decrease by the amount of YES tokens burned",
-        );
+      const statsAfter = await program.account.userStats.fetch(seller.stats);
+      const collateralAfter = await getAccount(
+        provider.connection,
+        seller.collateral,
+      );
+      assert.equal(
+        Number(collateralAfter.amount) - Number(collateralBefore.amount),
+        Number(statsBefore.claimableCollateral),
+      );
+      assert.equal(Number(statsAfter.claimableCollateral), 0);
+      assert.equal(
+        Number(statsAfter.claimableYes),
+        Number(statsBefore.claimableYes),
+      );
 
-        console.log(
-          "Claimed rewards: burned",
-          yesToBurn,
-          "YES tokens, received",
-          yesToBurn,
-          "collateral",
-        );
-      });
+      try {
+        await claimCollateral();
+        assert.fail("Should not claim an empty collateral balance");
+      } catch (err) {
+        expect(err.toString()).to.include("NothingToClaim");
+      }
+    });
 
-      it("What if user tries to claim rewards again, should fail with NothingToClaim", async () => {
-        // After claimRewards succeeds, user_stats.reward_claimed is set to true
-        // The guard require!(!user_stats.reward_claimed, NothingToClaim) fires on the next call,
-        // even before it can reach the InvalidAmount check
+    it("Rejects max_iteration above MAX_ITERATION_CAP", async () => {
+      try {
+        await placeLimit(buyer, { buy: {} }, 1_000, 500_000, [], 33);
+        assert.fail("Should not allow max_iteration above the cap");
+      } catch (err) {
+        expect(err.toString()).to.include("InvalidIterationLimit");
+      }
+    });
 
-        try {
-          await program.methods
-            .claimRewards(marketId2)
-            .accounts({
-              market: marketPda2,
-              user: user.publicKey,
-              userStats: userStatsAccount2,
-              collateralMint,
-              userCollateral: userCollateralAccount,
-              collateralVault: collateralVault2,
-              outcomeYesMint: outcomeYesMint2,
-              outcomeNoMint: outcomeNoMint2,
-              userOutcomeYes: userOutcomeYesAccount2,
-              userOutcomeNo: userOutcomeNoAccount2,
-              associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-              tokenProgram: TOKEN_PROGRAM_ID,
-              systemProgram: SystemProgram.programId,
-            })
-            .signers([user])
-            .rpc();
-        } catch (err) {
-          expect(err.toString()).to.include("NothingToClaim");
-        }
-      });
+    it("Rejects orders inside the trading cutoff window", async () => {
+      const setCutoff = (seconds: number) =>
+        program.methods
+          .setTradingCutoff(roundingMarketId, new BN(seconds))
+          .accounts({ authority: authority.publicKey, market: market3 })
+          .rpc();
+
+      // The deadline is a day out, a two day buffer closes trading now
+      await setCutoff(2 * 86400);
+      try {
+        await placeLimit(buyer, { buy: {} }, 1_000, 500_000);
+        assert.fail("Should not accept orders inside the cutoff window");
+      } catch (err) {
+        expect(err.toString()).to.include("TradingWindowClosed");
+      }
+
+      await setCutoff(0);
+      const market = await program.account.market.fetch(market3);
+      assert.equal(Number(market.tradingCutoffBuffer), 0);
     });
 
-    describe("Close Market", () => {
-      it("What if we try to close before market is settled, should fail with MarketNotSettled", async () => {
-        // require!(market.is_settled, MarketNotSettled)
-        // market_id=1 is still live
+    it("Rejects prices at or above the 1.0 max_price", async () => {
+      try {
+        await placeLimit(buyer, { buy: {} }, 1_000, USDC_UNIT);
+        assert.fail("Should not allow a price of 1.0");
+      } catch (err) {
+        expect(err.toString()).to.include("PriceOutOfBounds");
+      }
+    });
 
-        try {
-          await program.methods
-            .closeMarket(marketId)
-            .accounts({
-              market: marketPda,
-              authority: authority.publicKey,
-              orderbook: orderbookPda,
-            })
-            .rpc();
-        } catch (err) {
-          expect(err.toString()).to.include("MarketNotSettled");
-        }
+    it("Rejects quantities that aren't a multiple of the lot size", async () => {
+      const setLotSize = (lotSize: number) =>
+        program.methods
+          .setLotSize(roundingMarketId, new BN(lotSize))
+          .accounts({ authority: authority.publicKey, market: market3 })
+          .rpc();
+
+      await setLotSize(1_000);
+      try {
+        await placeLimit(buyer, { buy: {} }, 1_500, 500_000);
+        assert.fail("Should not allow 1.5 lots");
+      } catch (err) {
+        expect(err.toString()).to.include("InvalidLotSize");
+      }
+
+      await setLotSize(0);
+      const market = await program.account.market.fetch(market3);
+      assert.equal(Number(market.lotSize), 0);
+    });
+
+    it("Rejects orders above the max order quantity", async () => {
+      const setMaxOrderQuantity = (maxOrderQuantity: number) =>
+        program.methods
+          .setMaxOrderQuantity(roundingMarketId, new BN(maxOrderQuantity))
+          .accounts({ authority: authority.publicKey, market: market3 })
+          .rpc();
+
+      await setMaxOrderQuantity(1_000);
+      try {
+        await placeLimit(buyer, { buy: {} }, 1_001, 500_000);
+        assert.fail("Should not allow an order above the maximum");
+      } catch (err) {
+        expect(err.toString()).to.include("OrderTooLarge");
+      }
+
+      await setMaxOrderQuantity(0);
+      const market = await program.account.market.fetch(market3);
+      assert.equal(Number(market.maxOrderQuantity), 0);
+    });
+
+    it("Rejects a resting taker limit order on a maker-only book", async () => {
+      const setBookMode = (bookMode: object) =>
+        program.methods
+          .setBookMode(roundingMarketId, bookMode as any)
+          .accounts({ authority: authority.publicKey, market: market3 })
+          .rpc();
+
+      await setBookMode({ makerOnly: {} });
+      try {
+        // Far below any ask, so the whole order would rest
+        await placeLimit(buyer, { buy: {} }, 1_000, 1_000);
+        assert.fail("Only maker quotes may rest");
+      } catch (err) {
+        expect(err.toString()).to.include("MakerOnlyBook");
+      }
+
+      await setBookMode({ clob: {} });
+      const market = await program.account.market.fetch(market3);
+      assert.deepEqual(market.bookMode, { clob: {} });
+    });
+
+    it("Toggles socialized loss", async () => {
+      const setSocializedLoss = (enabled: boolean) =>
+        program.methods
+          .setSocializedLoss(roundingMarketId, enabled)
+          .accounts({ authority: authority.publicKey, market: market3 })
+          .rpc();
+
+      await setSocializedLoss(true);
+      let market = await program.account.market.fetch(market3);
+      assert.isTrue(market.socializedLoss);
+
+      await setSocializedLoss(false);
+      market = await program.account.market.fetch(market3);
+      assert.isFalse(market.socializedLoss);
+    });
+
+    it("Rejects orders on a paused outcome", async () => {
+      const setSidePaused = (tokenType: object, paused: boolean) =>
+        program.methods
+          .setSidePaused(roundingMarketId, tokenType as any, paused)
+          .accounts({ authority: authority.publicKey, market: market3 })
+          .rpc();
+
+      await setSidePaused({ yes: {} }, true);
+      await setSidePaused({ no: {} }, true);
+      let market = await program.account.market.fetch(market3);
+      assert.isTrue(market.yesTradingPaused);
+      assert.isTrue(market.noTradingPaused);
+
+      // NO is resumed first, YES orders keep failing until YES is resumed too
+      await setSidePaused({ no: {} }, false);
+      try {
+        await placeLimit(buyer, { buy: {} }, 1_000, 500_000);
+        assert.fail("YES trading is paused");
+      } catch (err) {
+        expect(err.toString()).to.include("SideTradingPaused");
+      }
+
+      await setSidePaused({ yes: {} }, false);
+      market = await program.account.market.fetch(market3);
+      assert.isFalse(market.yesTradingPaused);
+      assert.isFalse(market.noTradingPaused);
+    });
+
+    it("Decays the trading fee with the market's trade count", async () => {
+      const effectiveFee = () =>
+        program.methods
+          .effectiveFee(roundingMarketId)
+          .accounts({ market: market3 })
+          .view();
+      const setFeeSchedule = (schedule) =>
+        program.methods
+          .setFeeSchedule(roundingMarketId, schedule)
+          .accounts({ authority: authority.publicKey, market: market3 })
+          .rpc();
+
+      assert.equal(await effectiveFee(), 0);
+
+      // Trades already happened on this market, a step of one fill per bps puts the fee
+      // between the floor and the base
+      const { totalTrades } = await program.account.market.fetch(market3);
+      assert.isAbove(Number(totalTrades), 0);
+      await setFeeSchedule({
+        baseFeeBps: 1_000,
+        minFeeBps: 10,
+        decayStep: new BN(1),
       });
+      assert.equal(
+        await effectiveFee(),
+        Math.max(10, 1_000 - Number(totalTrades)),
+      );
 
-      it("Closing market_id=2 successfully after all collateral is claimed", async () => {
-        // By this point: setWinner ran (market settled), claimRewards ran (vault is empty)
-        // closeMarket will close both the market + orderbook accounts
-        // and return rent back to authority
+      // The floor holds once the decay runs past it
+      await setFeeSchedule({
+        baseFeeBps: 20,
+        minFeeBps: 20,
+        decayStep: new BN(1),
+      });
+      assert.equal(await effectiveFee(), 20);
 
-        let authorityBalanceBefore = await provider.connection.getBalance(
-          authority.publicKey,
-        );
+      try {
+        await setFeeSchedule({
+          baseFeeBps: 10,
+          minFeeBps: 20,
+          decayStep: new BN(1),
+        });
+        assert.fail("A floor above the base fee is rejected");
+      } catch (err) {
+        expect(err.toString()).to.include("InvalidFeeSchedule");
+      }
 
-        await program.methods
-          .closeMarket(marketId2)
+      // Back to the flat fee so later fills are unaffected
+      await setFeeSchedule(null);
+      assert.equal(await effectiveFee(), 0);
+    });
+
+    it("Marks a market maker fee exempt", async () => {
+      const setFeeExempt = (exempt: boolean) =>
+        program.methods
+          .setFeeExempt(roundingMarketId, exempt)
           .accounts({
-            market: marketPda2,
             authority: authority.publicKey,
-            orderbook: orderbookPda2,
+            market: market3,
+            user: buyer.kp.publicKey,
+            userStats: buyer.stats,
           })
           .rpc();
 
-        let authorityBalanceAfter = await provider.connection.getBalance(
-          authority.publicKey,
-        );
+      await setFeeExempt(true);
+      let stats = await program.account.userStats.fetch(buyer.stats);
+      assert.isTrue(stats.feeExempt);
 
-        // Authority should have received rent back (balance increased)
-        assert.isTrue(
-          authorityBalanceAfter > authorityBalanceBefore,
-          "Authority should receive rent back when market is closed",
-        );
+      try {
+        await program.methods
+          .setFeeExempt(roundingMarketId, false)
+          .accounts({
+            authority: buyer.kp.publicKey,
+            market: market3,
+            user: buyer.kp.publicKey,
+            userStats: buyer.stats,
+          })
+          .signers([buyer.kp])
+          .rpc();
+        assert.fail("Only the market authority can change exemptions");
+      } catch (err) {
+        expect(err.toString()).to.include("ConstraintRaw");
+      }
 
-        // Market account should no longer exist on-chain
-        let marketInfo = await provider.connection.getAccountInfo(marketPda2);
-        assert.isNull(marketInfo, "Market account should be closed");
+      await setFeeExempt(false);
+      stats = await program.account.userStats.fetch(buyer.stats);
+      assert.isFalse(stats.feeExempt);
+    });
 
-        console.log(
-          "Market closed successfully! Rent returned:",
-          authorityBalanceAfter - authorityBalanceBefore,
-          "lamports",
-        );
-      });
+    it("Refuses to resync collateral on a live market", async () => {
+      try {
+        await program.methods
+          .resyncCollateral(roundingMarketId)
+          .accounts({
+            caller: buyer.kp.publicKey,
+            market: market3,
+            orderbook: orderbook3,
+            collateralVault: vault3,
+          })
+          .signers([buyer.kp])
+          .rpc();
+        assert.fail("Only settled markets can be resynced");
+      } catch (err) {
+        expect(err.toString()).to.include("MarketNotSettled");
+      }
+    });
+
+    it("Fills a staker's order first at an equal price", async () => {
+      const staker = await newTrader(101 * USDC_UNIT);
+      const stakeAccount = pda([
+        Buffer.from("stake"),
+        roundingMarketIdLE,
+        staker.kp.publicKey.toBuffer(),
+      ]);
+      const stakeVault = pda([Buffer.from("stake_vault"), roundingMarketIdLE]);
+      const price = 400_000;
+
+      // Buyer is first in time, staker reaches tier 1 and bids the same price afterwards
+      await placeLimit(buyer, { buy: {} }, 1_000, price);
+      await program.methods
+        .stake(roundingMarketId, new BN(100 * USDC_UNIT))
+        .accounts({
+          user: staker.kp.publicKey,
+          market: market3,
+          collateralMint: collateralMint,
+          userCollateral: staker.collateral,
+          stakeAccount,
+          stakeVault,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([staker.kp])
+        .rpc();
+      await placeLimit(
+        staker,
+        { buy: {} },
+        1_000,
+        price,
+        [],
+        max_iteration,
+        stakeAccount,
+      );
+
+      const book = await program.account.orderBook.fetch(orderbook3);
+      assert.isTrue(book.yesBuyOrders[0].userKey.equals(staker.kp.publicKey));
+      assert.equal(book.yesBuyOrders[0].priority, 1);
+      assert.equal(book.yesBuyOrders[1].priority, 0);
+
+      const unstake = () =>
+        program.methods
+          .unstake(roundingMarketId, new BN(100 * USDC_UNIT))
+          .accounts({
+            user: staker.kp.publicKey,
+            market: market3,
+            orderbook: orderbook3,
+            userCollateral: staker.collateral,
+            stakeAccount,
+            stakeVault,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([staker.kp])
+          .rpc();
+
+      try {
+        await unstake();
+        assert.fail("Should not unstake while an order is resting");
+      } catch (err) {
+        expect(err.toString()).to.include("StakeLockedByOrders");
+      }
+
+      await placeLimit(seller, { sell: {} }, 1_000, price, [buyer, staker]);
+      const stakerStats = await program.account.userStats.fetch(staker.stats);
+      const buyerStats = await program.account.userStats.fetch(buyer.stats);
+      assert.equal(Number(stakerStats.claimableYes), 1_000);
+      assert.equal(Number(buyerStats.lockedCollateral), 400);
+
+      // Clear the buyer's bid and return the stake
+      await placeLimit(seller, { sell: {} }, 1_000, price, [buyer]);
+      await unstake();
+      const market = await program.account.market.fetch(market3);
+      assert.equal(Number(market.totalStaked), 0);
+    });
+
+    it("Refunds exactly the unspent collateral of a market buy across price levels", async () => {
+      const lp = await newTrader(USDC_UNIT);
+      const taker = await newTrader(USDC_UNIT);
+      await program.methods
+        .splitTokens(roundingMarketId, new BN(2_000_000))
+        .accounts({
+          market: market3,
+          user: lp.kp.publicKey,
+          userCollateral: lp.collateral,
+          collateralVault: vault3,
+          outcomeYesMint: yesMint3,
+          outcomeNoMint: noMint3,
+          userOutcomeYes: lp.yes,
+          userOutcomeNo: lp.no,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([lp.kp])
+        .rpc();
+
+      // One whole token at 3 and at 7 base units, neither divides the budget evenly
+      await placeLimit(lp, { sell: {} }, 1_000_000, 3);
+      await placeLimit(lp, { sell: {} }, 1_000_000, 7);
+
+      const orderAmount = 11;
+      const walletBefore = await getAccount(
+        provider.connection,
+        taker.collateral,
+      );
+      await program.methods
+        .marketOrder(
+          roundingMarketId,
+          { buy: {} },
+          { yes: {} },
+          new BN(orderAmount),
+          new BN(max_iteration),
+          false,
+          null,
+        )
+        .accounts({
+          market: market3,
+          orderbook: orderbook3,
+          user: taker.kp.publicKey,
+          userOutcomeYes: taker.yes,
+          userOutcomeNo: taker.no,
+          collateralVault: vault3,
+          userCollateral: taker.collateral,
+          userStatsAccount: taker.stats,
+          outcomeYesMint: yesMint3,
+          outcomeNoMint: noMint3,
+          yesEscrow: yesEscrow3,
+          noEscrow: noEscrow3,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts([
+          { pubkey: lp.stats, isSigner: false, isWritable: true },
+        ])
+        .signers([taker.kp])
+        .rpc();
+
+      // 3 + 7 spent on the two levels, the leftover unit comes back
+      const walletAfter = await getAccount(
+        provider.connection,
+        taker.collateral,
+      );
+      const takerYes = await getAccount(provider.connection, taker.yes);
+      const lpStats = await program.account.userStats.fetch(lp.stats);
+      const spent = Number(walletBefore.amount) - Number(walletAfter.amount);
+      assert.equal(spent, 10);
+      assert.equal(Number(lpStats.claimableCollateral), spent);
+      assert.equal(Number(takerYes.amount), 2_000_000);
+
+      const market = await program.account.market.fetch(market3);
+      const vault = await getAccount(provider.connection, vault3);
+      assert.equal(Number(market.totalCollateralLocked), Number(vault.amount));
+      // The last fill was the second level, market orders have no limit price
+      assert.equal(Number(market.lastYesPrice), 7);
+    });
+
+    it("Locks buy orders from deposited collateral without a transfer", async () => {
+      const bucketAccounts = {
+        user: buyer.kp.publicKey,
+        market: market3,
+        userStatsAccount: buyer.stats,
+        userCollateral: buyer.collateral,
+        collateralVault: vault3,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      };
+      await program.methods
+        .depositCollateral(roundingMarketId, new BN(1_000))
+        .accounts(bucketAccounts)
+        .signers([buyer.kp])
+        .rpc();
+
+      const walletBefore = await getAccount(
+        provider.connection,
+        buyer.collateral,
+      );
+      let stats = await program.account.userStats.fetch(buyer.stats);
+      const lockedBefore = Number(stats.lockedCollateral);
+
+      // 1000 base units @ 0.4 locks 400, all of it from the bucket
+      await placeLimit(buyer, { buy: {} }, 1_000, 400_000);
+
+      const walletAfter = await getAccount(
+        provider.connection,
+        buyer.collateral,
+      );
+      stats = await program.account.userStats.fetch(buyer.stats);
+      assert.equal(Number(walletAfter.amount), Number(walletBefore.amount));
+      assert.equal(Number(stats.availableCollateral), 600);
+      assert.equal(Number(stats.lockedCollateral), lockedBefore + 400);
+
+      // The order keeps resting, only the unlocked 600 can come back out
+      try {
+        await program.methods
+          .withdrawCollateral(roundingMarketId, new BN(601))
+          .accounts(bucketAccounts)
+          .signers([buyer.kp])
+          .rpc();
+        assert.fail("Should not withdraw more than is available");
+      } catch (err) {
+        expect(err.toString()).to.include("NotEnoughBalance");
+      }
+
+      await program.methods
+        .withdrawCollateral(roundingMarketId, new BN(600))
+        .accounts(bucketAccounts)
+        .signers([buyer.kp])
+        .rpc();
+      stats = await program.account.userStats.fetch(buyer.stats);
+      assert.equal(Number(stats.availableCollateral), 0);
+
+      const market = await program.account.market.fetch(market3);
+      const vault = await getAccount(provider.connection, vault3);
+      assert.equal(Number(market.totalCollateralLocked), Number(vault.amount));
+    });
+
+    it("Locks sell orders from deposited tokens without a transfer", async () => {
+      const maker = await newTrader(USDC_UNIT);
+      await program.methods
+        .splitTokens(roundingMarketId, new BN(2_000))
+        .accounts({
+          market: market3,
+          user: maker.kp.publicKey,
+          userCollateral: maker.collateral,
+          collateralVault: vault3,
+          outcomeYesMint: yesMint3,
+          outcomeNoMint: noMint3,
+          userOutcomeYes: maker.yes,
+          userOutcomeNo: maker.no,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([maker.kp])
+        .rpc();
+
+      // The maker's UserStats is created by setup_user or a first order
+      await placeLimit(maker, { buy: {} }, 1_000, 1_000);
+
+      const bucketAccounts = {
+        user: maker.kp.publicKey,
+        market: market3,
+        userStatsAccount: maker.stats,
+        outcomeYesMint: yesMint3,
+        outcomeNoMint: noMint3,
+        userOutcomeYes: maker.yes,
+        userOutcomeNo: maker.no,
+        yesEscrow: yesEscrow3,
+        noEscrow: noEscrow3,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      };
+      await program.methods
+        .depositTokens(roundingMarketId, { yes: {} }, new BN(2_000))
+        .accounts(bucketAccounts)
+        .signers([maker.kp])
+        .rpc();
+
+      await placeLimit(maker, { sell: {} }, 1_000, 900_000);
+
+      let stats = await program.account.userStats.fetch(maker.stats);
+      let wallet = await getAccount(provider.connection, maker.yes);
+      assert.equal(Number(wallet.amount), 0);
+      assert.equal(Number(stats.availableYes), 1_000);
+      assert.equal(Number(stats.lockedYes), 1_000);
+
+      await program.methods
+        .withdrawTokens(roundingMarketId, { yes: {} }, new BN(1_000))
+        .accounts(bucketAccounts)
+        .signers([maker.kp])
+        .rpc();
+
+      stats = await program.account.userStats.fetch(maker.stats);
+      wallet = await getAccount(provider.connection, maker.yes);
+      assert.equal(Number(wallet.amount), 1_000);
+      assert.equal(Number(stats.availableYes), 0);
+    });
+
+    it("Cancels the resting order on a self-match under the CancelOldest STP policy", async () => {
+      const trader = await newTrader(USDC_UNIT);
+      await program.methods
+        .splitTokens(roundingMarketId, new BN(1_000))
+        .accounts({
+          market: market3,
+          user: trader.kp.publicKey,
+          userCollateral: trader.collateral,
+          collateralVault: vault3,
+          outcomeYesMint: yesMint3,
+          outcomeNoMint: noMint3,
+          userOutcomeYes: trader.yes,
+          userOutcomeNo: trader.no,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([trader.kp])
+        .rpc();
+
+      const setPolicy = (policy: object) =>
+        program.methods
+          .setStpPolicy(roundingMarketId, policy as any)
+          .accounts({ authority: authority.publicKey, market: market3 })
+          .rpc();
+
+      // The NO book of this market is untouched by the other tests
+      const placeNo = (side: object) =>
+        program.methods
+          .placeOrder(
+            roundingMarketId,
+            side as any,
+            { no: {} },
+            new BN(1_000),
+            new BN(600_000),
+            new BN(max_iteration),
+            null,
+          )
+          .accounts({
+            market: market3,
+            orderbook: orderbook3,
+            user: trader.kp.publicKey,
+            userOutcomeYes: trader.yes,
+            userOutcomeNo: trader.no,
+            collateralVault: vault3,
+            userCollateral: trader.collateral,
+            userStatsAccount: trader.stats,
+            yesEscrow: yesEscrow3,
+            noEscrow: noEscrow3,
+            incentiveVault: null,
+            stakeAccount: null,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([trader.kp])
+          .rpc();
+
+      await setPolicy({ cancelOldest: {} });
+      await placeNo({ buy: {} });
+      const restingBid = (await program.account.orderBook.fetch(orderbook3))
+        .noBuyOrders[0];
+      await placeNo({ sell: {} });
+
+      // The bid is cancelled instead of skipped, the ask rests and nothing traded
+      const book = await program.account.orderBook.fetch(orderbook3);
+      assert.equal(book.noBuyOrders.length, 0);
+      assert.equal(book.noSellOrders.length, 1);
+      assert.equal(book.noSellOrders[0].filledquantity.toNumber(), 0);
+
+      const stats = await program.account.userStats.fetch(trader.stats);
+      assert.equal(Number(stats.lockedCollateral), 0);
+      assert.equal(Number(stats.claimableCollateral), 600);
+      assert.equal(Number(stats.lockedNo), 1_000);
+      assert.equal(Number(stats.claimableNo), 0);
+
+      const closed = await program.methods
+        .getClosedOrder(roundingMarketId, restingBid.id)
+        .accounts({ orderbook: orderbook3 })
+        .view();
+      assert.deepEqual(closed.reason, { cancelled: {} });
+
+      await setPolicy({ skip: {} });
+    });
+
+    it("Splits collateral and quotes both legs in one transaction", async () => {
+      const lp = await newTrader(USDC_UNIT);
+      const walletBefore = await getAccount(provider.connection, lp.collateral);
+
+      // Both prices are above every resting bid, so each leg rests in full
+      await program.methods
+        .splitAndQuote(
+          roundingMarketId,
+          new BN(1_000),
+          new BN(950_000),
+          new BN(700_000),
+        )
+        .accounts({
+          place: {
+            market: market3,
+            orderbook: orderbook3,
+            user: lp.kp.publicKey,
+            userOutcomeYes: null,
+            userOutcomeNo: null,
+            collateralVault: vault3,
+            userCollateral: lp.collateral,
+            userStatsAccount: lp.stats,
+            yesEscrow: yesEscrow3,
+            noEscrow: noEscrow3,
+            outcomeMint: null,
+            incentiveVault: null,
+            stakeAccount: null,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          },
+          outcomeYesMint: yesMint3,
+          outcomeNoMint: noMint3,
+        })
+        .signers([lp.kp])
+        .rpc();
+
+      const walletAfter = await getAccount(provider.connection, lp.collateral);
+      assert.equal(
+        Number(walletBefore.amount) - Number(walletAfter.amount),
+        1_000,
+      );
+
+      const book = await program.account.orderBook.fetch(orderbook3);
+      const ownAsk = (orders: any[]) =>
+        orders.find((o) => o.userKey.equals(lp.kp.publicKey));
+      const yesAsk = ownAsk(book.yesSellOrders);
+      const noAsk = ownAsk(book.noSellOrders);
+      assert.equal(yesAsk.price.toNumber(), 950_000);
+      assert.equal(yesAsk.quantity.toNumber(), 1_000);
+      assert.equal(noAsk.price.toNumber(), 700_000);
+      assert.equal(noAsk.quantity.toNumber(), 1_000);
+
+      // The pair went straight to escrow and is locked by the two asks
+      const stats = await program.account.userStats.fetch(lp.stats);
+      assert.equal(Number(stats.lockedYes), 1_000);
+      assert.equal(Number(stats.lockedNo), 1_000);
+      assert.equal(Number(stats.availableYes), 0);
+      assert.equal(Number(stats.availableNo), 0);
+
+      const market = await program.account.market.fetch(market3);
+      const vault = await getAccount(provider.connection, vault3);
+      assert.equal(Number(market.totalCollateralLocked), Number(vault.amount));
+    });
+  });
+
+  describe("Settlement & Lifecycle", () => {
+    let marketId2 = 2;
+    let marketPda2: PublicKey;
+    let collateralVault2: PublicKey;
+    let outcomeYesMint2: PublicKey;
+    let outcomeNoMint2: PublicKey;
+    let orderbookPda2: PublicKey;
+    let yesEscrowPda2: PublicKey;
+    let noEscrowPda2: PublicKey;
+    let userStatsAccount2: PublicKey;
+
+    let userOutcomeYesAccount2: PublicKey;
+    let userOutcomeNoAccount2: PublicKey;
+
+    before(async () => {
+      const marketId2BN = new BN(2);
+      const marketId2LE = marketId2BN.toArrayLike(Buffer, "le", 4);
+
+      [marketPda2] = PublicKey.findProgramAddressSync(
+        [Buffer.from("market"), marketId2LE],
+        program.programId,
+      );
+      [collateralVault2] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), marketId2LE],
+        program.programId,
+      );
+      [outcomeYesMint2] = PublicKey.findProgramAddressSync(
+        [Buffer.from("outcome_a"), marketId2LE],
+        program.programId,
+      );
+      [outcomeNoMint2] = PublicKey.findProgramAddressSync(
+        [Buffer.from("outcome_b"), marketId2LE],
+        program.programId,
+      );
+      [orderbookPda2] = PublicKey.findProgramAddressSync(
+        [Buffer.from("orderbook"), marketId2LE],
+        program.programId,
+      );
+      [yesEscrowPda2] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), marketId2LE, outcomeYesMint2.toBuffer()],
+        program.programId,
+      );
+      [noEscrowPda2] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), marketId2LE, outcomeNoMint2.toBuffer()],
+        program.programId,
+      );
+      [userStatsAccount2] = PublicKey.findProgramAddressSync(
+        [Buffer.from("user_stats"), marketId2LE, user.publicKey.toBuffer()],
+        program.programId,
+      );
+
+      // Deadline of 2 seconds
+      const shortDeadline = new BN(Math.floor(Date.now() / 1000) + 2);
+
+      await program.methods
+        .initializeMarket(marketId2, shortDeadline, "", 0, false, "", "", { binary: {} }, null, null, slugOf(marketId2), false, new BN(0))
+        .accounts({
+          market: marketPda2,
+          authority: authority.publicKey,
+          collateralMint: collateralMint,
+          collateralVault: collateralVault2,
+          outcomeYesMint: outcomeYesMint2,
+          outcomeNoMint: outcomeNoMint2,
+          yesEscrow: yesEscrowPda2,
+          noEscrow: noEscrowPda2,
+          orderbook: orderbookPda2,
+          marketRegistry: registryPda(marketId2),
+          marketSlug: slugPda(slugOf(marketId2)),
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      let splitAmount2 = 50;
+
+      const userOutcomeYesInfo2 = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        authority.payer,
+        outcomeYesMint2,
+        user.publicKey,
+      );
+      userOutcomeYesAccount2 = userOutcomeYesInfo2.address;
+
+      const userOutcomeNoInfo2 = await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        authority.payer,
+        outcomeNoMint2,
+        user.publicKey,
+      );
+      userOutcomeNoAccount2 = userOutcomeNoInfo2.address;
+
+      await program.methods
+        .splitTokens(marketId2, new BN(splitAmount2))
+        .accounts({
+          market: marketPda2,
+          user: user.publicKey,
+          userCollateral: userCollateralAccount,
+          collateralVault: collateralVault2,
+          outcomeYesMint: outcomeYesMint2,
+          outcomeNoMint: outcomeNoMint2,
+          userOutcomeYes: userOutcomeYesAccount2,
+          userOutcomeNo: userOutcomeNoAccount2,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+      console.log(
+        "Market 2 initialised with short deadline & user funded with YES/NO tokens",
+      );
+    });
+
+    describe("Update Metadata", () => {
+      it("Updating the market metadata URL", async () => {
+        const newUrl = "https://new_url.com";
+
+        await program.methods
+          .updateMetadata(marketId, newUrl, null, null)
+          .accounts({
+            market: marketPda,
+            authority: authority.publicKey,
+          })
+          .rpc();
+
+        const market = await program.account.market.fetch(marketPda);
+        assert.equal(
+          market.metaDataUrl,
+          newUrl,
+          "Market metaDataUrl should be updated",
+        );
+        assert.isAtLeast(
+          market.lastMetadataUpdate.toNumber(),
+          Math.floor(Date.now() / 1000) - 60,
+          "lastMetadataUpdate should be refreshed",
+        );
+
+        console.log("Metadata updated to:", newUrl);
+      });
+    });
+
+    describe("Set Winner", () => {
+      it("What if we try to set winner before the deadline, should fail with SettlementDeadlineNotReached", async () => {
+        try {
+          await program.methods
+            .setWinner(marketId, { outcomeA: {} }, null)
+            .accounts({
+              market: marketPda,
+              authority: authority.publicKey,
+              outcomeYesMint,
+              outcomeNoMint,
+              associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .rpc();
+        } catch (err) {
+          expect(err.toString()).to.include("SettlementDeadlineNotReached");
+        }
+      });
+
+      it("What if a keeper prunes orders before the deadline, should fail with SettlementDeadlineNotReached", async () => {
+        try {
+          await program.methods
+            .pruneExpiredOrders(marketId, new BN(max_iteration))
+            .accounts({
+              caller: authority.publicKey,
+              market: marketPda,
+              orderbook: orderbookPda,
+              collateralVault,
+              incentiveVault: null,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .remainingAccounts([
+              { pubkey: userStatsAccount, isSigner: false, isWritable: true },
+            ])
+            .rpc();
+          assert.fail("Resting orders can still trade before the deadline");
+        } catch (err) {
+          expect(err.toString()).to.include("SettlementDeadlineNotReached");
+        }
+      });
+
+      it("Setting YES as the winner on market_id=2 after deadline expires", async () => {
+        // Waiting for 3 sec
+        await new Promise((s) => setTimeout(s, 3000));
+
+        await program.methods
+          .setWinner(marketId2, { outcomeA: {} }, null)
+          .accounts({
+            market: marketPda2,
+            authority: authority.publicKey,
+            outcomeYesMint: outcomeYesMint2,
+            outcomeNoMint: outcomeNoMint2,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .rpc();
+
+        const market2 = await program.account.market.fetch(marketPda2);
+
+        assert.isTrue(market2.isSettled, "Market should be marked as settled");
+        assert.deepEqual(
+          market2.winningOutcome,
+          { outcomeA: {} },
+          "Winning outcome should be YES",
+        );
+
+        console.log("Winner set: YES");
+
+        const [settlementRecordPda] = PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("settlement_record"),
+            new BN(marketId2).toArrayLike(Buffer, "le", 4),
+          ],
+          program.programId,
+        );
+        const record =
+          await program.account.settlementRecord.fetch(settlementRecordPda);
+        assert.equal(record.marketId, marketId2);
+        assert.isTrue(record.resolver.equals(authority.publicKey));
+        assert.deepEqual(record.outcome, { outcomeA: {} });
+        assert.equal(record.source, 0);
+        assert.isAbove(Number(record.settledAt), 0);
+
+        // Verify collateral is still in the vault (50 from splitTokens)
+        // This is important — we'll use this to test CollateralNotFullyClaimed below
+        const lockedAfterSettle = Number(market2.totalCollateralLocked);
+        assert.isAbove(
+          lockedAfterSettle,
+          0,
+          "Vault should still have collateral after setWinner",
+        );
+
+        // set_winner refuses to settle unless the vault covers every winning token
+        const vault = await getAccount(provider.connection, collateralVault2);
+        const yesMint = await getMint(provider.connection, outcomeYesMint2);
+        assert.isTrue(
+          vault.amount >= yesMint.supply,
+          "Vault should cover the full YES supply at settlement",
+        );
+      });
+
+      it("What if we try to set winner again on an already settled market, should fail", async () => {
+        // require!(!self.market.is_settled, MarketAlreadySettled)
+
+        try {
+          await program.methods
+            .setWinner(marketId2, { outcomeB: {} }, null)
+            .accounts({
+              market: marketPda2,
+              authority: authority.publicKey,
+              outcomeYesMint: outcomeYesMint2,
+              outcomeNoMint: outcomeNoMint2,
+              associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .rpc();
+        } catch (err) {
+          expect(err.toString()).to.include("MarketAlreadySettled");
+        }
+      });
+
+      it("Rejects orders on a settled market whose mint authority is revoked", async () => {
+        const yesMint = await getMint(provider.connection, outcomeYesMint2);
+        const noMint = await getMint(provider.connection, outcomeNoMint2);
+        assert.isNull(yesMint.mintAuthority);
+        assert.isNull(noMint.mintAuthority);
+
+        // The settled check runs before the clock, so it wins over MarketExpired
+        try {
+          await program.methods
+            .placeOrder(
+              marketId2,
+              { sell: {} },
+              { yes: {} },
+              new BN(10),
+              new BN(500_000),
+              new BN(max_iteration),
+              null,
+            )
+            .accounts({
+              market: marketPda2,
+              orderbook: orderbookPda2,
+              user: user.publicKey,
+              userOutcomeYes: userOutcomeYesAccount2,
+              userOutcomeNo: userOutcomeNoAccount2,
+              collateralVault: collateralVault2,
+              userCollateral: userCollateralAccount,
+              userStatsAccount: userStatsAccount2,
+              yesEscrow: yesEscrowPda2,
+              noEscrow: noEscrowPda2,
+              incentiveVault: null,
+              stakeAccount: null,
+              systemProgram: SystemProgram.programId,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .signers([user])
+            .rpc();
+          assert.fail("Should not accept orders after settlement");
+        } catch (err) {
+          expect(err.toString()).to.include("MarketAlreadySettled");
+        }
+      });
+
+      it("Only the authority can reset the orderbook of a settled market", async () => {
+        const resetAccounts = (signer: PublicKey) => ({
+          authority: signer,
+          market: marketPda2,
+          orderbook: orderbookPda2,
+          collateralVault: collateralVault2,
+          incentiveVault: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        });
+
+        try {
+          await program.methods
+            .resetOrderbook(marketId2)
+            .accounts(resetAccounts(user.publicKey))
+            .signers([user])
+            .rpc();
+          assert.fail("A non-authority reset should fail");
+        } catch (err) {
+          expect(err.toString()).to.include("ConstraintRaw");
+        }
+
+        const spaceBefore = (
+          await provider.connection.getAccountInfo(orderbookPda2)
+        ).data.length;
+        await program.methods
+          .resetOrderbook(marketId2)
+          .accounts(resetAccounts(authority.publicKey))
+          .rpc();
+
+        const book = await program.account.orderBook.fetch(orderbookPda2);
+        assert.equal(book.yesBuyOrders.length, 0);
+        assert.equal(book.yesSellOrders.length, 0);
+        assert.equal(book.noBuyOrders.length, 0);
+        assert.equal(book.noSellOrders.length, 0);
+        const spaceAfter = (
+          await provider.connection.getAccountInfo(orderbookPda2)
+        ).data.length;
+        assert.isAtMost(spaceAfter, spaceBefore);
+      });
+
+      it("What if we try to close the settled market while collateral is still in the vault, should fail with CollateralNotFullyClaimed", async () => {
+        // Right after setWinner, the 50 collateral from splitTokens is still in the vault
+        // closeMarket requires total_collateral_locked == 0
+        // So closing here (before anyone calls claimRewards) should fail
+        //
+        // This is the exact ordering users must follow:
+        //   setWinner -> all users claimRewards -> authority closeMarket
+
+        let market2 = await program.account.market.fetch(marketPda2);
+        console.log(
+          "totalCollateralLocked right after setWinner:",
+          Number(market2.totalCollateralLocked),
+        );
+        assert.isAbove(
+          Number(market2.totalCollateralLocked),
+          0,
+          "Precondition: vault must still have collateral right after setWinner",
+        );
+
+        try {
+          await program.methods
+            .closeMarket(marketId2, false)
+            .accounts({
+              market: marketPda2,
+              authority: authority.publicKey,
+              orderbook: orderbookPda2,
+              marketRegistry: registryPda(marketId2),
+              marketSlug: slugPda(slugOf(marketId2)),
+              ...noRefundAccounts,
+            })
+            .rpc();
+        } catch (err) {
+          expect(err.toString()).to.include("CollateralNotFullyClaimed");
+        }
+      });
+
+      it("force_settle_and_close can't close while a winner's tokens are unredeemed", async () => {
+        // The user holds winning YES in their wallet and nothing claimable, sweeping them
+        // pays 0 and the collateral backing their tokens keeps the close blocked
+        try {
+          await program.methods
+            .forceSettleAndClose(marketId2)
+            .accounts({
+              close: {
+                market: marketPda2,
+                authority: authority.publicKey,
+                orderbook: orderbookPda2,
+                marketRegistry: registryPda(marketId2),
+                marketSlug: slugPda(slugOf(marketId2)),
+                ...noRefundAccounts,
+              },
+              collateralVault: collateralVault2,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .remainingAccounts([
+              { pubkey: userStatsAccount2, isSigner: false, isWritable: true },
+              { pubkey: userCollateralAccount, isSigner: false, isWritable: true },
+            ])
+            .rpc();
+          assert.fail("Should not close while collateral is still owed");
+        } catch (err) {
+          expect(err.toString()).to.include("CollateralNotFullyClaimed");
+        }
+      });
+    });
+
+    describe("Claim Rewards", () => {
+      it("What if we try to claimRewards before market is settled, should fail", async () => {
+        // require!(self.market.is_settled, MarketNotSettled)
+        // market_id=1 is not settled yet — good test target
+
+        try {
+          await program.methods
+            .claimRewards(marketId, null, false)
+            .accounts({
+              market: marketPda,
+              user: user.publicKey,
+              claimer: user.publicKey,
+              userStats: userStatsAccount,
+              collateralMint,
+              userCollateral: userCollateralAccount,
+              collateralVault,
+              outcomeYesMint,
+              outcomeNoMint,
+              userOutcomeYes: userOutcomeYesAccount,
+              userOutcomeNo: userOutcomeNoAccount,
+              associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+              tokenProgram: TOKEN_PROGRAM_ID,
+              systemProgram: SystemProgram.programId,
+            })
+            .signers([user])
+            .rpc();
+        } catch (err) {
+          expect(err.toString()).to.include("MarketNotSettled");
+        }
+      });
+
+      it("Winner (YES holder) claiming collateral rewards by burning YES tokens", async () => {
+        // YES wins on market_id=2 — user holds YES tokens from splitTokens in before()
+        // claimRewards burns all YES tokens and returns 1:1 collateral
+        // totalCollateralLocked should decrease by the amount burned
+
+        let userYesAccountBefore = await getAccount(
+          provider.connection,
+          userOutcomeYesAccount2,
+        );
+        let userCollateralAccountBefore = await getAccount(
+          provider.connection,
+          userCollateralAccount,
+        );
+        let market2Before = await program.account.market.fetch(marketPda2);
+
+        let yesToBurn = Number(userYesAccountBefore.amount);
+        console.log("YES tokens to burn for reward:", yesToBurn);
+
+        await program.methods
+          .claimRewards(marketId2, null, false)
+          .accounts({
+            market: marketPda2,
+            user: user.publicKey,
+            claimer: user.publicKey,
+            userStats: userStatsAccount2,
+            collateralMint,
+            userCollateral: userCollateralAccount,
+            collateralVault: collateralVault2,
+            outcomeYesMint: outcomeYesMint2,
+            outcomeNoMint: outcomeNoMint2,
+            userOutcomeYes: userOutcomeYesAccount2,
+            userOutcomeNo: userOutcomeNoAccount2,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([user])
+          .rpc();
+
+        let userYesAccountAfter = await getAccount(
+          provider.connection,
+          userOutcomeYesAccount2,
+        );
+        let userCollateralAccountAfter = await getAccount(
+          provider.connection,
+          userCollateralAccount,
+        );
+        let market2After = await program.account.market.fetch(marketPda2);
+
+        // All YES tokens should be burned
+        assert.equal(
+          Number(userYesAccountAfter.amount),
+          0,
+          "All YES tokens should be burned after claiming rewards",
+        );
+
+        // Collateral returned 1:1 for the burned YES tokens
+        assert.equal(
+          Number(userCollateralAccountAfter.amount) -
+            Number(userCollateralAccountBefore.amount),
+          yesToBurn,
+          "User should receive collateral equal to burned YES tokens",
+        );
+
+        // Market totalCollateralLocked should decrease by the burned amount
+        assert.equal(
+          Number(market2Before.totalCollateralLocked) -
+            Number(market2After.totalCollateralLocked),
+          yesToBurn,
+          "totalCollateralLocked should decrease by the amount of YES tokens burned",
+        );
+
+        console.log(
+          "Claimed rewards: burned",
+          yesToBurn,
+          "YES tokens, received",
+          yesToBurn,
+          "collateral",
+        );
+      });
+
+      it("What if user tries to claim rewards again, should fail with NothingToClaim", async () => {
+        // After claimRewards succeeds, user_stats.reward_claimed is set to true
+        // The guard require!(!user_stats.reward_claimed, NothingToClaim) fires on the next call,
+        // even before it can reach the InvalidAmount check
+
+        try {
+          await program.methods
+            .claimRewards(marketId2, null, false)
+            .accounts({
+              market: marketPda2,
+              user: user.publicKey,
+              claimer: user.publicKey,
+              userStats: userStatsAccount2,
+              collateralMint,
+              userCollateral: userCollateralAccount,
+              collateralVault: collateralVault2,
+              outcomeYesMint: outcomeYesMint2,
+              outcomeNoMint: outcomeNoMint2,
+              userOutcomeYes: userOutcomeYesAccount2,
+              userOutcomeNo: userOutcomeNoAccount2,
+              associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+              tokenProgram: TOKEN_PROGRAM_ID,
+              systemProgram: SystemProgram.programId,
+            })
+            .signers([user])
+            .rpc();
+        } catch (err) {
+          expect(err.toString()).to.include("NothingToClaim");
+        }
+      });
+    });
+
+    describe("Close Market", () => {
+      it("What if we try to close before market is settled, should fail with MarketNotSettled", async () => {
+        // require!(market.is_settled, MarketNotSettled)
+        // market_id=1 is still live
+
+        try {
+          await program.methods
+            .closeMarket(marketId, false)
+            .accounts({
+              market: marketPda,
+              authority: authority.publicKey,
+              orderbook: orderbookPda,
+              marketRegistry: registryPda(marketId),
+              marketSlug: slugPda(slugOf(marketId)),
+              ...noRefundAccounts,
+            })
+            .rpc();
+        } catch (err) {
+          expect(err.toString()).to.include("MarketNotSettled");
+        }
+      });
+
+      it("Closing market_id=2 successfully after all collateral is claimed", async () => {
+        // By this point: setWinner ran (market settled), claimRewards ran (vault is empty)
+        // closeMarket will close both the market + orderbook accounts
+        // and return rent back to authority
+
+        let authorityBalanceBefore = await provider.connection.getBalance(
+          authority.publicKey,
+        );
+
+        // The authority has no resting orders, so cancel_own_orders needs none of the
+        // refund accounts
+        await program.methods
+          .closeMarket(marketId2, true)
+          .accounts({
+            market: marketPda2,
+            authority: authority.publicKey,
+            orderbook: orderbookPda2,
+            marketRegistry: registryPda(marketId2),
+            marketSlug: slugPda(slugOf(marketId2)),
+            ...noRefundAccounts,
+          })
+          .rpc();
+
+        let authorityBalanceAfter = await provider.connection.getBalance(
+          authority.publicKey,
+        );
+
+        // Authority should have received rent back (balance increased)
+        assert.isTrue(
+          authorityBalanceAfter > authorityBalanceBefore,
+          "Authority should receive rent back when market is closed",
+        );
+
+        // Market account should no longer exist on-chain
+        let marketInfo = await provider.connection.getAccountInfo(marketPda2);
+        assert.isNull(marketInfo, "Market account should be closed");
+
+        const registry = await program.account.marketRegistry.fetch(
+          registryPda(marketId2),
+        );
+        assert.deepInclude(registry.entries, {
+          marketId: marketId2,
+          closed: true,
+        });
+
+        console.log(
+          "Market closed successfully! Rent returned:",
+          authorityBalanceAfter - authorityBalanceBefore,
+          "lamports",
+        );
+      });
+    });
+  });
+
+  describe("Scalar Settlement", () => {
+    // Range [100, 200], settled below, at and above both bounds
+    const low = 100;
+    const high = 200;
+    const pairs = 1_000;
+    const cases = [
+      { marketId: 20, value: 50, yesPayout: 0 },
+      { marketId: 21, value: 100, yesPayout: 0 },
+      { marketId: 22, value: 200, yesPayout: pairs },
+      { marketId: 23, value: 250, yesPayout: pairs },
+    ];
+    // Settled at 133, so a YES token is worth 0.33 and small claims round down
+    const dustMarketId = 24;
+
+    const pda = (seeds: Buffer[]) =>
+      PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+    const accountsOf = (marketId: number) => {
+      const idLE = new BN(marketId).toArrayLike(Buffer, "le", 4);
+      const yesMint = pda([Buffer.from("outcome_a"), idLE]);
+      const noMint = pda([Buffer.from("outcome_b"), idLE]);
+      return {
+        market: pda([Buffer.from("market"), idLE]),
+        vault: pda([Buffer.from("vault"), idLE]),
+        yesMint,
+        noMint,
+        orderbook: pda([Buffer.from("orderbook"), idLE]),
+        yesEscrow: pda([Buffer.from("escrow"), idLE, yesMint.toBuffer()]),
+        noEscrow: pda([Buffer.from("escrow"), idLE, noMint.toBuffer()]),
+        statsOf: (owner: PublicKey) =>
+          pda([Buffer.from("user_stats"), idLE, owner.toBuffer()]),
+      };
+    };
+
+    // YES holder and NO holder of each market, so each side's payout is measured on its own
+    const holders = new Map<
+      number,
+      { yes: Keypair; no: Keypair; collateral: PublicKey[] }
+    >();
+
+    const ata = async (mint: PublicKey, owner: PublicKey) =>
+      (
+        await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          authority.payer,
+          mint,
+          owner,
+        )
+      ).address;
+
+    const claim = async (
+      marketId: number,
+      holder: Keypair,
+      closeEmptyAta = false,
+      maxAmount: number | null = null,
+    ) => {
+      const a = accountsOf(marketId);
+      await program.methods
+        .claimRewards(
+          marketId,
+          maxAmount === null ? null : new BN(maxAmount),
+          closeEmptyAta,
+        )
+        .accounts({
+          market: a.market,
+          user: holder.publicKey,
+          claimer: holder.publicKey,
+          userStats: a.statsOf(holder.publicKey),
+          collateralMint,
+          userCollateral: await ata(collateralMint, holder.publicKey),
+          collateralVault: a.vault,
+          outcomeYesMint: a.yesMint,
+          outcomeNoMint: a.noMint,
+          userOutcomeYes: await ata(a.yesMint, holder.publicKey),
+          userOutcomeNo: await ata(a.noMint, holder.publicKey),
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([holder])
+        .rpc();
+    };
+
+    before(async () => {
+      const deadline = new BN(Math.floor(Date.now() / 1000) + 2);
+
+      for (const marketId of [...cases.map((c) => c.marketId), dustMarketId]) {
+        const a = accountsOf(marketId);
+        await program.methods
+          .initializeMarket(
+            marketId,
+            deadline,
+            "",
+            0,
+            false,
+            "",
+            "",
+            { scalar: { low: new BN(low), high: new BN(high) } },
+            null,
+            null,
+            slugOf(marketId),
+            false,
+            new BN(0),
+          )
+          .accounts({
+            market: a.market,
+            authority: authority.publicKey,
+            collateralMint: collateralMint,
+            collateralVault: a.vault,
+            outcomeYesMint: a.yesMint,
+            outcomeNoMint: a.noMint,
+            yesEscrow: a.yesEscrow,
+            noEscrow: a.noEscrow,
+            orderbook: a.orderbook,
+            marketRegistry: registryPda(marketId),
+            marketSlug: slugPda(slugOf(marketId)),
+            systemProgram: SystemProgram.programId,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .rpc();
+
+        const yesHolder = Keypair.generate();
+        const noHolder = Keypair.generate();
+        for (const kp of [yesHolder, noHolder]) {
+          const sig = await provider.connection.requestAirdrop(
+            kp.publicKey,
+            LAMPORTS_PER_SOL,
+          );
+          await provider.connection.confirmTransaction(sig);
+        }
+
+        // The YES holder splits, then hands the NO leg to the NO holder
+        const yesCollateral = await ata(collateralMint, yesHolder.publicKey);
+        await mintTo(
+          provider.connection,
+          authority.payer,
+          collateralMint,
+          yesCollateral,
+          authority.publicKey,
+          pairs,
+        );
+        await program.methods
+          .splitTokens(marketId, new BN(pairs))
+          .accounts({
+            market: a.market,
+            user: yesHolder.publicKey,
+            userCollateral: yesCollateral,
+            collateralVault: a.vault,
+            outcomeYesMint: a.yesMint,
+            outcomeNoMint: a.noMint,
+            userOutcomeYes: await ata(a.yesMint, yesHolder.publicKey),
+            userOutcomeNo: await ata(a.noMint, yesHolder.publicKey),
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([yesHolder])
+          .rpc();
+        await transfer(
+          provider.connection,
+          authority.payer,
+          await ata(a.noMint, yesHolder.publicKey),
+          await ata(a.noMint, noHolder.publicKey),
+          yesHolder,
+          pairs,
+        );
+
+        // claim_rewards needs the NO holder's UserStats
+        await program.methods
+          .setupUser(marketId)
+          .accounts({
+            user: noHolder.publicKey,
+            market: a.market,
+            collateralMint,
+            outcomeYesMint: a.yesMint,
+            outcomeNoMint: a.noMint,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([noHolder])
+          .rpc();
+
+        holders.set(marketId, {
+          yes: yesHolder,
+          no: noHolder,
+          collateral: [
+            yesCollateral,
+            await ata(collateralMint, noHolder.publicKey),
+          ],
+        });
+      }
+
+      await new Promise((s) => setTimeout(s, 3000));
+    });
+
+    for (const { marketId, value, yesPayout } of cases) {
+      it(`Pays YES ${yesPayout} and NO ${pairs - yesPayout} for a value of ${value} in [${low}, ${high}]`, async () => {
+        const a = accountsOf(marketId);
+        await program.methods
+          .setWinner(marketId, { outcomeA: {} }, new BN(value))
+          .accounts({
+            market: a.market,
+            authority: authority.publicKey,
+            outcomeYesMint: a.yesMint,
+            outcomeNoMint: a.noMint,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .rpc();
+
+        const market = await program.account.market.fetch(a.market);
+        assert.equal(
+          Number(market.settlementValue),
+          Math.min(Math.max(value, low), high),
+        );
+
+        const { yes, no, collateral } = holders.get(marketId);
+        const before = await Promise.all(
+          collateral.map((c) => getAccount(provider.connection, c)),
+        );
+        const yesHolderAtas = [
+          await ata(a.yesMint, yes.publicKey),
+          await ata(a.noMint, yes.publicKey),
+        ];
+        await claim(marketId, yes, true);
+        await claim(marketId, no);
+        const after = await Promise.all(
+          collateral.map((c) => getAccount(provider.connection, c)),
+        );
+
+        assert.equal(
+          Number(after[0].amount) - Number(before[0].amount),
+          yesPayout,
+        );
+        assert.equal(
+          Number(after[1].amount) - Number(before[1].amount),
+          pairs - yesPayout,
+        );
+
+        // The YES holder asked for its emptied outcome accounts to be closed
+        for (const address of yesHolderAtas) {
+          assert.isNull(await provider.connection.getAccountInfo(address));
+        }
+
+        // Together the two sides never pay out more than the pairs' collateral
+        const settled = await program.account.market.fetch(a.market);
+        assert.equal(Number(settled.totalCollateralLocked), 0);
+      });
+    }
+
+    it("Rounds small scalar claims down and sends the dust to the treasury", async () => {
+      const a = accountsOf(dustMarketId);
+      await program.methods
+        .setWinner(dustMarketId, { outcomeA: {} }, new BN(133))
+        .accounts({
+          market: a.market,
+          authority: authority.publicKey,
+          outcomeYesMint: a.yesMint,
+          outcomeNoMint: a.noMint,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const { yes, no, collateral } = holders.get(dustMarketId);
+      const before = await getAccount(provider.connection, collateral[0]);
+
+      // Claims of 1..9 YES pay floor(0.33 × k), 9 in total for 45 tokens worth 14.85
+      for (let k = 1; k <= 9; k++) {
+        await claim(dustMarketId, yes, false, k);
+      }
+      // The other 955 YES are worth 315.15 and pay 315
+      await claim(dustMarketId, yes);
+      // 1000 NO are worth exactly 670
+      await claim(dustMarketId, no);
+
+      const after = await getAccount(provider.connection, collateral[0]);
+      assert.equal(Number(after.amount) - Number(before.amount), 9 + 315);
+
+      // YES was worth 330 in total, the 6 units not paid out went to the treasury
+      const settled = await program.account.market.fetch(a.market);
+      assert.equal(Number(settled.totalCollateralLocked), 0);
+      assert.equal(Number(settled.treasuryBalance), 6);
+      let vault = await getAccount(provider.connection, a.vault);
+      assert.equal(Number(vault.amount), 6);
+
+      const authorityCollateral = await ata(
+        collateralMint,
+        authority.publicKey,
+      );
+      const authorityBefore = await getAccount(
+        provider.connection,
+        authorityCollateral,
+      );
+      await program.methods
+        .withdrawTreasury(dustMarketId)
+        .accounts({
+          authority: authority.publicKey,
+          market: a.market,
+          authorityCollateral,
+          collateralVault: a.vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const authorityAfter = await getAccount(
+        provider.connection,
+        authorityCollateral,
+      );
+      assert.equal(
+        Number(authorityAfter.amount) - Number(authorityBefore.amount),
+        6,
+      );
+      vault = await getAccount(provider.connection, a.vault);
+      assert.equal(Number(vault.amount), 0);
+      const drained = await program.account.market.fetch(a.market);
+      assert.equal(Number(drained.treasuryBalance), 0);
+    });
+  });
+
+  describe("Trading Fees", () => {
+    const feeMarketId = 25;
+    const idLE = new BN(feeMarketId).toArrayLike(Buffer, "le", 4);
+    const pda = (seeds: Buffer[]) =>
+      PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+    const market = pda([Buffer.from("market"), idLE]);
+    const vault = pda([Buffer.from("vault"), idLE]);
+    const yesMint = pda([Buffer.from("outcome_a"), idLE]);
+    const noMint = pda([Buffer.from("outcome_b"), idLE]);
+    const orderbook = pda([Buffer.from("orderbook"), idLE]);
+    const yesEscrow = pda([Buffer.from("escrow"), idLE, yesMint.toBuffer()]);
+    const noEscrow = pda([Buffer.from("escrow"), idLE, noMint.toBuffer()]);
+    const statsOf = (owner: PublicKey) =>
+      pda([Buffer.from("user_stats"), idLE, owner.toBuffer()]);
+
+    const ata = async (mint: PublicKey, owner: PublicKey) =>
+      (
+        await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          authority.payer,
+          mint,
+          owner,
+        )
+      ).address;
+
+    type Trader = {
+      kp: Keypair;
+      collateral: PublicKey;
+      yes: PublicKey;
+      no: PublicKey;
+      stats: PublicKey;
+    };
+    const newTrader = async (): Promise<Trader> => {
+      const kp = Keypair.generate();
+      const sig = await provider.connection.requestAirdrop(
+        kp.publicKey,
+        LAMPORTS_PER_SOL,
+      );
+      await provider.connection.confirmTransaction(sig);
+      const collateral = await ata(collateralMint, kp.publicKey);
+      await mintTo(
+        provider.connection,
+        authority.payer,
+        collateralMint,
+        collateral,
+        authority.publicKey,
+        2 * USDC_UNIT,
+      );
+      return {
+        kp,
+        collateral,
+        yes: await ata(yesMint, kp.publicKey),
+        no: await ata(noMint, kp.publicKey),
+        stats: statsOf(kp.publicKey),
+      };
+    };
+
+    const tradeAccounts = (trader: Trader) => ({
+      market,
+      orderbook,
+      user: trader.kp.publicKey,
+      userOutcomeYes: trader.yes,
+      userOutcomeNo: trader.no,
+      collateralVault: vault,
+      userCollateral: trader.collateral,
+      userStatsAccount: trader.stats,
+      yesEscrow,
+      noEscrow,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    });
+    const placeLimit = (
+      trader: Trader,
+      side: object,
+      tokenType: object,
+      quantity: number,
+      price: number,
+      counterparties: Trader[] = [],
+    ) =>
+      program.methods
+        .placeOrder(
+          feeMarketId,
+          side as any,
+          tokenType as any,
+          new BN(quantity),
+          new BN(price),
+          new BN(max_iteration),
+          null,
+        )
+        .accounts({
+          ...tradeAccounts(trader),
+          incentiveVault: null,
+          stakeAccount: null,
+        })
+        .remainingAccounts(
+          counterparties.map((c) => ({
+            pubkey: c.stats,
+            isSigner: false,
+            isWritable: true,
+          })),
+        )
+        .signers([trader.kp])
+        .rpc();
+    const claimFunds = (trader: Trader) =>
+      program.methods
+        .claimFunds(feeMarketId)
+        .accounts({
+          market,
+          user: trader.kp.publicKey,
+          claimer: trader.kp.publicKey,
+          userStats: trader.stats,
+          collateralMint,
+          outcomeYesMint: yesMint,
+          outcomeNoMint: noMint,
+          userCollateral: trader.collateral,
+          collateralVault: vault,
+          userOutcomeYes: trader.yes,
+          userOutcomeNo: trader.no,
+          yesEscrow,
+          noEscrow,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([trader.kp])
+        .rpc();
+
+    let seller: Trader;
+    let buyer: Trader;
+    let deadline: number;
+
+    before(async () => {
+      deadline = Math.floor(Date.now() / 1000) + 20;
+      await program.methods
+        .initializeMarket(
+          feeMarketId,
+          new BN(deadline),
+          "",
+          0,
+          false,
+          "",
+          "",
+          { binary: {} },
+          null,
+          null,
+          slugOf(feeMarketId),
+          false,
+          new BN(0),
+        )
+        .accounts({
+          market,
+          authority: authority.publicKey,
+          collateralMint,
+          collateralVault: vault,
+          outcomeYesMint: yesMint,
+          outcomeNoMint: noMint,
+          yesEscrow,
+          noEscrow,
+          orderbook,
+          marketRegistry: registryPda(feeMarketId),
+          marketSlug: slugPda(slugOf(feeMarketId)),
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      seller = await newTrader();
+      buyer = await newTrader();
+      await program.methods
+        .splitTokens(feeMarketId, new BN(USDC_UNIT))
+        .accounts({
+          market,
+          user: seller.kp.publicKey,
+          userCollateral: seller.collateral,
+          collateralVault: vault,
+          outcomeYesMint: yesMint,
+          outcomeNoMint: noMint,
+          userOutcomeYes: seller.yes,
+          userOutcomeNo: seller.no,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([seller.kp])
+        .rpc();
+    });
+
+    it("Routes fees to the treasury so a market that charged them can close", async () => {
+      // A flat 10% fee, paid by the seller out of the proceeds
+      await program.methods
+        .setFeeSchedule(feeMarketId, {
+          baseFeeBps: 1_000,
+          minFeeBps: 1_000,
+          decayStep: new BN(1),
+        })
+        .accounts({ authority: authority.publicKey, market })
+        .rpc();
+
+      await placeLimit(seller, { sell: {} }, { yes: {} }, USDC_UNIT, 500_000);
+      await placeLimit(seller, { sell: {} }, { no: {} }, USDC_UNIT, 400_000);
+
+      // market_order: 1 YES for 0.50, the buyer gets none of the fee back
+      const walletBefore = await getAccount(
+        provider.connection,
+        buyer.collateral,
+      );
+      await program.methods
+        .marketOrder(
+          feeMarketId,
+          { buy: {} },
+          { yes: {} },
+          new BN(500_000),
+          new BN(max_iteration),
+          false,
+          null,
+        )
+        .accounts({
+          ...tradeAccounts(buyer),
+          outcomeYesMint: yesMint,
+          outcomeNoMint: noMint,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts([
+          { pubkey: seller.stats, isSigner: false, isWritable: true },
+        ])
+        .signers([buyer.kp])
+        .rpc();
+      const walletAfter = await getAccount(
+        provider.connection,
+        buyer.collateral,
+      );
+      assert.equal(
+        Number(walletBefore.amount) - Number(walletAfter.amount),
+        500_000,
+      );
+
+      // place_order: 1 NO for 0.40
+      await placeLimit(buyer, { buy: {} }, { no: {} }, USDC_UNIT, 400_000, [
+        seller,
+      ]);
+
+      // 50_000 + 40_000 in fees, the seller is paid the rest
+      const sellerStats = await program.account.userStats.fetch(seller.stats);
+      assert.equal(Number(sellerStats.claimableCollateral), 450_000 + 360_000);
+      // Both fills charged the seller, rewards stay out of fees_earned
+      assert.equal(Number(sellerStats.feesEarned), -90_000);
+      let state = await program.account.market.fetch(market);
+      assert.equal(Number(state.treasuryBalance), 90_000);
+      let vaultAccount = await getAccount(provider.connection, vault);
+      assert.equal(
+        Number(state.totalCollateralLocked),
+        Number(vaultAccount.amount) - 90_000,
+      );
+
+      await new Promise((s) =>
+        setTimeout(s, (deadline + 1) * 1000 - Date.now()),
+      );
+      await program.methods
+        .setWinner(feeMarketId, { outcomeA: {} }, null)
+        .accounts({
+          market,
+          authority: authority.publicKey,
+          outcomeYesMint: yesMint,
+          outcomeNoMint: noMint,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      // Seller takes the proceeds, the buyer redeems the winning YES
+      await claimFunds(seller);
+      await claimFunds(buyer);
+      await program.methods
+        .claimRewards(feeMarketId, null, false)
+        .accounts({
+          market,
+          user: buyer.kp.publicKey,
+          claimer: buyer.kp.publicKey,
+          userStats: buyer.stats,
+          collateralMint,
+          userCollateral: buyer.collateral,
+          collateralVault: vault,
+          outcomeYesMint: yesMint,
+          outcomeNoMint: noMint,
+          userOutcomeYes: buyer.yes,
+          userOutcomeNo: buyer.no,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer.kp])
+        .rpc();
+
+      state = await program.account.market.fetch(market);
+      assert.equal(Number(state.totalCollateralLocked), 0);
+      vaultAccount = await getAccount(provider.connection, vault);
+      assert.equal(Number(vaultAccount.amount), 90_000);
+
+      const closeMarket = () =>
+        program.methods
+          .closeMarket(feeMarketId, false)
+          .accounts({
+            market,
+            authority: authority.publicKey,
+            orderbook,
+            marketRegistry: registryPda(feeMarketId),
+            marketSlug: slugPda(slugOf(feeMarketId)),
+            ...noRefundAccounts,
+          })
+          .rpc();
+      try {
+        await closeMarket();
+        assert.fail("Closing would strand the treasury");
+      } catch (err) {
+        expect(err.toString()).to.include("TreasuryNotWithdrawn");
+      }
+
+      await program.methods
+        .withdrawTreasury(feeMarketId)
+        .accounts({
+          authority: authority.publicKey,
+          market,
+          authorityCollateral: await ata(collateralMint, authority.publicKey),
+          collateralVault: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      vaultAccount = await getAccount(provider.connection, vault);
+      assert.equal(Number(vaultAccount.amount), 0);
+
+      await closeMarket();
+      assert.isNull(await provider.connection.getAccountInfo(market));
+    });
+  });
+
+  describe("Market Order Remainder", () => {
+    const remainderMarketId = 26;
+    const idLE = new BN(remainderMarketId).toArrayLike(Buffer, "le", 4);
+    const pda = (seeds: Buffer[]) =>
+      PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+    const market = pda([Buffer.from("market"), idLE]);
+    const vault = pda([Buffer.from("vault"), idLE]);
+    const yesMint = pda([Buffer.from("outcome_a"), idLE]);
+    const noMint = pda([Buffer.from("outcome_b"), idLE]);
+    const orderbook = pda([Buffer.from("orderbook"), idLE]);
+    const yesEscrow = pda([Buffer.from("escrow"), idLE, yesMint.toBuffer()]);
+    const noEscrow = pda([Buffer.from("escrow"), idLE, noMint.toBuffer()]);
+    const statsOf = (owner: PublicKey) =>
+      pda([Buffer.from("user_stats"), idLE, owner.toBuffer()]);
+
+    const ata = async (mint: PublicKey, owner: PublicKey) =>
+      (
+        await getOrCreateAssociatedTokenAccount(
+          provider.connection,
+          authority.payer,
+          mint,
+          owner,
+        )
+      ).address;
+
+    type Trader = {
+      kp: Keypair;
+      collateral: PublicKey;
+      yes: PublicKey;
+      no: PublicKey;
+      stats: PublicKey;
+    };
+    const newTrader = async (): Promise<Trader> => {
+      const kp = Keypair.generate();
+      const sig = await provider.connection.requestAirdrop(
+        kp.publicKey,
+        LAMPORTS_PER_SOL,
+      );
+      await provider.connection.confirmTransaction(sig);
+      const collateral = await ata(collateralMint, kp.publicKey);
+      await mintTo(
+        provider.connection,
+        authority.payer,
+        collateralMint,
+        collateral,
+        authority.publicKey,
+        2 * USDC_UNIT,
+      );
+      return {
+        kp,
+        collateral,
+        yes: await ata(yesMint, kp.publicKey),
+        no: await ata(noMint, kp.publicKey),
+        stats: statsOf(kp.publicKey),
+      };
+    };
+
+    const tradeAccounts = (trader: Trader) => ({
+      market,
+      orderbook,
+      user: trader.kp.publicKey,
+      userOutcomeYes: trader.yes,
+      userOutcomeNo: trader.no,
+      collateralVault: vault,
+      userCollateral: trader.collateral,
+      userStatsAccount: trader.stats,
+      yesEscrow,
+      noEscrow,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    });
+    const placeSell = (trader: Trader, quantity: number, price: number) =>
+      program.methods
+        .placeOrder(
+          remainderMarketId,
+          { sell: {} },
+          { yes: {} },
+          new BN(quantity),
+          new BN(price),
+          new BN(max_iteration),
+          null,
+        )
+        .accounts({
+          ...tradeAccounts(trader),
+          incentiveVault: null,
+          stakeAccount: null,
+        })
+        .signers([trader.kp])
+        .rpc();
+
+    let seller: Trader;
+    let buyer: Trader;
+
+    before(async () => {
+      await program.methods
+        .initializeMarket(
+          remainderMarketId,
+          new BN(Math.floor(Date.now() / 1000) + 3600),
+          "",
+          0,
+          false,
+          "",
+          "",
+          { binary: {} },
+          null,
+          null,
+          slugOf(remainderMarketId),
+          false,
+          new BN(0),
+        )
+        .accounts({
+          market,
+          authority: authority.publicKey,
+          collateralMint,
+          collateralVault: vault,
+          outcomeYesMint: yesMint,
+          outcomeNoMint: noMint,
+          yesEscrow,
+          noEscrow,
+          orderbook,
+          marketRegistry: registryPda(remainderMarketId),
+          marketSlug: slugPda(slugOf(remainderMarketId)),
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      seller = await newTrader();
+      buyer = await newTrader();
+      await program.methods
+        .splitTokens(remainderMarketId, new BN(USDC_UNIT))
+        .accounts({
+          market,
+          user: seller.kp.publicKey,
+          userCollateral: seller.collateral,
+          collateralVault: vault,
+          outcomeYesMint: yesMint,
+          outcomeNoMint: noMint,
+          userOutcomeYes: seller.yes,
+          userOutcomeNo: seller.no,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([seller.kp])
+        .rpc();
+    });
+
+    it("Refunds a remainder that would rest crossed when max_iteration stops matching", async () => {
+      await placeSell(seller, 400_000, 400_000);
+      await placeSell(seller, 400_000, 500_000);
+
+      const walletBefore = await getAccount(
+        provider.connection,
+        buyer.collateral,
+      );
+      // One iteration takes the 0.40 ask, the 0.50 ask is still below the 0.60 rest price
+      await program.methods
+        .marketOrder(
+          remainderMarketId,
+          { buy: {} },
+          { yes: {} },
+          new BN(USDC_UNIT),
+          new BN(1),
+          false,
+          new BN(600_000),
+        )
+        .accounts({
+          ...tradeAccounts(buyer),
+          outcomeYesMint: yesMint,
+          outcomeNoMint: noMint,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts([
+          { pubkey: seller.stats, isSigner: false, isWritable: true },
+        ])
+        .signers([buyer.kp])
+        .rpc();
+      const walletAfter = await getAccount(
+        provider.connection,
+        buyer.collateral,
+      );
+
+      // Only the fill is paid, the rest comes back instead of resting
+      assert.equal(
+        Number(walletBefore.amount) - Number(walletAfter.amount),
+        160_000,
+      );
+      const book = await program.account.orderBook.fetch(orderbook);
+      assert.equal(book.yesBuyOrders.length, 0);
+      assert.equal(book.yesSellOrders.length, 1);
+      assert.equal(Number(book.yesSellOrders[0].price), 500_000);
     });
   });
 });